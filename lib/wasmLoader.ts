@@ -5,8 +5,45 @@ export interface CompilationResult {
   success: boolean;
   output: string;
   error?: string;
+  error_category?: 'parse' | 'runtime' | 'unsupported' | 'memory';
+  error_line?: number;
+  error_column?: number;  // Where the failing statement starts on error_line
   needs_input?: string;  // Prompt for input if needed
   state?: string;  // Internal state (for resuming)
+  leaked_blocks?: number;  // malloc'd blocks never freed
+  leaked_bytes?: number;
+  warnings?: string[];  // Diagnostics that did not stop the program
+  exit_code?: number;  // main's return value or exit()'s argument
+}
+
+// A variable's final value, tagged with its interpreter type
+export type DumpedValue =
+  | { type: 'Int' | 'Float' | 'Pointer'; value: number }
+  | { type: 'Char' | 'String'; value: string }
+  | { type: 'Bool'; value: boolean }
+  | { type: 'Array'; value: DumpedValue[] }
+  | { type: 'Struct'; value: Record<string, DumpedValue> };
+
+export interface StateDump extends CompilationResult {
+  variables: Record<string, DumpedValue>;  // main's variables and the globals when the run ended
+}
+
+export interface TraceEntry {
+  statement: string;
+  line: number | null;  // null when the statement's text appears more than once
+  changed: Record<string, DumpedValue>;  // Variables the statement set
+}
+
+export interface ExecutionTrace extends CompilationResult {
+  trace: TraceEntry[];  // Each statement run, in order; loop bodies repeat per iteration
+}
+
+export interface RunOptions {
+  lenient?: boolean;  // Treat undeclared identifiers as 0 with a warning
+  max_iterations?: number;  // Iterations of one loop before it is reported as infinite
+  max_call_depth?: number;  // Nested calls before recursion is reported as runaway
+  max_output_bytes?: number;  // Output the program may write before it is stopped
+  macro_warnings?: boolean;  // Warn when a macro evaluates an argument like i++ more than once
 }
 
 let isInitialized = false;
@@ -68,16 +105,17 @@ export async function initWasm(): Promise<void> {
 /**
  * Compile and run C code
  * @param code - The C source code to compile and execute
+ * @param options - Limits and modes for the run; omitted fields use the defaults
  * @returns CompilationResult object with success status, output, and any errors
  */
-export async function compileAndRunC(code: string): Promise<CompilationResult> {
+export async function compileAndRunC(code: string, options: RunOptions = {}): Promise<CompilationResult> {
   if (!isInitialized || !wasmBindgen) {
     throw new Error('WASM module not initialized. Call initWasm() first.');
   }
 
   try {
     // Call the WASM function
-    const resultJson = wasmBindgen.compile_and_run_c(code);
+    const resultJson = wasmBindgen.compile_and_run_c_with_options(code, '', JSON.stringify(options));
     
     // Parse the JSON result
     const result: CompilationResult = JSON.parse(resultJson);
@@ -93,6 +131,113 @@ export async function compileAndRunC(code: string): Promise<CompilationResult> {
   }
 }
 
+/**
+ * Compile and run C code, then report the final value of every variable
+ * @param code - The C source code to compile and execute
+ * @returns The CompilationResult fields plus the variables when the run ended
+ */
+export async function compileAndDumpState(code: string): Promise<StateDump> {
+  if (!isInitialized || !wasmBindgen) {
+    throw new Error('WASM module not initialized. Call initWasm() first.');
+  }
+
+  try {
+    return JSON.parse(wasmBindgen.compile_and_dump_state(code));
+  } catch (error) {
+    console.error('Compilation error:', error);
+    return {
+      success: false,
+      output: '',
+      error: `Runtime error: ${error}`,
+      variables: {},
+    };
+  }
+}
+
+/**
+ * Compile and run C code, recording every statement it executes
+ * @param code - The C source code to compile and execute
+ * @returns The CompilationResult fields plus the statement trace
+ */
+export async function compileAndTrace(code: string): Promise<ExecutionTrace> {
+  if (!isInitialized || !wasmBindgen) {
+    throw new Error('WASM module not initialized. Call initWasm() first.');
+  }
+
+  try {
+    return JSON.parse(wasmBindgen.compile_and_trace(code));
+  } catch (error) {
+    console.error('Compilation error:', error);
+    return {
+      success: false,
+      output: '',
+      error: `Runtime error: ${error}`,
+      trace: [],
+    };
+  }
+}
+
+export interface ReusableInterpreter {
+  run(code: string, stdin?: string, options?: RunOptions): CompilationResult;
+  free(): void;  // Release the WASM-side interpreter
+}
+
+/**
+ * Create an interpreter that keeps its allocations between runs, for
+ * compiling on every keystroke
+ * @returns An interpreter whose run() behaves like compileAndRunC
+ */
+export function createInterpreter(): ReusableInterpreter {
+  if (!isInitialized || !wasmBindgen) {
+    throw new Error('WASM module not initialized. Call initWasm() first.');
+  }
+
+  const interpreter = new wasmBindgen.Interpreter();
+  return {
+    run(code: string, stdin: string = '', options: RunOptions = {}): CompilationResult {
+      try {
+        return JSON.parse(interpreter.run(code, stdin, JSON.stringify(options)));
+      } catch (error) {
+        console.error('Compilation error:', error);
+        return {
+          success: false,
+          output: '',
+          error: `Runtime error: ${error}`,
+        };
+      }
+    },
+    free(): void {
+      interpreter.free();
+    },
+  };
+}
+
+/**
+ * Compile and run C code with the given standard input
+ * @param code - The C source code to compile and execute
+ * @param stdin - Text the program reads with scanf/gets
+ * @returns CompilationResult object with success status, output, and any errors
+ */
+export async function compileAndRunCWithInput(code: string, stdin: string): Promise<CompilationResult> {
+  if (!isInitialized || !wasmBindgen) {
+    throw new Error('WASM module not initialized. Call initWasm() first.');
+  }
+
+  try {
+    const resultJson = wasmBindgen.compile_and_run_c_with_input(code, stdin);
+    const result: CompilationResult = JSON.parse(resultJson);
+    
+    return result;
+  } catch (error) {
+    console.error('Compilation error:', error);
+    return {
+      success: false,
+      output: '',
+      error: `Runtime error: ${error}`,
+    };
+  }
+}
+
 /**
  * Provide input to a waiting program
  * @param input - The user input string
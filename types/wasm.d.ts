@@ -2,6 +2,16 @@
 
 declare module '/wasm/c_compiler_wasm.js' {
   export function compile_and_run_c(code: string): string;
+  export function compile_and_run_c_with_input(code: string, stdin: string): string;
+  export function compile_and_run_c_with_options(code: string, stdin: string, options: string): string;
+  export function compile_and_run_c_with_limits(code: string, max_iterations: number, max_output_bytes: number): string;
+  export function compile_and_dump_state(code: string): string;
+  export function compile_and_trace(code: string): string;
+  export class Interpreter {
+    constructor();
+    run(code: string, stdin: string, options: string): string;
+    free(): void;
+  }
   export default function init(path: string): Promise<void>;
   export function initSync(module: WebAssembly.Module): void;
 }
@@ -0,0 +1,165 @@
+//! Splits C source into tokens, so scanners can tell a `;` ending a
+//! statement from one inside `";"` and the keyword `do` from `double`
+
+/// What a token is, as far as the interpreter cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Keyword,
+    Identifier,
+    Number,
+    CharLiteral,
+    StringLiteral,
+    Operator,
+    Punctuation, // ( ) { } [ ] ; ,
+    Unknown, // A character no C token starts with, such as `@`
+}
+
+/// One token: its text (literals keep their quotes) and where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    pub(crate) start: usize, // Byte offset in the source
+    pub(crate) line: usize, // 1-based
+    pub(crate) column: usize, // 1-based, in characters
+}
+
+impl Token<'_> {
+    /// Whether this is the punctuation or operator `text`
+    pub(crate) fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenKind::Punctuation | TokenKind::Operator) && self.text == text
+    }
+
+    /// Whether this is the keyword `keyword`
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Keyword && self.text == keyword
+    }
+
+    /// Byte offset just past the token
+    pub(crate) fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+    "extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while",
+    "_Bool",
+];
+
+// Longest first, so `<<=` isn't read as `<<` then `=`
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "...",
+    "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "+", "-", "*", "/", "%", "<", ">", "=", "!", "&", "|", "^", "~", "?", ":", ".",
+];
+
+/// Split `source` into tokens, skipping whitespace and comments. An
+/// unterminated literal or comment runs to the end of the source.
+pub(crate) fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'\n' {
+            line += 1;
+            line_start = i + 1;
+            i += 1;
+            continue;
+        }
+        if byte.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let kind = if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            continue;
+        } else if source[i..].starts_with("/*") {
+            let end = source[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+            for (offset, _) in source[i..end].match_indices('\n') {
+                line += 1;
+                line_start = i + offset + 1;
+            }
+            i = end;
+            continue;
+        } else if byte == b'"' || byte == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != byte && bytes[i] != b'\n' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(bytes.len());
+            if byte == b'"' { TokenKind::StringLiteral } else { TokenKind::CharLiteral }
+        } else if byte.is_ascii_digit() || (byte == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            // Digits, letters and dots, plus the sign of an exponent: 1.5e-3
+            while i < bytes.len() {
+                let exponent_sign = matches!(bytes[i], b'+' | b'-') && matches!(bytes[i - 1], b'e' | b'E')
+                    && !source[start..].starts_with("0x") && !source[start..].starts_with("0X");
+                if !(bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_' || exponent_sign) {
+                    break;
+                }
+                i += 1;
+            }
+            TokenKind::Number
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            if KEYWORDS.contains(&&source[start..i]) { TokenKind::Keyword } else { TokenKind::Identifier }
+        } else if b"(){}[];,".contains(&byte) {
+            i += 1;
+            TokenKind::Punctuation
+        } else if let Some(operator) = OPERATORS.iter().find(|operator| source[i..].starts_with(*operator)) {
+            i += operator.len();
+            TokenKind::Operator
+        } else {
+            i += source[i..].chars().next().map_or(1, char::len_utf8);
+            TokenKind::Unknown
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &source[start..i],
+            start,
+            line,
+            column: source[line_start..start].chars().count() + 1,
+        });
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_and_positions() {
+        let tokens = tokenize("int x = a->b << 2;\n  if (s[0] != '\\'') puts(\"a; b\");");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(texts, [
+            "int", "x", "=", "a", "->", "b", "<<", "2", ";",
+            "if", "(", "s", "[", "0", "]", "!=", "'\\''", ")", "puts", "(", "\"a; b\"", ")", ";",
+        ]);
+        assert_eq!(tokens[0].kind, TokenKind::Keyword);
+        assert_eq!(tokens[1].kind, TokenKind::Identifier);
+        assert_eq!(tokens[16].kind, TokenKind::CharLiteral);
+        assert_eq!(tokens[20].kind, TokenKind::StringLiteral);
+        assert_eq!((tokens[9].line, tokens[9].column, tokens[9].start), (2, 3, 21));
+    }
+
+    #[test]
+    fn test_numbers_comments_and_keyword_prefixes() {
+        let tokens = tokenize("double d = 1.5e-3 + .5f - 0xFE; /* a;\n b */ do_it(); // x;");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(texts, ["double", "d", "=", "1.5e-3", "+", ".5f", "-", "0xFE", ";", "do_it", "(", ")", ";"]);
+        assert_eq!(tokens[9].kind, TokenKind::Identifier);
+        assert_eq!(tokens[9].line, 2);
+    }
+}
@@ -1,11 +1,32 @@
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+mod lexer;
+use lexer::{Token, TokenKind};
+
+/// The largest value rand() returns
+const RAND_MAX: i64 = 32767;
+
+/// String functions that write into their first argument and return it
+const STRING_COPY_FUNCTIONS: &[&str] = &["strcpy", "strcat", "strncpy", "strncat"];
+
+/// String functions that measure or search their first argument by a set
+/// of chars given as the second
+const STRING_SCAN_FUNCTIONS: &[&str] = &["strspn", "strcspn", "strpbrk"];
+
+/// Statements a trace records before it stops, so tracing a long loop
+/// can't exhaust memory
+const MAX_TRACE_ENTRIES: usize = 10_000;
 
 thread_local! {
-    static INTERPRETER_STATE: RefCell<Option<CInterpreter>> = RefCell::new(None);
-    static SOURCE_CODE: RefCell<Option<String>> = RefCell::new(None);
+    static INTERPRETER_STATE: RefCell<Option<CInterpreter>> = const { RefCell::new(None) };
+    static SOURCE_CODE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static RUN_OPTIONS: Cell<RunOptions> = const { Cell::new(RunOptions::DEFAULT) };
+    // All stdin given to the program so far, replayed when it is re-run
+    static PROGRAM_INPUT: RefCell<String> = const { RefCell::new(String::new()) };
 }
 
 #[wasm_bindgen]
@@ -14,15 +35,246 @@ extern "C" {
     fn log(s: &str);
 }
 
+/// Limits and modes for one run, read from a JSON object such as
+/// `{"lenient": true, "max_iterations": 500}`; missing fields keep their
+/// defaults
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunOptions {
+    lenient: bool, // Undeclared identifiers read as 0 with a warning
+    max_iterations: usize, // Iterations of any one loop before it is reported as infinite
+    max_call_depth: usize, // Nested calls before recursion is reported as runaway
+    max_output_bytes: usize, // Output the program may write before it is stopped
+    macro_warnings: bool, // Warn when a macro evaluates a side-effecting argument more than once
+}
+
+impl RunOptions {
+    const DEFAULT: RunOptions = RunOptions {
+        lenient: false,
+        max_iterations: 100000,
+        max_call_depth: 200,
+        max_output_bytes: 4 * 1024 * 1024,
+        macro_warnings: false,
+    };
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions::DEFAULT
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CompilationResult {
     success: bool,
     output: String,
     error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    error_category: Option<String>, // parse, runtime, unsupported or memory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_column: Option<usize>, // Where the failing statement starts on error_line
+    #[serde(skip_serializing_if = "Option::is_none")]
     needs_input: Option<String>, // Prompt message if input is needed
     #[serde(skip_serializing_if = "Option::is_none")]
     state: Option<String>, // Serialized interpreter state for resuming
+    leaked_blocks: usize, // malloc'd blocks never freed by the end of the program
+    leaked_bytes: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>, // Diagnostics that did not stop the program
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i64>, // Set when the program ran to completion
+}
+
+/// A run's result with the variables main's scope held when it ended
+#[derive(Serialize)]
+struct StateDump {
+    #[serde(flatten)]
+    result: CompilationResult,
+    variables: BTreeMap<String, Value>,
+}
+
+/// One statement run in trace mode and the variables it set
+#[derive(Serialize)]
+struct TraceEntry {
+    statement: String,
+    line: Option<usize>, // None when the statement's text appears more than once
+    changed: BTreeMap<String, Value>,
+}
+
+/// A run's result with every statement it executed, in order
+#[derive(Serialize)]
+struct TraceDump {
+    #[serde(flatten)]
+    result: CompilationResult,
+    trace: Vec<TraceEntry>,
+}
+
+impl CompilationResult {
+    /// Build the result of a run from how execution ended; `partial_output`
+    /// is reported alongside an error or an input request
+    fn from_execution(outcome: Result<String, InterpError>, interpreter: &CInterpreter, partial_output: String) -> Self {
+        match outcome {
+            Ok(output) => CompilationResult {
+                success: true,
+                output,
+                error: None,
+                error_category: None,
+                error_line: None,
+                error_column: None,
+                needs_input: None,
+                state: None,
+                leaked_blocks: interpreter.memory.allocations.len(),
+                leaked_bytes: interpreter.memory.allocations.values().sum(),
+                warnings: interpreter.warnings.clone(),
+                exit_code: Some(interpreter.exit_code),
+            },
+            Err(InterpError::InputNeeded(prompt)) => CompilationResult {
+                success: false,
+                output: partial_output,
+                error: None,
+                error_category: None,
+                error_line: None,
+                error_column: None,
+                needs_input: Some(prompt),
+                state: Some("waiting".to_string()),
+                leaked_blocks: 0,
+                leaked_bytes: 0,
+                warnings: interpreter.warnings.clone(),
+                exit_code: None,
+            },
+            Err(error) => CompilationResult {
+                success: false,
+                output: partial_output,
+                error: Some(match error.line() {
+                    Some(line) => format!("line {}: {}", line, error.message()),
+                    None => error.message().to_string(),
+                }),
+                error_category: error.category().map(str::to_string),
+                error_line: error.line(),
+                error_column: error.column(),
+                needs_input: None,
+                state: None,
+                leaked_blocks: 0,
+                leaked_bytes: 0,
+                warnings: interpreter.warnings.clone(),
+                exit_code: None,
+            },
+        }
+    }
+}
+
+/// Why interpretation stopped. Every error carries a message and, when the
+/// statement that failed can be located, its 1-based source line and column
+#[derive(Debug, Clone, PartialEq)]
+enum InterpError {
+    /// The source could not be understood
+    Parse { message: String, line: Option<usize>, column: Option<usize> },
+    /// The program did something invalid while running
+    Runtime { message: String, line: Option<usize>, column: Option<usize> },
+    /// Valid C that the interpreter does not handle
+    Unsupported { message: String, line: Option<usize>, column: Option<usize> },
+    /// Invalid memory access or allocation
+    Memory { message: String, line: Option<usize>, column: Option<usize> },
+    /// Not an error: the program is waiting for stdin, with a prompt
+    InputNeeded(String),
+}
+
+impl InterpError {
+    fn parse(message: impl Into<String>) -> Self {
+        InterpError::Parse { message: message.into(), line: None, column: None }
+    }
+
+    fn runtime(message: impl Into<String>) -> Self {
+        InterpError::Runtime { message: message.into(), line: None, column: None }
+    }
+
+    fn unsupported(message: impl Into<String>) -> Self {
+        InterpError::Unsupported { message: message.into(), line: None, column: None }
+    }
+
+    fn memory(message: impl Into<String>) -> Self {
+        InterpError::Memory { message: message.into(), line: None, column: None }
+    }
+
+    fn category(&self) -> Option<&'static str> {
+        match self {
+            InterpError::Parse { .. } => Some("parse"),
+            InterpError::Runtime { .. } => Some("runtime"),
+            InterpError::Unsupported { .. } => Some("unsupported"),
+            InterpError::Memory { .. } => Some("memory"),
+            InterpError::InputNeeded(_) => None,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            InterpError::Parse { message, .. }
+            | InterpError::Runtime { message, .. }
+            | InterpError::Unsupported { message, .. }
+            | InterpError::Memory { message, .. } => message,
+            InterpError::InputNeeded(prompt) => prompt,
+        }
+    }
+
+    fn line(&self) -> Option<usize> {
+        match self {
+            InterpError::Parse { line, .. }
+            | InterpError::Runtime { line, .. }
+            | InterpError::Unsupported { line, .. }
+            | InterpError::Memory { line, .. } => *line,
+            InterpError::InputNeeded(_) => None,
+        }
+    }
+
+    fn column(&self) -> Option<usize> {
+        match self {
+            InterpError::Parse { column, .. }
+            | InterpError::Runtime { column, .. }
+            | InterpError::Unsupported { column, .. }
+            | InterpError::Memory { column, .. } => *column,
+            InterpError::InputNeeded(_) => None,
+        }
+    }
+
+    /// Attach `line` unless a more precise one was already recorded
+    fn with_line(self, line: usize) -> Self {
+        self.with_position(line, None)
+    }
+
+    /// Attach `line` and `column` unless a more precise position was
+    /// already recorded
+    fn with_position(mut self, line: usize, column: Option<usize>) -> Self {
+        if let InterpError::Parse { line: line_slot @ None, column: column_slot, .. }
+        | InterpError::Runtime { line: line_slot @ None, column: column_slot, .. }
+        | InterpError::Unsupported { line: line_slot @ None, column: column_slot, .. }
+        | InterpError::Memory { line: line_slot @ None, column: column_slot, .. } = &mut self
+        {
+            *line_slot = Some(line);
+            *column_slot = column;
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for InterpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+// Errors built from plain strings are runtime errors
+impl From<String> for InterpError {
+    fn from(message: String) -> Self {
+        InterpError::runtime(message)
+    }
+}
+
+impl From<&str> for InterpError {
+    fn from(message: &str) -> Self {
+        InterpError::runtime(message)
+    }
 }
 
 #[wasm_bindgen(start)]
@@ -34,51 +286,129 @@ pub fn init() {
 /// Returns a JSON string with compilation result
 #[wasm_bindgen]
 pub fn compile_and_run_c(c_code: &str) -> String {
-    // Store source code for potential resume
-    SOURCE_CODE.with(|sc| {
-        *sc.borrow_mut() = Some(c_code.to_string());
-    });
-    
-    let result = match compile_c_code(c_code) {
-        Ok(output) => CompilationResult {
-            success: true,
-            output,
-            error: None,
-            needs_input: None,
-            state: None,
-        },
-        Err(error) => {
-            // Check if this is an input request
-            if error.starts_with("INPUT_NEEDED:") {
-                let prompt = error.strip_prefix("INPUT_NEEDED:").unwrap_or("Enter input");
-                CompilationResult {
-                    success: false,
-                    output: String::new(),
-                    error: None,
-                    needs_input: Some(prompt.to_string()),
-                    state: Some("waiting".to_string()),
-                }
-            } else {
-                CompilationResult {
-                    success: false,
-                    output: String::new(),
-                    error: Some(error),
-                    needs_input: None,
-                    state: None,
-                }
+    compile_and_run_c_with_input(c_code, "")
+}
+
+/// Compile and execute C code with `stdin` as the program's standard
+/// input, e.g. the contents of a textarea in the playground
+#[wasm_bindgen]
+pub fn compile_and_run_c_with_input(c_code: &str, stdin: &str) -> String {
+    run_program(&mut CInterpreter::new(), c_code, stdin, RunOptions::default())
+}
+
+/// Compile and execute C code with stdin and `options`, a JSON object of
+/// `RunOptions` fields such as `{"lenient": true}`
+#[wasm_bindgen]
+pub fn compile_and_run_c_with_options(c_code: &str, stdin: &str, options: &str) -> String {
+    Interpreter::new().run(c_code, stdin, options)
+}
+
+/// Compile and execute C code with its loop iteration and output limits
+/// given directly rather than as JSON; other options keep their defaults
+#[wasm_bindgen]
+pub fn compile_and_run_c_with_limits(c_code: &str, max_iterations: usize, max_output_bytes: usize) -> String {
+    let options = RunOptions { max_iterations, max_output_bytes, ..RunOptions::DEFAULT };
+    run_program(&mut CInterpreter::new(), c_code, "", options)
+}
+
+/// Compile and execute C code, then report the final value of every
+/// variable alongside the result, as a post-mortem view for debugging
+#[wasm_bindgen]
+pub fn compile_and_dump_state(c_code: &str) -> String {
+    let mut interpreter = CInterpreter::new();
+    let result = execute_program(&mut interpreter, c_code, "", RunOptions::default());
+    let dump = StateDump {
+        result,
+        variables: interpreter.variables.into_iter().collect(),
+    };
+    serde_json::to_string(&dump).unwrap_or_else(|_| {
+        r#"{"success":false,"output":"","error":"Failed to serialize result"}"#.to_string()
+    })
+}
+
+/// Compile and execute C code, recording each statement it runs with its
+/// line and the variables it changed; a loop body appears once per iteration
+#[wasm_bindgen]
+pub fn compile_and_trace(c_code: &str) -> String {
+    let mut interpreter = CInterpreter::new();
+    interpreter.trace = Some(Vec::new());
+    let outcome = interpreter.execute(c_code);
+    let partial_output = interpreter.output.clone();
+    let dump = TraceDump {
+        result: CompilationResult::from_execution(outcome, &interpreter, partial_output),
+        trace: interpreter.trace.take().unwrap_or_default(),
+    };
+    serde_json::to_string(&dump).unwrap_or_else(|_| {
+        r#"{"success":false,"output":"","error":"Failed to serialize result"}"#.to_string()
+    })
+}
+
+/// An interpreter kept across runs, so a playground compiling on every
+/// keystroke reuses its tables instead of allocating them each time
+#[wasm_bindgen]
+pub struct Interpreter {
+    interpreter: CInterpreter,
+}
+
+#[wasm_bindgen]
+impl Interpreter {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Interpreter {
+        Interpreter { interpreter: CInterpreter::new() }
+    }
+
+    /// Compile and execute C code like `compile_and_run_c_with_options`,
+    /// starting from a clean state whatever the previous run left behind
+    pub fn run(&mut self, c_code: &str, stdin: &str, options: &str) -> String {
+        match parse_run_options(options) {
+            Ok(options) => run_program(&mut self.interpreter, c_code, stdin, options),
+            Err(error) => {
+                self.interpreter.reset();
+                let result = CompilationResult::from_execution(Err(error), &self.interpreter, String::new());
+                serde_json::to_string(&result).unwrap_or_default()
             }
         }
-    };
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+/// Read `RunOptions` from JSON; an empty string means the defaults
+fn parse_run_options(options: &str) -> Result<RunOptions, InterpError> {
+    if options.trim().is_empty() {
+        return Ok(RunOptions::default());
+    }
+    serde_json::from_str(options).map_err(|error| InterpError::parse(format!("Invalid run options: {}", error)))
+}
 
+fn run_program(interpreter: &mut CInterpreter, c_code: &str, stdin: &str, options: RunOptions) -> String {
+    let result = execute_program(interpreter, c_code, stdin, options);
     serde_json::to_string(&result).unwrap_or_else(|_| {
         r#"{"success":false,"output":"","error":"Failed to serialize result"}"#.to_string()
     })
 }
 
-/// Execution result that can signal need for input
-enum ExecutionResult {
-    Complete(String),
-    NeedsInput { prompt: String, format_spec: String },
+/// Run `c_code` from a clean state and report how it ended
+fn execute_program(interpreter: &mut CInterpreter, c_code: &str, stdin: &str, options: RunOptions) -> CompilationResult {
+    // Store source code, input and options for potential resume
+    SOURCE_CODE.with(|sc| {
+        *sc.borrow_mut() = Some(c_code.to_string());
+    });
+    PROGRAM_INPUT.with(|pi| {
+        *pi.borrow_mut() = stdin.to_string();
+    });
+    RUN_OPTIONS.with(|run_options| run_options.set(options));
+    
+    interpreter.reset();
+    interpreter.options = options;
+    interpreter.input.push_str(stdin);
+    let outcome = interpreter.execute(c_code);
+    let partial_output = interpreter.output.clone();
+    CompilationResult::from_execution(outcome, interpreter, partial_output)
 }
 
 /// Provide input to a waiting program
@@ -93,48 +423,36 @@ pub fn provide_input(input: &str) -> String {
             success: false,
             output: String::new(),
             error: Some("No program waiting for input".to_string()),
+            error_category: None,
+            error_line: None,
+            error_column: None,
             needs_input: None,
             state: None,
+            leaked_blocks: 0,
+            leaked_bytes: 0,
+            warnings: Vec::new(),
+            exit_code: None,
         }).unwrap();
     }
     
     let code = code.unwrap();
     
-    // Create interpreter with input buffer
+    // The program runs again from the start, so it gets all earlier input
+    // followed by the new line the user submitted
+    let stdin = PROGRAM_INPUT.with(|pi| {
+        let mut pi = pi.borrow_mut();
+        pi.push_str(input);
+        pi.push('\n');
+        pi.clone()
+    });
     let mut interpreter = CInterpreter::new();
-    interpreter.input_buffer.push(input.to_string());
+    interpreter.options = RUN_OPTIONS.with(Cell::get);
+    interpreter.input.push_str(&stdin);
     
     // Try to execute again
-    let result = match interpreter.execute(&code) {
-        Ok(output) => CompilationResult {
-            success: true,
-            output,
-            error: None,
-            needs_input: None,
-            state: None,
-        },
-        Err(error) => {
-            // Check if we need more input
-            if error.starts_with("INPUT_NEEDED:") {
-                let prompt = error.strip_prefix("INPUT_NEEDED:").unwrap_or("Enter input");
-                CompilationResult {
-                    success: false,
-                    output: interpreter.output.clone(),
-                    error: None,
-                    needs_input: Some(prompt.to_string()),
-                    state: Some("waiting".to_string()),
-                }
-            } else {
-                CompilationResult {
-                    success: false,
-                    output: interpreter.output.clone(),
-                    error: Some(error),
-                    needs_input: None,
-                    state: None,
-                }
-            }
-        }
-    };
+    let outcome = interpreter.execute(&code);
+    let partial_output = interpreter.output.clone();
+    let result = CompilationResult::from_execution(outcome, &interpreter, partial_output);
     
     // Clear source if complete
     if result.needs_input.is_none() {
@@ -148,14 +466,9 @@ pub fn provide_input(input: &str) -> String {
     })
 }
 
-/// Simple C interpreter for basic C programs
-fn compile_c_code(code: &str) -> Result<String, String> {
-    // Parse and execute the C code
-    let mut interpreter = CInterpreter::new();
-    interpreter.execute(code)
-}
-
-#[derive(Clone, Debug)]
+// Serialized as {"type": "Int", "value": 5} for the state dump and trace
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
 enum Value {
     Int(i64),
     Float(f64),
@@ -164,21 +477,169 @@ enum Value {
     Bool(bool),
     Array(Vec<Value>),
     Pointer(i64), // Simulated memory address
+    Struct(HashMap<String, Value>), // Member name -> value
+}
+
+// One step of a postfix expression chain like `list->items[i].name`
+#[derive(Debug)]
+enum Postfix<'a> {
+    Index(&'a str),  // [expr]
+    Member(&'a str), // .name
+    Arrow(&'a str),  // ->name
+}
+
+// One step of a store through a postfix chain, its subscript evaluated
+#[derive(Debug)]
+enum Step<'a> {
+    Index(i64),
+    Member(&'a str),
+}
+
+// A location that can be stored to, its subscripts already evaluated, so a
+// read-modify-write such as `b[i++]++` runs their side effects once
+#[derive(Debug)]
+enum Place<'a> {
+    Variable(&'a str),
+    Element(&'a str, Vec<Step<'a>>), // A path into an array or struct variable
+    Address(i64), // What a pointer points at: *p or p[i]
+    Pointee(i64, String, Vec<Step<'a>>), // A path into the struct of this type at the address, past a ->
+}
+
+// One statement of a cached body
+struct CachedStatement {
+    offset: usize, // Byte offset in the body, to locate it in the source
+    text: String,
+    folded: Option<String>, // The text with its constant subexpressions evaluated, if it had any
 }
 
+type Statements = Rc<Vec<CachedStatement>>;
+
 #[derive(Clone, Debug)]
 struct Function {
-    params: Vec<(String, String)>, // (type, name)
-    body: String,
+    params: Vec<(String, String)>, // (type, name); pointer and array params end in * or []
+    body: Rc<str>, // Shared, so the copy taken per call keeps the address its statements are cached under
     return_type: String,
 }
 
+// One member of a struct definition
+#[derive(Clone, Debug)]
+struct StructMember {
+    name: String,
+    type_name: String, // Canonical type, `struct Tag`, or a pointer type ending in *
+    dims: Vec<usize>, // Array dimensions, empty for a scalar member
+}
+
+// A printf length modifier: how wide an integer %d, %u or %x reads
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Length {
+    Char,  // hh
+    Short, // h
+    Int,   // none, or the L of %Lf
+    Long,  // l, ll, z, j or t
+}
+
+// A caller's local state, set aside while a called function runs
+struct Frame {
+    variables: HashMap<String, Value>,
+    address_map: HashMap<String, i64>,
+    pointee_types: HashMap<String, String>,
+    variable_types: HashMap<String, String>,
+    constants: HashSet<String>,
+}
+
+// A #define: `params` is Some for function-like macros such as MAX(a, b)
+#[derive(Clone, Debug)]
+struct Macro {
+    params: Option<Vec<String>>,
+    body: String,
+}
+
+/// The program's stdin and a read position shared by scanf, getchar, gets
+/// and ungetc, so each sees exactly what the previous read left behind
+#[derive(Default)]
+struct InputCursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl InputCursor {
+    fn push_str(&mut self, text: &str) {
+        self.chars.extend(text.chars());
+    }
+
+    fn clear(&mut self) {
+        self.chars.clear();
+        self.pos = 0;
+    }
+
+    /// Whether every character has been read
+    fn is_empty(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += 1;
+        Some(ch)
+    }
+
+    /// Step back over the last `count` characters read
+    fn rewind(&mut self, count: usize) {
+        self.pos = self.pos.saturating_sub(count);
+    }
+
+    /// Make `ch` the next character read, as ungetc does
+    fn unread(&mut self, ch: char) {
+        if self.pos > 0 {
+            self.pos -= 1;
+            self.chars[self.pos] = ch;
+        } else {
+            self.chars.insert(0, ch);
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume the longest run of input accepted by `accept`, which is told
+    /// whether it is looking at the first character of the token
+    fn take_while(&mut self, accept: impl Fn(char, bool) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| accept(c, self.pos == start)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Consume up to and including the next newline, which is not returned
+    fn read_line(&mut self) -> String {
+        let line = self.take_while(|c, _| c != '\n');
+        self.next_char();
+        line
+    }
+}
+
 // Simulated memory system for pointers
 struct Memory {
     heap: HashMap<i64, Value>,
     next_address: i64,
     // Map variable names to their simulated addresses
     address_map: HashMap<String, i64>,
+    // Live malloc'd blocks: base address -> requested size in bytes
+    allocations: HashMap<i64, usize>,
+    // Base addresses of freed blocks; addresses are never reused
+    freed: HashSet<i64>,
+    // Address ranges [start, end) holding string literals, which are read-only
+    read_only: Vec<(i64, i64)>,
+    // Base address of each string literal by its text, laid out only once
+    literals: HashMap<String, i64>,
 }
 
 impl Memory {
@@ -187,9 +648,24 @@ impl Memory {
             heap: HashMap::new(),
             next_address: 0x1000, // Start at a "realistic" address
             address_map: HashMap::new(),
+            allocations: HashMap::new(),
+            freed: HashSet::new(),
+            read_only: Vec::new(),
+            literals: HashMap::new(),
         }
     }
 
+    /// Forget every variable and block, keeping the tables' capacity
+    fn clear(&mut self) {
+        self.heap.clear();
+        self.next_address = 0x1000;
+        self.address_map.clear();
+        self.allocations.clear();
+        self.freed.clear();
+        self.read_only.clear();
+        self.literals.clear();
+    }
+
     fn allocate(&mut self, value: Value) -> i64 {
         let addr = self.next_address;
         self.heap.insert(addr, value);
@@ -197,6 +673,63 @@ impl Memory {
         addr
     }
 
+    /// Reserve a heap block of `size` bytes. Elements occupy one 8-byte slot
+    /// each whatever their type, so a block spans one slot per byte requested
+    /// and slots are created lazily on first write.
+    fn malloc(&mut self, size: usize) -> i64 {
+        let addr = self.next_address;
+        self.allocations.insert(addr, size);
+        self.next_address += 8 * size.max(1) as i64;
+        addr
+    }
+
+    /// Release a malloc'd block. free(NULL) does nothing.
+    fn free(&mut self, addr: i64) -> Result<(), InterpError> {
+        if addr == 0 {
+            return Ok(());
+        }
+        if self.freed.contains(&addr) {
+            return Err(InterpError::memory(format!("free(): double free detected at 0x{:x}", addr)));
+        }
+        let size = self.allocations.remove(&addr)
+            .ok_or_else(|| InterpError::memory(format!("free(): invalid pointer 0x{:x}", addr)))?;
+        self.freed.insert(addr);
+        let end = addr + 8 * size as i64;
+        self.heap.retain(|&slot, _| slot < addr || slot >= end);
+        Ok(())
+    }
+
+    /// calloc: a malloc'd block whose first `count` slots hold `zero`
+    fn calloc(&mut self, count: usize, size: usize, zero: Value) -> i64 {
+        let addr = self.malloc(count * size);
+        for i in 0..count {
+            self.heap.insert(addr + 8 * i as i64, zero.clone());
+        }
+        addr
+    }
+
+    /// Lay out a string literal as consecutive read-only chars ending in
+    /// '\0', or find where the same text already was, as the literal has
+    /// static storage however often it is evaluated
+    fn allocate_string_literal(&mut self, literal: &str) -> i64 {
+        if let Some(&base) = self.literals.get(literal) {
+            return base;
+        }
+        let base = self.next_address;
+        for ch in literal.chars().chain(std::iter::once('\0')) {
+            self.allocate(Value::Char(ch));
+        }
+        self.read_only.push((base, self.next_address));
+        self.literals.insert(literal.to_string(), base);
+        base
+    }
+
+    /// Whether `addr` falls inside a live malloc'd block
+    fn is_allocated(&self, addr: i64) -> bool {
+        self.allocations.iter()
+            .any(|(&base, &size)| addr >= base && addr < base + 8 * size as i64)
+    }
+
     fn get_address_of(&mut self, var_name: &str, value: &Value) -> i64 {
         if let Some(&addr) = self.address_map.get(var_name) {
             addr
@@ -204,24 +737,29 @@ impl Memory {
             let addr = self.next_address;
             self.heap.insert(addr, value.clone());
             self.address_map.insert(var_name.to_string(), addr);
-            self.next_address += 8;
+            self.next_address += 8 * scalar_count(value) as i64;
             addr
         }
     }
 
-    fn read(&self, addr: i64) -> Result<Value, String> {
-        self.heap.get(&addr)
-            .cloned()
-            .ok_or_else(|| format!("Segmentation fault: invalid memory address 0x{:x}", addr))
+    fn read(&self, addr: i64) -> Result<Value, InterpError> {
+        match self.heap.get(&addr) {
+            Some(value) => Ok(value.clone()),
+            // Unwritten malloc'd memory reads back as zero
+            None if self.is_allocated(addr) => Ok(Value::Int(0)),
+            None => Err(InterpError::memory(format!("Segmentation fault: invalid memory address 0x{:x}", addr))),
+        }
     }
 
-    fn write(&mut self, addr: i64, value: Value) -> Result<(), String> {
-        if self.heap.contains_key(&addr) {
-            self.heap.insert(addr, value);
-            Ok(())
-        } else {
-            Err(format!("Segmentation fault: invalid memory address 0x{:x}", addr))
+    fn write(&mut self, addr: i64, value: Value) -> Result<(), InterpError> {
+        if self.read_only.iter().any(|&(start, end)| addr >= start && addr < end) {
+            return Err(InterpError::memory(format!("Segmentation fault: write to read-only string literal at 0x{:x}", addr)));
         }
+        if !self.heap.contains_key(&addr) && !self.is_allocated(addr) {
+            return Err(InterpError::memory(format!("Segmentation fault: invalid memory address 0x{:x}", addr)));
+        }
+        self.heap.insert(addr, value);
+        Ok(())
     }
 
     fn update_variable_address(&mut self, var_name: &str, value: &Value) {
@@ -231,1728 +769,8401 @@ impl Memory {
     }
 }
 
-struct CInterpreter {
-    variables: HashMap<String, Value>,
-    global_variables: HashMap<String, Value>,
-    functions: HashMap<String, Function>,
-    output: String,
-    input_buffer: Vec<String>,
-    loop_break: bool,
-    loop_continue: bool,
-    memory: Memory,
+/// The number of scalar elements in `value`, counting through nested arrays
+fn scalar_count(value: &Value) -> usize {
+    match value {
+        Value::Array(elements) => elements.iter().map(scalar_count).sum(),
+        _ => 1,
+    }
 }
 
-impl CInterpreter {
-    fn new() -> Self {
-        CInterpreter {
-            variables: HashMap::new(),
-            global_variables: HashMap::new(),
-            functions: HashMap::new(),
-            output: String::new(),
-            input_buffer: Vec::new(),
-            loop_break: false,
-            loop_continue: false,
-            memory: Memory::new(),
+/// The `index`th scalar of `value` in memory order, counting through nested arrays
+fn scalar_at(value: &Value, mut index: usize) -> Option<&Value> {
+    match value {
+        Value::Array(elements) => {
+            for element in elements {
+                let count = scalar_count(element);
+                if index < count {
+                    return scalar_at(element, index);
+                }
+                index -= count;
+            }
+            None
         }
+        scalar => (index == 0).then_some(scalar),
     }
+}
 
-    fn execute(&mut self, code: &str) -> Result<String, String> {
-        // Parse global variables and functions
-        self.parse_globals_and_functions(code)?;
-        
-        // Find main function
-        let main_start = code.find("int main").or(code.find("void main"))
-            .ok_or_else(|| "Error: No main function found".to_string())?;
-        
-        let code = &code[main_start..];
-        
-        // Find the body of main function
-        let body_start = code.find('{')
-            .ok_or_else(|| "Error: Invalid main function syntax".to_string())?;
-        
-        let body_end = self.find_matching_brace(code, body_start)
-            .ok_or_else(|| "Error: Unmatched braces in main function".to_string())?;
-        
-        let body = &code[body_start + 1..body_end];
-        
-        // Execute statements in the body
-        self.execute_statements(body)?;
-        
-        Ok(self.output.clone())
+/// Mutable form of `scalar_at`
+fn scalar_at_mut(value: &mut Value, mut index: usize) -> Option<&mut Value> {
+    match value {
+        Value::Array(elements) => {
+            for element in elements {
+                let count = scalar_count(element);
+                if index < count {
+                    return scalar_at_mut(element, index);
+                }
+                index -= count;
+            }
+            None
+        }
+        scalar => (index == 0).then_some(scalar),
     }
+}
 
-    fn parse_globals_and_functions(&mut self, _code: &str) -> Result<(), String> {
-        // This is a simplified parser - just acknowledges functions exist
-        // In a real implementation, you would parse function definitions here
-        Ok(())
-    }
-
-    fn find_matching_brace(&self, code: &str, start: usize) -> Option<usize> {
-        let chars: Vec<char> = code.chars().collect();
-        let mut depth = 0;
-        
-        for i in start..chars.len() {
-            match chars[i] {
-                '{' => depth += 1,
-                '}' => {
-                    depth -= 1;
-                    if depth == 0 {
-                        return Some(i);
-                    }
-                }
-                _ => {}
+/// Byte position of the `close` that balances the `open` at or after
+/// `start`, skipping string and char literals such as "}" or ')'
+fn find_matching_delimiter(code: &str, start: usize, open: u8, close: u8) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut depth = 0;
+    let mut quote: Option<u8> = None;
+    let mut i = start;
+    
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if let Some(q) = quote {
+            if byte == b'\\' {
+                i += 1;
+            } else if byte == q {
+                quote = None;
+            }
+        } else if byte == b'"' || byte == b'\'' {
+            quote = Some(byte);
+        } else if byte == open {
+            depth += 1;
+        } else if byte == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
             }
         }
-        None
+        i += 1;
     }
+    None
+}
 
-    fn find_matching_paren(&self, code: &str, start: usize) -> Option<usize> {
-        let chars: Vec<char> = code.chars().collect();
-        let mut depth = 0;
-        
-        for i in start..chars.len() {
-            match chars[i] {
-                '(' => depth += 1,
-                ')' => {
-                    depth -= 1;
-                    if depth == 0 {
-                        return Some(i);
-                    }
-                }
-                _ => {}
+/// Index of the token that closes the bracket at `tokens[open]`
+fn matching_token(tokens: &[Token], open: usize) -> Option<usize> {
+    let close = match tokens.get(open)?.text {
+        "(" => ")",
+        "[" => "]",
+        "{" => "}",
+        _ => return None,
+    };
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if token.is(tokens[open].text) {
+            depth += 1;
+        } else if token.is(close) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
             }
         }
-        None
     }
+    None
+}
 
-    fn execute_statements(&mut self, body: &str) -> Result<(), String> {
-        let body = body.trim();
-        if body.is_empty() {
-            return Ok(());
+/// Index of the last token of the statement that starts at `tokens[first]`.
+/// An `if` takes the `else` that follows its body, so in
+/// `if (a) if (b) x(); else y();` the else belongs to the inner if.
+fn statement_end(tokens: &[Token], first: usize) -> Option<usize> {
+    let token = tokens.get(first)?;
+    if token.is("{") {
+        return matching_token(tokens, first);
+    }
+    if ["if", "for", "while", "switch"].iter().any(|keyword| token.is_keyword(keyword)) {
+        let body_end = statement_end(tokens, matching_token(tokens, first + 1)? + 1)?;
+        if token.is_keyword("if") && tokens.get(body_end + 1).is_some_and(|next| next.is_keyword("else")) {
+            return statement_end(tokens, body_end + 2);
         }
-
-        // Handle complex structures first
-        if body.contains("for") {
-            return self.handle_for_loop(body);
+        return Some(body_end);
+    }
+    if token.is_keyword("do") {
+        let body_end = statement_end(tokens, first + 1)?;
+        let condition_end = matching_token(tokens, body_end + 2)?;
+        return tokens.get(condition_end + 1).filter(|next| next.is(";")).map(|_| condition_end + 1);
+    }
+    
+    // Anything else runs to the next `;` outside brackets, as in
+    // `int a[] = {1, 2};`
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(first) {
+        if token.kind != TokenKind::Punctuation {
+            continue;
         }
-
-        if body.contains("while") {
-            return self.handle_while_loop(body);
+        match token.text {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" if depth == 0 => return None,
+            ")" | "]" | "}" => depth -= 1,
+            ";" if depth == 0 => return Some(index),
+            _ => {}
         }
+    }
+    None
+}
 
-        if body.contains("do") && body.contains("while") {
-            return self.handle_do_while_loop(body);
-        }
+/// Every scalar of a possibly nested array, in memory order
+fn flatten_scalars(elements: &[Value]) -> Vec<Value> {
+    elements.iter()
+        .flat_map(|element| match element {
+            Value::Array(inner) => flatten_scalars(inner),
+            scalar => vec![scalar.clone()],
+        })
+        .collect()
+}
 
-        if body.contains("if") {
-            return self.handle_if_else_statement(body);
+/// Overwrite the scalars of a possibly nested array in memory order with
+/// `values` until they run out
+fn fill_scalars(elements: &mut [Value], values: &mut impl Iterator<Item = Value>) {
+    for element in elements {
+        match element {
+            Value::Array(inner) => fill_scalars(inner, values),
+            scalar => match values.next() {
+                Some(value) => *scalar = value,
+                None => return,
+            },
         }
+    }
+}
 
-        if body.contains("switch") {
-            return self.handle_switch_statement(body);
-        }
+/// `index` as a position in an array of `len` elements, or an error when it
+/// is out of bounds
+fn checked_index(index: i64, len: usize) -> Result<usize, InterpError> {
+    usize::try_from(index).ok().filter(|&i| i < len)
+        .ok_or_else(|| format!("Error: array index {} out of bounds (size {})", index, len).into())
+}
 
-        let statements: Vec<String> = self.split_statements(body).iter().map(|s| s.to_string()).collect();
+/// `left op right` for one of + - * / %, wrapping on overflow as the
+/// 64-bit values would; dividing by zero, or the most negative value by
+/// -1, is an error
+fn arithmetic(left: i64, op: char, right: i64) -> Result<i64, InterpError> {
+    let quotient = match op {
+        '+' => return Ok(left.wrapping_add(right)),
+        '-' => return Ok(left.wrapping_sub(right)),
+        '*' => return Ok(left.wrapping_mul(right)),
+        _ if right == 0 => return Err(if op == '/' { "Error: Division by zero" } else { "Error: Modulo by zero" }.into()),
+        '/' => left.checked_div(right),
+        _ => left.checked_rem(right),
+    };
+    quotient.ok_or_else(|| format!("Error: {} {} {} overflows", left, op, right).into())
+}
 
-        for statement in statements {
-            if self.loop_break || self.loop_continue {
-                break;
-            }
-            self.execute_statement(&statement)?;
+/// `value` as %e, %E, %g or %G print it. %e keeps `precision` decimals
+/// and an exponent of at least two digits; %g keeps `precision`
+/// significant digits, printing as %f unless the exponent is below -4 or
+/// not below the precision, and drops trailing zeros.
+fn format_exponential(value: f64, conversion: char, precision: Option<usize>) -> String {
+    // The mantissa and exponent with `decimals` digits after the point
+    let split = |decimals: usize| {
+        let text = format!("{:.*e}", decimals, value);
+        let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+        (mantissa.to_string(), exponent.parse::<i32>().unwrap_or(0))
+    };
+    let join = |(mantissa, exponent): (String, i32)| {
+        format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.unsigned_abs())
+    };
+    let strip_zeros = |digits: String| match digits.contains('.') {
+        true => digits.trim_end_matches('0').trim_end_matches('.').to_string(),
+        false => digits,
+    };
+    
+    let text = if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value < 0.0 { "-inf" } else { "inf" }.to_string()
+    } else if matches!(conversion, 'e' | 'E') {
+        join(split(precision.unwrap_or(6)))
+    } else {
+        let precision = precision.unwrap_or(6).max(1);
+        let (mantissa, exponent) = split(precision - 1);
+        if exponent < -4 || exponent >= precision as i32 {
+            join((strip_zeros(mantissa), exponent))
+        } else {
+            strip_zeros(format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value))
         }
+    };
+    if conversion.is_ascii_uppercase() { text.to_uppercase() } else { text }
+}
 
-        Ok(())
-    }
-
-    fn split_statements<'a>(&self, body: &'a str) -> Vec<&'a str> {
-        let mut statements = Vec::new();
-        let mut current_start = 0;
-        let mut brace_depth = 0;
-        let mut paren_depth = 0;
-        let mut in_string = false;
-        let chars: Vec<char> = body.chars().collect();
+/// `left << count` or `left >> count` for `op`, or an error for a negative
+/// count or one past the width, which C leaves undefined
+fn shift(left: i64, op: &str, count: i64) -> Result<i64, InterpError> {
+    u32::try_from(count).ok()
+        .and_then(|count| if op.starts_with("<<") { left.checked_shl(count) } else { left.checked_shr(count) })
+        .ok_or_else(|| format!("Error: shift count {} is negative or too large", count).into())
+}
 
-        for i in 0..chars.len() {
-            match chars[i] {
-                '"' if i == 0 || chars[i - 1] != '\\' => in_string = !in_string,
-                '{' if !in_string => brace_depth += 1,
-                '}' if !in_string => brace_depth -= 1,
-                '(' if !in_string => paren_depth += 1,
-                ')' if !in_string => paren_depth -= 1,
-                ';' if !in_string && brace_depth == 0 && paren_depth == 0 => {
-                    let stmt = body[current_start..=i].trim();
-                    if !stmt.is_empty() {
-                        statements.push(stmt);
-                    }
-                    current_start = i + 1;
-                }
-                _ => {}
+/// Store `value` in `slot` at `path`, one index per array dimension and a
+/// name per struct member; `name` is the stored-to variable or expression
+fn store_at_path(mut slot: &mut Value, name: &str, path: &[Step], value: Value) -> Result<(), InterpError> {
+    for (depth, step) in path.iter().enumerate() {
+        slot = match (step, slot) {
+            (Step::Index(index), Value::Array(elements)) => {
+                let index = checked_index(*index, elements.len())?;
+                &mut elements[index]
             }
-        }
-
-        let remaining = body[current_start..].trim();
-        if !remaining.is_empty() {
-            statements.push(remaining);
-        }
-
-        statements
+            (Step::Index(_), _) if depth == 0 => {
+                return Err(format!("Error: subscripted value '{}' is not an array", name).into());
+            }
+            (Step::Index(_), _) => {
+                return Err(format!("Error: subscripted value '{}' has too many dimensions", name).into());
+            }
+            (Step::Member(member), Value::Struct(fields)) => fields.get_mut(*member)
+                .ok_or_else(|| format!("Error: struct has no member named '{}'", member))?,
+            (Step::Member(member), _) => {
+                return Err(InterpError::unsupported(format!("Error: request for member '{}' in something not a structure", member)));
+            }
+        };
     }
+    *slot = value;
+    Ok(())
+}
 
-    fn execute_statement(&mut self, statement: &str) -> Result<(), String> {
-        let statement = statement.trim().trim_end_matches(';');
-        
-        if statement.is_empty() {
-            return Ok(());
-        }
-
-        // Handle break and continue
-        if statement == "break" {
-            self.loop_break = true;
-            return Ok(());
-        }
+/// The value at `path` in `value`, failing as `store_at_path` would
+fn load_at_path(mut value: &Value, name: &str, path: &[Step]) -> Result<Value, InterpError> {
+    for (depth, step) in path.iter().enumerate() {
+        value = match (step, value) {
+            (Step::Index(index), Value::Array(elements)) => &elements[checked_index(*index, elements.len())?],
+            (Step::Index(_), _) if depth == 0 => {
+                return Err(format!("Error: subscripted value '{}' is not an array", name).into());
+            }
+            (Step::Index(_), _) => {
+                return Err(format!("Error: subscripted value '{}' has too many dimensions", name).into());
+            }
+            (Step::Member(member), Value::Struct(fields)) => fields.get(*member)
+                .ok_or_else(|| format!("Error: struct has no member named '{}'", member))?,
+            (Step::Member(member), _) => {
+                return Err(InterpError::unsupported(format!("Error: request for member '{}' in something not a structure", member)));
+            }
+        };
+    }
+    Ok(value.clone())
+}
 
-        if statement == "continue" {
-            self.loop_continue = true;
-            return Ok(());
-        }
+/// The C string held in a char array: its characters up to the first '\0'
+fn c_string(chars: &[Value]) -> String {
+    chars.iter()
+        .map_while(|value| match value {
+            Value::Char(c) if *c != '\0' => Some(*c),
+            _ => None,
+        })
+        .collect()
+}
 
-        // Handle printf statements
-        if statement.contains("printf") {
-            return self.handle_printf(statement);
-        }
+/// The value of an integer literal: decimal, hex `0xFF`, binary `0b1010`
+/// or octal `017`, with any `U` or `L` suffix, as in `42UL`
+fn parse_integer_literal(literal: &str) -> Option<i64> {
+    let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        (binary, 2)
+    } else if let Some(octal) = digits.strip_prefix('0').filter(|octal| !octal.is_empty()) {
+        (octal, 8)
+    } else {
+        return digits.parse().ok();
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    // 0xFFFFFFFFFFFFFFFF wraps like an unsigned long stored in a long
+    u64::from_str_radix(digits, radix).ok().map(|value| value as i64)
+}
 
-        // Handle scanf statements
-        if statement.contains("scanf") {
-            return self.handle_scanf(statement);
+/// The character a char literal such as 'a', '\n', '\x41' or '\101' denotes
+fn parse_char_literal(literal: &str) -> Result<char, InterpError> {
+    let invalid = || InterpError::parse(format!("Error: invalid character constant {}", literal));
+    let inner = literal.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')).ok_or_else(invalid)?;
+    let Some(escape) = inner.strip_prefix('\\') else {
+        let mut chars = inner.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(invalid()),
+        };
+    };
+    
+    let code = if let Some(hex) = escape.strip_prefix('x') {
+        u8::from_str_radix(hex, 16).map_err(|_| invalid())?
+    } else if escape.starts_with(|c: char| c.is_digit(8)) {
+        if escape.len() > 3 {
+            return Err(invalid());
         }
-
-        // Handle puts statement
-        if statement.contains("puts") {
-            return self.handle_puts(statement);
+        u8::from_str_radix(escape, 8).map_err(|_| invalid())?
+    } else {
+        match escape {
+            "n" => b'\n',
+            "t" => b'\t',
+            "r" => b'\r',
+            "a" => 0x07,
+            "b" => 0x08,
+            "f" => 0x0c,
+            "v" => 0x0b,
+            "\\" | "'" | "\"" | "?" => escape.as_bytes()[0],
+            _ => return Err(invalid()),
         }
+    };
+    Ok(code as char)
+}
 
-        // Handle gets statement
-        if statement.contains("gets") {
-            return self.handle_gets(statement);
-        }
+struct CInterpreter {
+    source: String, // Preprocessed program, used to locate failing statements
+    variables: HashMap<String, Value>,
+    // File-scope variables, kept in sync with the frame that last ran
+    global_variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+    call_stack: Vec<Frame>, // Callers of the running function, innermost last
+    output: String,
+    warnings: Vec<String>,
+    options: RunOptions,
+    input: InputCursor, // stdin, consumed one character at a time
+    loop_break: bool,
+    loop_continue: bool,
+    returning: bool, // Set by return/exit, halts every enclosing block
+    return_value: Option<Value>, // Value of the last `return expr;`
+    exiting: bool, // Set by exit(), keeps `returning` set through every caller
+    exit_code: i64, // main's return value or exit()'s argument
+    rand_state: u32, // rand()'s generator state, set by srand
+    memory: Memory,
+    pointee_types: HashMap<String, String>, // Pointer variable -> element type it points at
+    variable_types: HashMap<String, String>, // Scalar or array variable -> declared (element) type
+    constants: HashSet<String>, // Variables declared const
+    macros: HashMap<String, Macro>, // #define name -> its parameters and replacement
+    structs: HashMap<String, Vec<StructMember>>, // struct tag -> its members in declaration order
+    typedefs: HashMap<String, String>, // typedef name -> the canonical type it stands for
+    // A block body's address and length -> its split statements, so a body
+    // executed many times (loop bodies, function bodies) is only parsed once.
+    // Bodies are slices of the run's source, a function's body or a cached
+    // statement, all of which live until reset, so the address identifies
+    // the body without rereading its text
+    statement_cache: HashMap<(usize, usize), Statements>,
+    trace: Option<Vec<TraceEntry>>, // Statements executed so far, when tracing
+}
 
-        // Handle string functions
-        if statement.contains("strlen") {
-            return self.handle_strlen(statement);
+impl CInterpreter {
+    fn new() -> Self {
+        CInterpreter {
+            source: String::new(),
+            variables: HashMap::new(),
+            global_variables: HashMap::new(),
+            functions: HashMap::new(),
+            call_stack: Vec::new(),
+            output: String::new(),
+            warnings: Vec::new(),
+            options: RunOptions::default(),
+            input: InputCursor::default(),
+            loop_break: false,
+            loop_continue: false,
+            returning: false,
+            return_value: None,
+            exiting: false,
+            exit_code: 0,
+            rand_state: 1,
+            memory: Memory::new(),
+            pointee_types: HashMap::new(),
+            variable_types: HashMap::new(),
+            constants: HashSet::new(),
+            macros: HashMap::new(),
+            structs: HashMap::new(),
+            typedefs: HashMap::new(),
+            statement_cache: HashMap::new(),
+            trace: None,
         }
+    }
 
-        if statement.contains("strcpy") {
-            return self.handle_strcpy(statement);
-        }
+    /// Return to the state of a new interpreter, keeping the capacity of its
+    /// tables so the next run doesn't allocate them again
+    fn reset(&mut self) {
+        self.source.clear();
+        self.variables.clear();
+        self.global_variables.clear();
+        self.functions.clear();
+        self.call_stack.clear();
+        self.output.clear();
+        self.warnings.clear();
+        self.options = RunOptions::default();
+        self.input.clear();
+        self.loop_break = false;
+        self.loop_continue = false;
+        self.returning = false;
+        self.return_value = None;
+        self.exiting = false;
+        self.exit_code = 0;
+        self.rand_state = 1;
+        self.memory.clear();
+        self.pointee_types.clear();
+        self.variable_types.clear();
+        self.constants.clear();
+        self.macros.clear();
+        self.structs.clear();
+        self.typedefs.clear();
+        self.statement_cache.clear();
+        self.trace = None;
+    }
 
-        if statement.contains("strcmp") {
-            return self.handle_strcmp(statement);
+    fn execute(&mut self, code: &str) -> Result<String, InterpError> {
+        let code = &Self::strip_comments(code)?;
+        let code = &self.preprocess(code)?;
+        self.source = code.clone();
+        
+        // Parse global variables and functions
+        self.parse_globals_and_functions(code)?;
+        
+        // Find main function
+        let main_start = code.find("int main").or(code.find("void main"))
+            .ok_or_else(|| InterpError::parse("Error: No main function found"))?;
+        
+        let code = &code[main_start..];
+        
+        // Find the body of main function
+        let body_start = code.find('{')
+            .ok_or_else(|| InterpError::parse("Error: Invalid main function syntax"))?;
+        
+        let body_end = self.find_matching_brace(code, body_start)
+            .ok_or_else(|| InterpError::parse("Error: Unmatched braces in main function"))?;
+        
+        let body = &code[body_start + 1..body_end];
+        
+        // Execute statements in the body
+        self.execute_statements(body)?;
+        if !self.exiting {
+            self.exit_code = match Self::convert_value("int", self.return_value.take().unwrap_or(Value::Int(0))) {
+                Value::Int(code) => code,
+                _ => 0,
+            };
         }
+        
+        Ok(self.output.clone())
+    }
 
-        if statement.contains("strcat") {
-            return self.handle_strcat(statement);
+    /// Replace `//` and `/* */` comments with a space, leaving string and
+    /// character literals alone. Newlines inside block comments are kept so
+    /// line numbers are preserved.
+    fn strip_comments(code: &str) -> Result<String, InterpError> {
+        let mut result = String::with_capacity(code.len());
+        let mut chars = code.chars().peekable();
+        let mut line = 1;
+        
+        while let Some(c) = chars.next() {
+            match c {
+                '"' | '\'' => {
+                    result.push(c);
+                    while let Some(next) = chars.next() {
+                        result.push(next);
+                        if next == '\\' {
+                            if let Some(escaped) = chars.next() {
+                                result.push(escaped);
+                            }
+                        } else if next == c || next == '\n' {
+                            if next == '\n' {
+                                line += 1;
+                            }
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    while chars.peek().is_some_and(|&next| next != '\n') {
+                        chars.next();
+                    }
+                    result.push(' ');
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let start_line = line;
+                    let mut closed = false;
+                    result.push(' ');
+                    while let Some(next) = chars.next() {
+                        if next == '*' && chars.peek() == Some(&'/') {
+                            chars.next();
+                            closed = true;
+                            break;
+                        }
+                        if next == '\n' {
+                            line += 1;
+                            result.push('\n');
+                        }
+                    }
+                    if !closed {
+                        return Err(InterpError::parse("Error: unterminated /* comment").with_line(start_line));
+                    }
+                }
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    result.push(c);
+                }
+            }
         }
+        
+        Ok(result)
+    }
 
-        // Handle math functions
-        if statement.contains("sqrt") || statement.contains("pow") || 
-           statement.contains("abs") || statement.contains("sin") ||
-           statement.contains("cos") || statement.contains("tan") ||
-           statement.contains("ceil") || statement.contains("floor") ||
-           statement.contains("exp") || statement.contains("log") ||
-           statement.contains("fabs") {
-            return self.handle_math_function(statement);
+    /// Run the preprocessor directives in source order and expand macros.
+    /// Directive lines and lines in untaken #ifdef branches become blank so
+    /// line numbers are preserved.
+    fn preprocess(&mut self, code: &str) -> Result<String, InterpError> {
+        let mut result = String::with_capacity(code.len());
+        // One entry per open #ifdef: (enclosing region active, this branch taken)
+        let mut conditionals: Vec<(bool, bool)> = Vec::new();
+        let mut lines = code.lines();
+        
+        while let Some(line) = lines.next() {
+            // Join lines ending in a backslash, padding with blank lines so
+            // later line numbers stay put
+            let mut line = line.to_string();
+            let mut joined = 0;
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some(next) => {
+                        line.push(' ');
+                        line.push_str(next);
+                        joined += 1;
+                    }
+                    None => break,
+                }
+            }
+            let line = line.as_str();
+            for _ in 0..joined {
+                result.push('\n');
+            }
+            
+            let active = conditionals.iter().all(|&(_, taken)| taken);
+            let trimmed = line.trim();
+            
+            if let Some(directive) = trimmed.strip_prefix('#') {
+                let directive = directive.trim_start();
+                let (name, argument) = directive.split_once(char::is_whitespace)
+                    .map(|(name, argument)| (name, argument.trim()))
+                    .unwrap_or((directive, ""));
+                
+                match name {
+                    "ifdef" | "ifndef" => {
+                        let defined = self.macros.contains_key(argument);
+                        conditionals.push((active, defined == (name == "ifdef")));
+                    }
+                    "else" => {
+                        let (enclosing, taken) = conditionals.pop()
+                            .ok_or_else(|| InterpError::parse("Preprocessor error: #else without #ifdef"))?;
+                        conditionals.push((enclosing, enclosing && !taken));
+                    }
+                    "endif" => {
+                        conditionals.pop().ok_or_else(|| InterpError::parse("Preprocessor error: #endif without #ifdef"))?;
+                    }
+                    "define" if active => {
+                        let name_end = argument.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                            .unwrap_or(argument.len());
+                        let (macro_name, rest) = argument.split_at(name_end);
+                        if macro_name.is_empty() {
+                            return Err(InterpError::parse("Preprocessor error: #define without a name"));
+                        }
+                        
+                        // A '(' right after the name, with no space, makes it function-like
+                        let (params, body) = match rest.strip_prefix('(') {
+                            Some(rest) => {
+                                let close = rest.find(')')
+                                    .ok_or_else(|| format!("Preprocessor error: missing ')' in #define {}", macro_name))?;
+                                let params = rest[..close].split(',')
+                                    .map(|param| param.trim().to_string())
+                                    .filter(|param| !param.is_empty())
+                                    .collect();
+                                (Some(params), &rest[close + 1..])
+                            }
+                            None => (None, rest),
+                        };
+                        self.macros.insert(macro_name.to_string(), Macro { params, body: body.trim().to_string() });
+                    }
+                    "undef" if active => {
+                        self.macros.remove(argument);
+                    }
+                    // #include and anything else needs no action here
+                    _ => {}
+                }
+                result.push('\n');
+                continue;
+            }
+            
+            if active {
+                result.push_str(&self.expand_macros(line, &mut Vec::new()));
+            }
+            result.push('\n');
         }
-
-        // Handle rand/srand
-        if statement.contains("rand") {
-            return self.handle_rand(statement);
+        
+        if !conditionals.is_empty() {
+            return Err(InterpError::parse("Preprocessor error: unterminated #ifdef"));
         }
+        
+        Ok(result)
+    }
 
-        if statement.contains("srand") {
-            return self.handle_srand(statement);
-        }
+    /// Replace macro names in `text`, rescanning replacements for further
+    /// macros. `expanding` holds the macros currently being expanded so a
+    /// self-referencing macro is left alone instead of recursing forever.
+    fn expand_macros(&mut self, text: &str, expanding: &mut Vec<String>) -> String {
+        let mut result = String::with_capacity(text.len());
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
         
-        // Handle variable declarations
-        if statement.starts_with("int ") || statement.starts_with("float ") || 
-           statement.starts_with("double ") || statement.starts_with("char ") ||
-           statement.starts_with("long ") || statement.starts_with("short ") {
-            return self.handle_declaration(statement);
+        while i < chars.len() {
+            let ch = chars[i];
+            
+            if ch == '"' || ch == '\'' {
+                // Copy string and char literals untouched
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != ch {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+                result.extend(&chars[start..i]);
+            } else if ch.is_alphabetic() || ch == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                let definition = match self.macros.get(&name) {
+                    Some(definition) if !expanding.contains(&name) => definition.clone(),
+                    _ => {
+                        result.push_str(&name);
+                        continue;
+                    }
+                };
+                
+                let body = match &definition.params {
+                    None => definition.body.clone(),
+                    Some(params) => {
+                        // A function-like macro name without arguments is left alone
+                        let mut open = i;
+                        while open < chars.len() && chars[open].is_whitespace() {
+                            open += 1;
+                        }
+                        if open == chars.len() || chars[open] != '(' {
+                            result.push_str(&name);
+                            continue;
+                        }
+                        let rest: String = chars[open..].iter().collect();
+                        let close = match self.find_matching_paren(&rest, 0) {
+                            Some(close) => close,
+                            None => {
+                                result.push_str(&name);
+                                continue;
+                            }
+                        };
+                        let mut args = Vec::new();
+                        for arg in self.split_top_level(&rest[1..close], ',') {
+                            args.push(self.expand_macros(&arg, expanding));
+                        }
+                        i = open + rest[..=close].chars().count();
+                        if self.options.macro_warnings {
+                            self.warn_repeated_side_effects(&name, &definition.body, params, &args);
+                        }
+                        self.substitute_macro_params(&definition.body, params, &args)
+                    }
+                };
+                
+                expanding.push(name);
+                result.push_str(&self.expand_macros(&body, expanding));
+                expanding.pop();
+            } else if ch.is_ascii_digit() {
+                // Numbers such as 10L or 0xFF are not identifiers
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                    result.push(chars[i]);
+                    i += 1;
+                }
+            } else {
+                result.push(ch);
+                i += 1;
+            }
         }
         
-        // Handle assignments with compound operators
-        if statement.contains("+=") || statement.contains("-=") || 
-           statement.contains("*=") || statement.contains("/=") ||
-           statement.contains("%=") {
-            return self.handle_compound_assignment(statement);
-        }
+        result
+    }
 
-        // Handle assignments
-        if statement.contains('=') && !self.is_declaration(statement) && 
-           !statement.contains("==") && !statement.contains("!=") &&
-           !statement.contains("<=") && !statement.contains(">=") {
-            return self.handle_assignment(statement);
+    /// Warn when an argument with side effects, such as `i++` or a call,
+    /// is substituted more than once and so is evaluated more than once
+    fn warn_repeated_side_effects(&mut self, name: &str, body: &str, params: &[String], args: &[String]) {
+        for (param, arg) in params.iter().zip(args) {
+            let bytes = arg.as_bytes();
+            let assigns = bytes.iter().enumerate().any(|(i, &b)| {
+                b == b'=' && !matches!(bytes.get(i + 1), Some(b'=')) &&
+                    !matches!(i.checked_sub(1).map(|p| bytes[p]), Some(b'=' | b'!' | b'<' | b'>'))
+            });
+            let calls = arg.match_indices('(').any(|(i, _)| {
+                arg[..i].trim_end().ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            });
+            if !(arg.contains("++") || arg.contains("--") || assigns || calls) {
+                continue;
+            }
+            let uses = self.substitute_macro_params(body, std::slice::from_ref(param), &["\0".to_string()])
+                .matches('\0').count();
+            let warning = format!("Warning: macro '{}' evaluates argument '{}' {} times", name, arg.trim(), uses);
+            if uses > 1 && !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
         }
+    }
 
-        // Handle increment/decrement
-        if statement.contains("++") || statement.contains("--") {
-            return self.handle_increment_decrement(statement);
-        }
+    /// Replace each parameter name in a function-like macro body with its
+    /// argument, leaving string and char literals untouched
+    fn substitute_macro_params(&self, body: &str, params: &[String], args: &[String]) -> String {
+        let mut result = String::with_capacity(body.len());
+        let mut chars = body.char_indices().peekable();
         
-        // Handle return statement
-        if statement.starts_with("return") {
-            return Ok(()); // Just ignore return for now
+        while let Some((start, ch)) = chars.next() {
+            if ch == '"' || ch == '\'' {
+                result.push(ch);
+                while let Some((_, next)) = chars.next() {
+                    result.push(next);
+                    if next == '\\' {
+                        if let Some((_, escaped)) = chars.next() {
+                            result.push(escaped);
+                        }
+                    } else if next == ch {
+                        break;
+                    }
+                }
+            } else if ch.is_alphabetic() || ch == '_' {
+                let mut end = start + ch.len_utf8();
+                while let Some(&(i, next)) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    end = i + next.len_utf8();
+                    chars.next();
+                }
+                let word = &body[start..end];
+                match params.iter().position(|param| param == word) {
+                    Some(index) => result.push_str(args.get(index).map(String::as_str).unwrap_or("")),
+                    None => result.push_str(word),
+                }
+            } else {
+                result.push(ch);
+            }
         }
-
-        Ok(())
+        
+        result
     }
 
-    fn is_declaration(&self, statement: &str) -> bool {
-        statement.starts_with("int ") || statement.starts_with("float ") ||
-        statement.starts_with("double ") || statement.starts_with("char ") ||
-        statement.starts_with("long ") || statement.starts_with("short ")
+    /// Walk the top level of the program, registering every function
+    /// definition other than main and declaring global variables
+    fn parse_globals_and_functions(&mut self, code: &str) -> Result<(), InterpError> {
+        let bytes = code.as_bytes();
+        let mut depth = 0;
+        let mut quote: Option<u8> = None;
+        let mut item_start = 0;
+        let mut brace_start = 0;
+        let mut i = 0;
+        
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if let Some(q) = quote {
+                if byte == b'\\' {
+                    i += 1;
+                } else if byte == q {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            match byte {
+                b'"' | b'\'' => quote = Some(byte),
+                b'{' => {
+                    if depth == 0 {
+                        brace_start = i;
+                    }
+                    depth += 1;
+                }
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // A function body ends its item; other braces, as in
+                        // an initializer, are part of an item ending in ';'
+                        if let Some((name, mut function)) = self.parse_function_header(&code[item_start..brace_start]) {
+                            if name != "main" {
+                                function.body = code[brace_start + 1..i].into();
+                                self.functions.insert(name, function);
+                            }
+                            item_start = i + 1;
+                        }
+                    }
+                }
+                b';' if depth == 0 => {
+                    self.declare_global(code[item_start..i].trim())?;
+                    item_start = i + 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(())
     }
 
-    fn handle_for_loop(&mut self, body: &str) -> Result<(), String> {
-        let for_start = body.find("for").ok_or("Invalid for loop")?;
-        let paren_start = body[for_start..].find('(').ok_or("Invalid for loop syntax")? + for_start;
-        let paren_end = self.find_matching_paren(body, paren_start).ok_or("Invalid for loop syntax")?;
+    /// Parse `int add(int a, int b)` into the function's name and signature
+    fn parse_function_header(&self, header: &str) -> Option<(String, Function)> {
+        let header = header.trim();
+        let params_start = header.find('(')?;
+        if !header.ends_with(')') {
+            return None;
+        }
         
-        let for_header = &body[paren_start + 1..paren_end];
-        let parts: Vec<&str> = for_header.split(';').collect();
+        let before = header[..params_start].trim_end();
+        let name_start = before.rfind(|c: char| !(c.is_alphanumeric() || c == '_')).map_or(0, |i| i + 1);
+        let name = &before[name_start..];
+        let return_type = before[..name_start].trim();
+        if name.is_empty() || return_type.is_empty() {
+            return None;
+        }
+        let return_type = self.resolve_type(return_type).unwrap_or_else(|| return_type.to_string());
         
-        if parts.len() != 3 {
-            return Err("Invalid for loop syntax".to_string());
+        let mut params = Vec::new();
+        for param in self.split_top_level(&header[params_start + 1..header.len() - 1], ',') {
+            if param.is_empty() || param == "void" {
+                continue;
+            }
+            // `int arr[]` and `int *p` both receive a pointer
+            let is_array = param.contains('[');
+            let declarator = param.split('[').next().unwrap_or(&param).trim_end();
+            let param_name_start = declarator.rfind(|c: char| !(c.is_alphanumeric() || c == '_')).map_or(0, |i| i + 1);
+            let written_type = declarator[..param_name_start].trim();
+            let base_type = written_type.trim_end_matches(['*', ' ']);
+            let param_type = match self.resolve_type(base_type) {
+                Some(canonical) => format!("{}{}", canonical, written_type[base_type.len()..].replace(' ', "")),
+                None => written_type.replace(' ', ""),
+            };
+            let param_type = if is_array { format!("{}[]", param_type) } else { param_type };
+            params.push((param_type, declarator[param_name_start..].to_string()));
         }
+        
+        Some((name.to_string(), Function { params, body: "".into(), return_type }))
+    }
 
-        // Initialize
-        self.execute_statement(parts[0].trim())?;
+    /// Declare a top-level `int count = 0;`; prototypes need no action
+    fn declare_global(&mut self, item: &str) -> Result<(), InterpError> {
+        let first_declarator = item.split('=').next().unwrap_or(item);
+        if !self.is_declaration(item) || first_declarator.contains('(') {
+            return Ok(());
+        }
+        
+        self.handle_declaration(item)?;
+        for (name, value) in &self.variables {
+            if !self.global_variables.contains_key(name) {
+                self.global_variables.insert(name.clone(), value.clone());
+            }
+        }
+        Ok(())
+    }
 
-        // Find loop body
-        let body_start = body[paren_end..].find('{').ok_or("Invalid for loop body")? + paren_end;
-        let body_end = self.find_matching_brace(body, body_start).ok_or("Unmatched braces")?;
-        let loop_body = &body[body_start + 1..body_end];
+    /// Split `name(args)` into its parts when `name` is a user-defined
+    /// function and the call is the whole expression
+    fn parse_call<'a>(&self, expr: &'a str) -> Option<(&'a str, &'a str)> {
+        let open = expr.find('(')?;
+        let name = expr[..open].trim();
+        if !self.functions.contains_key(name) || self.find_matching_paren(expr, open) != Some(expr.len() - 1) {
+            return None;
+        }
+        Some((name, &expr[open + 1..expr.len() - 1]))
+    }
 
-        // Execute loop
-        let condition = parts[1].trim();
-        let increment = parts[2].trim();
+    /// Whether `statement` calls a user-defined function anywhere
+    fn calls_user_function(&self, statement: &str) -> bool {
+        self.functions.keys().any(|name| {
+            statement.match_indices(name.as_str()).any(|(pos, _)| {
+                let before = statement[..pos].chars().next_back();
+                let after = statement[pos + name.len()..].trim_start();
+                !before.is_some_and(|c| c.is_alphanumeric() || c == '_') && after.starts_with('(')
+            })
+        })
+    }
 
-        let max_iterations = 100000; // Safety limit
-        let mut iterations = 0;
+    /// Call a user-defined function: bind the arguments in a fresh scope,
+    /// run the body and return its value converted to the return type
+    fn call_function(&mut self, name: &str, args: &str) -> Result<Value, InterpError> {
+        let function = self.functions.get(name).cloned()
+            .ok_or_else(|| format!("Error: implicit declaration of function '{}'", name))?;
+        // The scope switch lives in helpers so this frame, live once per
+        // level of C recursion, stays small
+        let copy_back = self.enter_function(name, &function, args)?;
+        let outcome = self.execute_statements(&function.body);
+        let return_value = self.return_value.take();
+        // return stops at the function boundary; exit() keeps unwinding
+        if !self.exiting {
+            self.returning = false;
+        }
+        self.leave_function(&function, copy_back);
+        outcome?;
+        
+        Ok(match return_value {
+            Some(value) if function.return_type != "void" => Self::convert_value(&function.return_type, value),
+            _ => Value::Int(0),
+        })
+    }
 
-        while self.evaluate_condition(condition)? {
-            if iterations >= max_iterations {
-                return Err("Loop exceeded maximum iterations (possible infinite loop)".to_string());
+    /// Bind `args` to the parameters of `function` in a new scope holding
+    /// only the globals and parameters, returning the (parameter, argument)
+    /// pairs of arrays and strings to copy back to the caller
+    fn enter_function(&mut self, name: &str, function: &Function, args: &str) -> Result<Vec<(String, String)>, InterpError> {
+        let mut args = self.split_top_level(args, ',');
+        if args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        if args.len() != function.params.len() {
+            return Err(format!("Error: function '{}' expects {} argument(s) but {} were given",
+                name, function.params.len(), args.len()).into());
+        }
+        if self.call_stack.len() >= self.options.max_call_depth {
+            return Err(format!("Error: maximum call depth of {} exceeded in '{}' (runaway recursion?)", self.options.max_call_depth, name).into());
+        }
+        
+        // Evaluate the arguments in the caller's scope. Arrays and strings are
+        // passed as their contents and copied back afterwards, standing in
+        // for C passing a pointer to them.
+        let mut bindings = Vec::new();
+        let mut copy_back = Vec::new();
+        for ((param_type, param_name), arg) in function.params.iter().zip(&args) {
+            let value = match self.variables.get(arg.as_str()) {
+                Some(value @ (Value::Array(_) | Value::String(_))) => {
+                    copy_back.push((param_name.clone(), arg.clone()));
+                    value.clone()
+                }
+                _ if param_type.ends_with('*') || param_type.ends_with("[]") => self.evaluate_pointer_expression(arg)?,
+                _ => self.evaluate_typed_initializer(param_type, arg)?,
+            };
+            bindings.push((param_type, param_name, value));
+        }
+        
+        // Memory must hold the caller's current values for pointers into them
+        for (var_name, &addr) in &self.memory.address_map {
+            match self.variables.get(var_name) {
+                Some(Value::Array(_)) | None => {}
+                Some(value) => { self.memory.heap.insert(addr, value.clone()); }
             }
-            iterations += 1;
-
-            self.loop_break = false;
-            self.loop_continue = false;
-
-            self.execute_statements(loop_body)?;
-
-            if self.loop_break {
-                self.loop_break = false;
-                break;
+        }
+        for global in self.global_variables.keys().cloned().collect::<Vec<_>>() {
+            if let Some(value) = self.variables.get(&global) {
+                self.global_variables.insert(global, value.clone());
             }
-
-            if !self.loop_continue {
-                self.execute_statement(increment)?;
+        }
+        
+        // Switch to a new scope holding only the globals and parameters
+        let globals = self.global_variables.clone();
+        let global_addresses = globals.keys()
+            .filter_map(|global| self.memory.address_map.get(global).map(|&addr| (global.clone(), addr)))
+            .collect();
+        let caller = Frame {
+            variables: std::mem::replace(&mut self.variables, globals),
+            address_map: std::mem::replace(&mut self.memory.address_map, global_addresses),
+            pointee_types: std::mem::take(&mut self.pointee_types),
+            variable_types: std::mem::take(&mut self.variable_types),
+            constants: std::mem::take(&mut self.constants),
+        };
+        for global in self.global_variables.keys() {
+            if let Some(var_type) = caller.variable_types.get(global) {
+                self.variable_types.insert(global.clone(), var_type.clone());
+            }
+            if let Some(var_type) = caller.pointee_types.get(global) {
+                self.pointee_types.insert(global.clone(), var_type.clone());
+            }
+            if caller.constants.contains(global) {
+                self.constants.insert(global.clone());
+            }
+        }
+        self.call_stack.push(caller);
+        
+        for (param_type, param_name, value) in bindings {
+            let base_type = param_type.trim_end_matches(['*', '[', ']']).to_string();
+            if param_type.ends_with('*') || param_type.ends_with("[]") {
+                self.pointee_types.insert(param_name.clone(), base_type);
             } else {
-                self.execute_statement(increment)?;
-                self.loop_continue = false;
+                self.variable_types.insert(param_name.clone(), base_type);
+                self.memory.get_address_of(param_name, &value);
+                self.memory.update_variable_address(param_name, &value);
             }
+            self.variables.insert(param_name.clone(), value);
         }
-
-        Ok(())
+        Ok(copy_back)
     }
 
-    fn handle_while_loop(&mut self, body: &str) -> Result<(), String> {
-        let while_start = body.find("while").ok_or("Invalid while loop")?;
-        let paren_start = body[while_start..].find('(').ok_or("Invalid while loop syntax")? + while_start;
-        let paren_end = self.find_matching_paren(body, paren_start).ok_or("Invalid while loop syntax")?;
-        
-        let condition = &body[paren_start + 1..paren_end];
+    /// Return to the caller after a call to `function`
+    fn leave_function(&mut self, function: &Function, copy_back: Vec<(String, String)>) {
+        // Restore the caller, carrying back globals and passed arrays
+        let caller = self.call_stack.pop().expect("call stack holds the caller");
+        let callee_variables = std::mem::replace(&mut self.variables, caller.variables);
+        self.memory.address_map = caller.address_map;
+        self.pointee_types = caller.pointee_types;
+        self.variable_types = caller.variable_types;
+        self.constants = caller.constants;
+        for global in self.global_variables.keys().cloned().collect::<Vec<_>>() {
+            let shadowed = function.params.iter().any(|(_, param_name)| *param_name == global);
+            if let (false, Some(value)) = (shadowed, callee_variables.get(&global)) {
+                self.global_variables.insert(global.clone(), value.clone());
+                self.variables.insert(global, value.clone());
+            }
+        }
+        for (param_name, arg) in copy_back {
+            if let Some(value) = callee_variables.get(&param_name) {
+                self.variables.insert(arg, value.clone());
+            }
+        }
+    }
 
-        // Find loop body
-        let body_start = body[paren_end..].find('{').ok_or("Invalid while loop body")? + paren_end;
-        let body_end = self.find_matching_brace(body, body_start).ok_or("Unmatched braces")?;
-        let loop_body = &body[body_start + 1..body_end];
+    /// Convert a value to a declared type, as on return from a function
+    fn convert_value(type_name: &str, value: Value) -> Value {
+        if type_name.ends_with('*') {
+            return value;
+        }
+        let type_name = Self::canonical_type(type_name)
+            .unwrap_or_else(|| type_name.split_whitespace().last().unwrap_or(type_name).to_string());
+        let wrap = |i| Value::Int(Self::wrap_integer(&type_name, i));
+        match (type_name.trim_start_matches("unsigned "), value) {
+            ("float" | "double", Value::Int(i)) => Value::Float(i as f64),
+            ("float" | "double", Value::Char(c)) => Value::Float(Self::char_as_i64(c) as f64),
+            ("int" | "long" | "short", Value::Int(i)) => wrap(i),
+            ("int" | "long" | "short", Value::Float(f)) => wrap(f as i64),
+            ("int" | "long" | "short", Value::Char(c)) => wrap(Self::char_as_i64(c)),
+            // An unsigned char is held as its value 0-255, since a Char
+            // reads back signed
+            ("char", Value::Char(c)) if type_name == "unsigned char" => wrap(c as i64),
+            ("char", Value::Int(i)) if type_name == "unsigned char" => wrap(i),
+            ("char", Value::Float(f)) if type_name == "unsigned char" => wrap(f as i64),
+            ("char", Value::Bool(b)) if type_name == "unsigned char" => wrap(b as i64),
+            ("int" | "long" | "short", Value::Bool(b)) => wrap(b as i64),
+            ("char", Value::Int(i)) => Value::Char(i as u8 as char),
+            ("char", Value::Float(f)) => Value::Char(f as i64 as u8 as char),
+            ("char", Value::Bool(b)) => Value::Char(b as u8 as char),
+            ("bool" | "_Bool", Value::Int(i)) => Value::Bool(i != 0),
+            (_, value) => value,
+        }
+    }
 
-        let max_iterations = 100000;
-        let mut iterations = 0;
+    fn find_matching_brace(&self, code: &str, start: usize) -> Option<usize> {
+        find_matching_delimiter(code, start, b'{', b'}')
+    }
 
-        // Execute loop
-        while self.evaluate_condition(condition)? {
-            if iterations >= max_iterations {
-                return Err("Loop exceeded maximum iterations (possible infinite loop)".to_string());
-            }
-            iterations += 1;
+    fn find_matching_paren(&self, code: &str, start: usize) -> Option<usize> {
+        find_matching_delimiter(code, start, b'(', b')')
+    }
 
-            self.loop_break = false;
-            self.loop_continue = false;
+    fn execute_statements(&mut self, body: &str) -> Result<(), InterpError> {
+        let body = body.trim();
+        if body.is_empty() {
+            return Ok(());
+        }
 
-            self.execute_statements(loop_body)?;
+        let key = (body.as_ptr() as usize, body.len());
+        let statements = match self.statement_cache.get(&key) {
+            Some(statements) => Rc::clone(statements),
+            None => {
+                // Statements are slices of the body, so their offsets follow
+                // from where they start in memory
+                let mut statements = Vec::new();
+                for text in self.split_statements(body) {
+                    let folded = self.fold_constants(text);
+                    statements.push(CachedStatement { offset: text.as_ptr() as usize - body.as_ptr() as usize, text: text.to_string(), folded });
+                }
+                let statements: Statements = Rc::new(statements);
+                self.statement_cache.insert(key, Rc::clone(&statements));
+                statements
+            }
+        };
 
-            if self.loop_break {
-                self.loop_break = false;
+        for CachedStatement { offset, text: statement, folded } in statements.iter() {
+            if self.loop_break || self.loop_continue || self.returning {
                 break;
             }
-
-            self.loop_continue = false;
+            // Blocks and control structures are traced through their inner statements
+            let before = (self.trace.is_some() && !self.is_control_statement(statement.trim())).then(|| self.variables.clone());
+            self.execute_compound_statement(folded.as_deref().unwrap_or(statement))
+                .map_err(|error| match self.statement_position(body, *offset, statement) {
+                    Some((line, column)) => error.with_position(line, Some(column)),
+                    None => error,
+                })?;
+            if let Some(before) = before {
+                self.record_trace(body, *offset, statement, &before);
+            }
         }
 
         Ok(())
     }
 
-    fn handle_do_while_loop(&mut self, body: &str) -> Result<(), String> {
-        let do_start = body.find("do").ok_or("Invalid do-while loop")?;
-        let body_start = body[do_start..].find('{').ok_or("Invalid do-while body")? + do_start;
-        let body_end = self.find_matching_brace(body, body_start).ok_or("Unmatched braces")?;
-        let loop_body = &body[body_start + 1..body_end];
+    /// Add `statement` to the trace with the variables that differ from
+    /// `before`, the scope as it was when the statement started
+    fn record_trace(&mut self, body: &str, offset: usize, statement: &str, before: &HashMap<String, Value>) {
+        let line = self.statement_position(body, offset, statement).map(|(line, _)| line);
+        let changed = self.variables.iter()
+            .filter(|(name, value)| before.get(*name) != Some(*value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let Some(trace) = self.trace.as_mut() else {
+            return;
+        };
+        if trace.len() < MAX_TRACE_ENTRIES {
+            trace.push(TraceEntry { statement: statement.trim().to_string(), line, changed });
+            return;
+        }
+        let warning = format!("Warning: trace stopped after {} statements", MAX_TRACE_ENTRIES);
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
 
-        let while_start = body[body_end..].find("while").ok_or("Invalid do-while loop")? + body_end;
-        let paren_start = body[while_start..].find('(').ok_or("Invalid do-while syntax")? + while_start;
-        let paren_end = self.find_matching_paren(body, paren_start).ok_or("Invalid do-while syntax")?;
-        let condition = &body[paren_start + 1..paren_end];
+    /// 1-based source line and column of `statement`, found at `offset` in
+    /// `body`. Bodies and statements are copies of the source, so they are
+    /// located by their text; one that appears more than once can't be
+    /// pinned down.
+    fn statement_position(&self, body: &str, offset: usize, statement: &str) -> Option<(usize, usize)> {
+        let unique_position = |text: &str| {
+            if self.source.matches(text).nth(1).is_some() {
+                return None;
+            }
+            self.source.find(text)
+        };
+        let position = unique_position(body).map(|start| start + offset)
+            .or_else(|| unique_position(statement))?;
+        let before = &self.source[..position];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Some((before.matches('\n').count() + 1, before[line_start..].chars().count() + 1))
+    }
 
-        let max_iterations = 100000;
-        let mut iterations = 0;
+    /// Dispatch a single statement on its leading keyword, so control
+    /// structures are only entered when the statement actually begins with one
+    fn execute_compound_statement(&mut self, statement: &str) -> Result<(), InterpError> {
+        let statement = statement.trim();
 
-        loop {
-            if iterations >= max_iterations {
-                return Err("Loop exceeded maximum iterations (possible infinite loop)".to_string());
-            }
-            iterations += 1;
+        if statement.starts_with('{') {
+            let block_end = self.find_matching_brace(statement, 0).ok_or_else(|| InterpError::parse("Unmatched braces"))?;
+            return self.execute_statements(&statement[1..block_end]);
+        }
 
-            self.loop_break = false;
-            self.loop_continue = false;
+        if self.starts_with_keyword(statement, "for") {
+            return self.handle_for_loop(statement);
+        }
 
-            self.execute_statements(loop_body)?;
+        if self.starts_with_keyword(statement, "while") {
+            return self.handle_while_loop(statement);
+        }
 
-            if self.loop_break {
-                self.loop_break = false;
-                break;
-            }
+        if self.starts_with_keyword(statement, "do") {
+            return self.handle_do_while_loop(statement);
+        }
 
-            self.loop_continue = false;
+        if self.starts_with_keyword(statement, "if") {
+            return self.handle_if_else_statement(statement);
+        }
 
-            if !self.evaluate_condition(condition)? {
-                break;
-            }
+        if self.starts_with_keyword(statement, "switch") {
+            return self.handle_switch_statement(statement);
         }
 
-        Ok(())
+        self.execute_statement(statement)
+    }
+
+    /// Check whether `code` begins with `keyword` as a whole word
+    fn starts_with_keyword(&self, code: &str, keyword: &str) -> bool {
+        code.strip_prefix(keyword)
+            .map(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+            .unwrap_or(false)
     }
 
-    fn handle_if_else_statement(&mut self, body: &str) -> Result<(), String> {
-        let if_start = body.find("if").ok_or("Invalid if statement")?;
-        let paren_start = body[if_start..].find('(').ok_or("Invalid if syntax")? + if_start;
-        let paren_end = self.find_matching_paren(body, paren_start).ok_or("Invalid if syntax")?;
+    /// Byte position of a call to `name` in `statement`: the whole word
+    /// followed by `(`, outside any literal, so `outputs = 1` or
+    /// `"use printf"` don't count as calls to puts or printf
+    fn find_call(&self, statement: &str, name: &str) -> Option<usize> {
+        let bytes = statement.as_bytes();
+        let is_word = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+        let mut quote: Option<u8> = None;
+        let mut i = 0;
         
-        let condition = &body[paren_start + 1..paren_end];
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if let Some(q) = quote {
+                if byte == b'\\' {
+                    i += 1;
+                } else if byte == q {
+                    quote = None;
+                }
+            } else if byte == b'"' || byte == b'\'' {
+                quote = Some(byte);
+            } else if statement[i..].starts_with(name) && (i == 0 || !is_word(bytes[i - 1])) &&
+                statement[i + name.len()..].trim_start().starts_with('(') {
+                return Some(i);
+            }
+            i += 1;
+        }
+        None
+    }
 
-        // Find if body
-        let body_start = body[paren_end..].find('{').ok_or("Invalid if body")? + paren_end;
-        let body_end = self.find_matching_brace(body, body_start).ok_or("Unmatched braces")?;
-        let if_body = &body[body_start + 1..body_end];
+    fn is_control_statement(&self, statement: &str) -> bool {
+        statement.starts_with('{') ||
+        ["if", "else", "for", "while", "do", "switch"].iter()
+            .any(|keyword| self.starts_with_keyword(statement, keyword))
+    }
 
-        // Check for else
-        let remaining = body[body_end + 1..].trim();
+    /// `statement` with each run of integer literals and operators that can
+    /// stand on its own, such as `2 * 3 + 100` in `arr[i] = 2 * 3 + 100` or
+    /// `6 % 5` in `x + 6 % 5`, replaced by its value. A run whose neighbours
+    /// bind tighter, as `2 + 3` in `x * 2 + 3`, is left alone, as is one that
+    /// fails to evaluate, such as `1 / 0`, so it reports its error if it
+    /// runs. None when nothing folds; blocks and control structures fold
+    /// statement by statement when their bodies run.
+    fn fold_constants(&mut self, statement: &str) -> Option<String> {
+        // Binding strength of each binary operator; the unary ones bind tighter
+        let precedence = |token: &Token| {
+            if token.kind != TokenKind::Operator {
+                return None;
+            }
+            Some(match token.text {
+                "*" | "/" | "%" => 10,
+                "+" | "-" => 9,
+                "<<" | ">>" => 8,
+                "<" | ">" | "<=" | ">=" => 7,
+                "==" | "!=" => 6,
+                "&" => 5,
+                "^" => 4,
+                "|" => 3,
+                "&&" => 2,
+                "||" => 1,
+                _ => return None,
+            })
+        };
+        const UNARY: u8 = 11;
+        let is_literal = |token: &Token| token.kind == TokenKind::Number && token.text.bytes().all(|b| b.is_ascii_digit());
+        let is_unary = |token: &Token| ["-", "+", "!", "~"].iter().any(|text| token.is(text));
+        // What ends an operand, making the operator after it binary
+        let ends_operand = |token: &Token| matches!(token.kind, TokenKind::Identifier | TokenKind::Number |
+            TokenKind::CharLiteral | TokenKind::StringLiteral) || token.is(")") || token.is("]");
+        // What may stand either side of a whole operand
+        let opens = |token: &Token| token.is_keyword("return") || token.is_keyword("case") ||
+            ["(", "[", "{", ",", ";", "=", "?", ":"].iter().any(|text| token.is(text)) ||
+            (token.kind == TokenKind::Operator && token.text.len() > 1 && token.text.ends_with('=') &&
+                !["==", "!=", "<=", ">="].contains(&token.text));
+        let closes = |token: &Token| [")", "]", "}", ",", ";", "?", ":"].iter().any(|text| token.is(text));
         
-        if self.evaluate_condition(condition)? {
-            self.execute_statements(if_body)?;
-        } else if remaining.starts_with("else") {
-            let else_part = &remaining[4..].trim();
-            
-            // Check for else if
-            if else_part.starts_with("if") {
-                self.handle_if_else_statement(else_part)?;
-            } else {
-                // Simple else
-                let else_body_start = else_part.find('{').ok_or("Invalid else body")?;
-                let else_body_end = self.find_matching_brace(else_part, else_body_start).ok_or("Unmatched braces")?;
-                let else_body = &else_part[else_body_start + 1..else_body_end];
-                self.execute_statements(else_body)?;
+        if self.is_control_statement(statement.trim()) {
+            return None;
+        }
+        let mut text = statement.to_string();
+        'fold: loop {
+            let tokens = lexer::tokenize(&text);
+            let in_run = |token: &Token| is_literal(token) || is_unary(token) || precedence(token).is_some();
+            let mut next = 0;
+            while next < tokens.len() {
+                if !in_run(&tokens[next]) {
+                    next += 1;
+                    continue;
+                }
+                let run_end = next + tokens[next..].iter().take_while(|token| in_run(token)).count();
+                // A binary operator at either end belongs to the neighbouring operand
+                let mut start = next;
+                if start > 0 && ends_operand(&tokens[start - 1]) {
+                    start += 1;
+                }
+                let mut end = run_end;
+                while end > start && !is_literal(&tokens[end - 1]) {
+                    end -= 1;
+                }
+                next = run_end;
+                if end - start < 2 || !(is_literal(&tokens[start]) || is_unary(&tokens[start])) {
+                    continue;
+                }
+                
+                // The loosest operator in the run must bind tighter than
+                // the one to its left, and at least as tight as the one to
+                // its right, for the run to be a whole operand
+                let run = &tokens[start..end];
+                let loosest = (1..run.len())
+                    .filter(|&i| is_literal(&run[i - 1]))
+                    .filter_map(|i| precedence(&run[i]))
+                    .min().unwrap_or(UNARY);
+                let left_ok = start == 0 || opens(&tokens[start - 1]) || precedence(&tokens[start - 1]).is_some_and(|p| p < loosest);
+                let right_ok = tokens.get(end).is_none_or(|token| closes(token) || precedence(token).is_some_and(|p| p <= loosest));
+                if !(left_ok && right_ok) {
+                    continue;
+                }
+                // A negative literal such as -2 is already folded
+                let range = run[0].start..run[run.len() - 1].end();
+                if let Ok(value) = self.evaluate_numeric_expression(&text[range.clone()]) {
+                    if value.to_string() != text[range.clone()] {
+                        text.replace_range(range, &value.to_string());
+                        continue 'fold;
+                    }
+                }
+            }
+            // A literal left in grouping parentheses, such as the (6) of
+            // (6) % 5, stands on its own; a call's or a cast's parentheses stay
+            let unwrap = tokens.windows(3).enumerate().find(|(i, window)| {
+                window[0].is("(") && is_literal(&window[1]) && window[2].is(")") &&
+                    (*i == 0 || opens(&tokens[i - 1]) || tokens[i - 1].kind == TokenKind::Operator)
+            });
+            match unwrap {
+                Some((_, window)) => {
+                    let literal = window[1].text.to_string();
+                    text.replace_range(window[0].start..window[2].end(), &literal);
+                }
+                None => break,
             }
         }
+        (text != statement).then_some(text)
+    }
 
-        Ok(())
+    /// Split a leading `case expr:` or `default:` label off a statement,
+    /// giving the case expression (None for default) and the rest
+    fn split_case_label<'a>(&self, statement: &'a str) -> Option<(Option<&'a str>, &'a str)> {
+        let statement = statement.trim_start();
+        if self.starts_with_keyword(statement, "default") {
+            let rest = statement["default".len()..].trim_start().strip_prefix(':')?;
+            return Some((None, rest.trim_start()));
+        }
+        if !self.starts_with_keyword(statement, "case") {
+            return None;
+        }
+        let (colon, _) = self.find_top_level_operator(statement, &[":"], false)?;
+        Some((Some(statement["case".len()..colon].trim()), statement[colon + 1..].trim_start()))
     }
 
-    fn handle_switch_statement(&mut self, body: &str) -> Result<(), String> {
-        let switch_start = body.find("switch").ok_or("Invalid switch statement")?;
-        let paren_start = body[switch_start..].find('(').ok_or("Invalid switch syntax")? + switch_start;
-        let paren_end = self.find_matching_paren(body, paren_start).ok_or("Invalid switch syntax")?;
-        
-        let switch_expr = &body[paren_start + 1..paren_end];
-        let switch_value = self.evaluate_numeric_expression(switch_expr)?;
+    /// The statement with any `case`/`default` labels in front removed
+    fn strip_case_labels<'a>(&self, mut statement: &'a str) -> &'a str {
+        while let Some((_, rest)) = self.split_case_label(statement) {
+            statement = rest;
+        }
+        statement
+    }
 
-        let body_start = body[paren_end..].find('{').ok_or("Invalid switch body")? + paren_end;
-        let body_end = self.find_matching_brace(body, body_start).ok_or("Unmatched braces")?;
-        let switch_body = &body[body_start + 1..body_end];
+    fn split_statements<'a>(&self, body: &'a str) -> Vec<&'a str> {
+        let mut statements = Vec::new();
+        let mut current_start = 0;
+        let mut first = 0; // Index of the current statement's first token
+        let mut brace_depth = 0;
+        let mut paren_depth = 0;
+        // Literals are single tokens, so `';'` or "{" don't split
+        let tokens = lexer::tokenize(body);
 
-        // Find matching case
-        let mut _found_case = false;
-        let mut execute_remaining = false;
-        
-        let lines: Vec<&str> = switch_body.lines().collect();
-        let mut i = 0;
-        
-        while i < lines.len() {
-            let line = lines[i].trim();
-            
-            if line.starts_with("case") {
-                let case_value_str = line.strip_prefix("case").unwrap().trim().trim_end_matches(':');
-                if let Ok(case_value) = case_value_str.parse::<i64>() {
-                    if case_value == switch_value || execute_remaining {
-                        _found_case = true;
-                        execute_remaining = true;
-                        i += 1;
+        for (index, token) in tokens.iter().enumerate() {
+            if token.kind != TokenKind::Punctuation {
+                continue;
+            }
+            let next = tokens.get(index + 1);
+            match token.text {
+                "{" => brace_depth += 1,
+                "}" => {
+                    brace_depth -= 1;
+
+                    // A control structure ends with its closing brace, unless an
+                    // `else` or the `while` of a do-while continues it
+                    if brace_depth == 0 && paren_depth == 0 {
+                        let stmt = body[current_start..token.end()].trim();
+                        let continues = next.is_some_and(|next| next.is_keyword("else") ||
+                            (self.starts_with_keyword(stmt, "do") && next.is_keyword("while")));
+                        if self.is_control_statement(self.strip_case_labels(stmt)) && !continues {
+                            statements.push(stmt);
+                            current_start = token.end();
+                            first = index + 1;
+                        }
+                    }
+                }
+                "(" => paren_depth += 1,
+                ")" => paren_depth -= 1,
+                ";" if brace_depth == 0 && paren_depth == 0 => {
+                    let stmt = body[current_start..token.end()].trim();
+                    // A do-while whose body is a single statement, as in
+                    // `do x++; while (x < 5);`, continues to its `while`
+                    let braceless_do = tokens[first].is_keyword("do") &&
+                        !tokens[first + 1].is("{") &&
+                        !tokens[first..index].iter().any(|token| token.is(";"));
+                    if braceless_do && next.is_some_and(|next| next.is_keyword("while")) {
+                        continue;
+                    }
+                    // The body of `if (x) y = 1; else y = 2;` continues to its else
+                    if next.is_some_and(|next| next.is_keyword("else")) {
                         continue;
                     }
+                    if !stmt.is_empty() {
+                        statements.push(stmt);
+                    }
+                    current_start = token.end();
+                    first = index + 1;
                 }
-            } else if line.starts_with("default:") {
-                execute_remaining = true;
-                i += 1;
-                continue;
-            } else if line == "break;" && execute_remaining {
-                break;
-            } else if execute_remaining && !line.is_empty() {
-                self.execute_statement(line)?;
+                _ => {}
             }
-            
-            i += 1;
         }
 
-        Ok(())
-    }
+        let remaining = body[current_start..].trim();
+        if !remaining.is_empty() {
+            statements.push(remaining);
+        }
 
-    fn evaluate_condition(&mut self, condition: &str) -> Result<bool, String> {
-        let condition = condition.trim();
+        statements
+    }
 
-        // Handle logical operators
-        if condition.contains("&&") {
-            let parts: Vec<&str> = condition.splitn(2, "&&").collect();
-            let left = self.evaluate_condition(parts[0].trim())?;
-            let right = self.evaluate_condition(parts[1].trim())?;
-            return Ok(left && right);
+    fn execute_statement(&mut self, statement: &str) -> Result<(), InterpError> {
+        // Whitespace (or a stripped comment) may sit before the semicolon
+        let statement = statement.trim().trim_end_matches(';').trim_end();
+        
+        if statement.is_empty() {
+            return Ok(());
         }
 
-        if condition.contains("||") {
-            let parts: Vec<&str> = condition.splitn(2, "||").collect();
-            let left = self.evaluate_condition(parts[0].trim())?;
-            let right = self.evaluate_condition(parts[1].trim())?;
-            return Ok(left || right);
+        // Handle break and continue
+        if statement == "break" {
+            self.loop_break = true;
+            return Ok(());
         }
 
-        if condition.starts_with('!') {
-            let inner = self.evaluate_condition(&condition[1..].trim())?;
-            return Ok(!inner);
+        if statement == "continue" {
+            self.loop_continue = true;
+            return Ok(());
         }
 
-        // Handle comparison operators
-        if condition.contains("<=") {
-            let parts: Vec<&str> = condition.splitn(2, "<=").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left <= right);
+        // Handle return statement
+        if self.starts_with_keyword(statement, "return") {
+            let expr = statement["return".len()..].trim();
+            if !expr.is_empty() {
+                self.return_value = Some(self.evaluate_value_expression(expr)?);
+            }
+            self.returning = true;
+            return Ok(());
         }
 
-        if condition.contains(">=") {
-            let parts: Vec<&str> = condition.splitn(2, ">=").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left >= right);
+        // exit() ends the whole program, from however deep in the call stack
+        if self.starts_with_keyword(statement, "exit") {
+            let args = statement["exit".len()..].trim();
+            let status = args.strip_prefix('(').and_then(|a| a.strip_suffix(')')).unwrap_or(args);
+            self.exit_code = if status.trim().is_empty() { 0 } else { self.evaluate_numeric_expression(status)? };
+            self.returning = true;
+            self.exiting = true;
+            return Ok(());
         }
 
-        if condition.contains("==") {
-            let parts: Vec<&str> = condition.splitn(2, "==").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left == right);
+        // A bare getchar(); discards one character of input
+        if self.parse_library_call(statement, &["getchar"]).is_some_and(|(_, args)| args.trim().is_empty()) {
+            self.input.next_char();
+            return Ok(());
         }
 
-        if condition.contains("!=") {
-            let parts: Vec<&str> = condition.splitn(2, "!=").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left != right);
+        // Handle ungetc before anything that might match inside its arguments
+        if self.starts_with_keyword(statement, "ungetc") {
+            return self.handle_ungetc(statement);
         }
 
-        if condition.contains('<') && !condition.contains("<<") {
-            let parts: Vec<&str> = condition.splitn(2, '<').collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left < right);
+        // Handle calls to user-defined functions here, so that a function
+        // named like cost() or get_total() isn't taken for a library call below
+        let is_output_call = self.starts_with_keyword(statement, "printf") || self.starts_with_keyword(statement, "puts");
+        if !is_output_call && self.calls_user_function(statement) {
+            if let Some((name, args)) = self.parse_call(statement) {
+                return self.call_function(name, args).map(|_| ());
+            }
+            return self.handle_variable_statement(statement);
         }
 
-        if condition.contains('>') && !condition.contains(">>") {
-            let parts: Vec<&str> = condition.splitn(2, '>').collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left > right);
+        // Handle printf statements
+        if self.find_call(statement, "printf").is_some() {
+            return self.handle_printf(statement);
         }
 
-        // Handle simple boolean values
-        let val = self.evaluate_numeric_expression(condition)?;
-        Ok(val != 0)
-    }
+        // Handle a bare scanf; used as a value it gives the count of
+        // conversions matched
+        if self.parse_library_call(statement, &["scanf"]).is_some() {
+            return self.evaluate_scanf(statement).map(|_| ());
+        }
 
-    fn handle_increment_decrement(&mut self, statement: &str) -> Result<(), String> {
-        if statement.contains("++") {
-            let var_name = statement.replace("++", "").trim().to_string();
-            if let Some(val) = self.variables.get(&var_name) {
-                match val {
-                    Value::Int(i) => {
-                        self.variables.insert(var_name, Value::Int(i + 1));
-                    },
-                    Value::Float(f) => {
-                        self.variables.insert(var_name, Value::Float(f + 1.0));
-                    },
-                    _ => {}
-                }
-            }
-        } else if statement.contains("--") {
-            let var_name = statement.replace("--", "").trim().to_string();
-            if let Some(val) = self.variables.get(&var_name) {
-                match val {
-                    Value::Int(i) => {
-                        self.variables.insert(var_name, Value::Int(i - 1));
-                    },
-                    Value::Float(f) => {
-                        self.variables.insert(var_name, Value::Float(f - 1.0));
-                    },
-                    _ => {}
-                }
-            }
+        // Handle puts statement
+        if self.find_call(statement, "puts").is_some() {
+            return self.handle_puts(statement);
         }
-        Ok(())
-    }
 
-    fn handle_printf(&mut self, statement: &str) -> Result<(), String> {
-        let start = statement.find('(')
-            .ok_or_else(|| "Error: Invalid printf syntax".to_string())?;
-        let end = statement.rfind(')')
-            .ok_or_else(|| "Error: Invalid printf syntax".to_string())?;
-        
-        let args = &statement[start + 1..end];
-        let parts = self.split_args(args);
-        
-        if parts.is_empty() {
-            return Ok(());
+        // Handle gets statement
+        if self.find_call(statement, "gets").is_some() {
+            return self.handle_gets(statement);
         }
 
-        let format_str = parts[0].trim_matches('"').trim_matches('\'');
-        let mut result = format_str.to_string();
-        
-        // Handle escape sequences
-        result = result.replace("\\n", "\n");
-        result = result.replace("\\t", "\t");
-        result = result.replace("\\\\", "\\");
-        result = result.replace("\\r", "\r");
-        result = result.replace("\\0", "\0");
-        
-        // Handle format specifiers
-        let mut arg_index = 1;
-        let format_specs = vec!["%p", "%d", "%i", "%f", "%lf", "%c", "%s", "%ld", "%u", "%x", "%o"];
-        
-        for spec in format_specs {
-            while result.contains(spec) && arg_index < parts.len() {
-                let value = self.evaluate_value_expression(&parts[arg_index])?;
-                let replacement = match value {
-                    Value::Int(i) => {
-                        if spec == "%x" {
-                            format!("{:x}", i)
-                        } else if spec == "%o" {
-                            format!("{:o}", i)
-                        } else {
-                            i.to_string()
-                        }
-                    },
-                    Value::Float(f) => f.to_string(),
-                    Value::Char(c) => c.to_string(),
-                    Value::String(s) => s,
-                    Value::Bool(b) => (b as i32).to_string(),
-                    Value::Array(_) => "[array]".to_string(),
-                    Value::Pointer(addr) => {
-                        if spec == "%x" {
-                            format!("{:x}", addr)
-                        } else if spec == "%p" {
-                            format!("0x{:x}", addr)
-                        } else {
-                            format!("0x{:x}", addr)
-                        }
-                    },
-                };
-                
-                if let Some(pos) = result.find(spec) {
-                    result.replace_range(pos..pos + spec.len(), &replacement);
-                }
-                arg_index += 1;
-            }
+        // Handle a bare strcpy/strcat or strncpy/strncat; strlen and strcmp, and these used
+        // as values, are evaluated within expressions
+        if self.parse_library_call(statement, STRING_COPY_FUNCTIONS).is_some() {
+            return self.evaluate_string_copy(statement).map(|_| ());
         }
-        
-        self.output.push_str(&result);
-        Ok(())
-    }
 
-    fn handle_scanf(&mut self, statement: &str) -> Result<(), String> {
-        // Parse scanf("format", &var1, &var2, ...)
-        let start = statement.find('(').ok_or("Invalid scanf syntax")?;
-        let end = statement.rfind(')').ok_or("Invalid scanf syntax")?;
-        let args = &statement[start + 1..end];
-        
-        // Split by comma, but be careful of commas in strings
-        let mut parts = Vec::new();
-        let mut current = String::new();
-        let mut in_string = false;
-        
-        for ch in args.chars() {
-            match ch {
-                '"' => in_string = !in_string,
-                ',' if !in_string => {
-                    parts.push(current.trim().to_string());
-                    current.clear();
-                }
-                _ => current.push(ch),
-            }
+        // Handle a bare sprintf; used as a value it gives the length written
+        if self.parse_library_call(statement, &["sprintf"]).is_some() {
+            return self.evaluate_sprintf(statement).map(|_| ());
         }
-        if !current.is_empty() {
-            parts.push(current.trim().to_string());
+
+        // Handle a bare memset/memcpy
+        if self.parse_library_call(statement, &["memset", "memcpy"]).is_some() {
+            return self.evaluate_memory_call(statement).map(|_| ());
         }
-        
-        if parts.is_empty() {
-            return Err("Invalid scanf: no arguments".to_string());
+
+        // Handle a bare putchar; used as a value it is evaluated within expressions
+        if self.parse_library_call(statement, &["putchar"]).is_some() {
+            return self.evaluate_numeric_expression(statement).map(|_| ());
         }
-        
-        // First part is the format string
-        let format_str = parts[0].trim_matches('"').to_string();
-        let var_names: Vec<String> = parts[1..].iter()
-            .map(|s| s.trim().trim_start_matches('&').to_string())
-            .collect();
-        
-        // Check if we have input in the buffer
-        if self.input_buffer.is_empty() {
-            // Signal that we need input - for now just return error
-            return Err(format!("INPUT_NEEDED:{}", format_str));
+
+        // Handle free
+        if self.starts_with_keyword(statement, "free") {
+            return self.handle_free(statement);
         }
-        
-        // Parse format string and assign values
-        let input_line = self.input_buffer.remove(0);
-        let input_values: Vec<&str> = input_line.split_whitespace().collect();
-        
-        let format_specs: Vec<&str> = format_str.match_indices('%')
-            .map(|(i, _)| {
-                let remaining = &format_str[i..];
-                if let Some(end_idx) = remaining.find(|c: char| c.is_alphabetic()) {
-                    &remaining[..=end_idx]
-                } else {
-                    ""
-                }
-            })
-            .collect();
-        
-        if var_names.len() != format_specs.len() {
-            return Err(format!("scanf: format specifier count ({}) doesn't match variable count ({})", 
-                format_specs.len(), var_names.len()));
+
+        // Handle srand; rand() is evaluated within expressions, and a bare
+        // rand(); just advances the generator
+        if self.starts_with_keyword(statement, "srand") {
+            return self.handle_srand(statement);
         }
-        
-        for (i, var_name) in var_names.iter().enumerate() {
-            if i >= input_values.len() {
-                return Err(format!("scanf: not enough input values provided"));
-            }
-            
-            let format_spec = format_specs[i];
-            let input_val = input_values[i];
-            
-            // Parse based on format specifier
-            let value = if format_spec.contains('d') || format_spec.contains('i') {
-                Value::Int(input_val.parse::<i64>()
-                    .map_err(|_| format!("scanf: invalid integer: {}", input_val))?)
-            } else if format_spec.contains('f') || format_spec.contains("lf") {
-                Value::Float(input_val.parse::<f64>()
-                    .map_err(|_| format!("scanf: invalid float: {}", input_val))?)
-            } else if format_spec.contains('c') {
-                Value::Char(input_val.chars().next()
-                    .ok_or_else(|| "scanf: empty input for char".to_string())?)
-            } else if format_spec.contains('s') {
-                Value::String(input_val.to_string())
-            } else {
-                return Err(format!("scanf: unsupported format specifier: {}", format_spec));
-            };
-            
-            self.variables.insert(var_name.clone(), value.clone());
-            self.memory.update_variable_address(var_name, &value);
+        if self.parse_library_call(statement, &["rand"]).is_some_and(|(_, args)| args.trim().is_empty()) {
+            self.next_random();
+            return Ok(());
         }
         
-        Ok(())
+        self.handle_variable_statement(statement)
     }
 
-    fn handle_puts(&mut self, statement: &str) -> Result<(), String> {
-        let start = statement.find('(').ok_or("Invalid puts syntax")?;
-        let end = statement.rfind(')').ok_or("Invalid puts syntax")?;
-        let content = &statement[start + 1..end].trim_matches('"');
+    /// Handle a declaration, assignment or increment/decrement statement
+    fn handle_variable_statement(&mut self, statement: &str) -> Result<(), InterpError> {
+        // Handle variable declarations
+        if self.is_declaration(statement) {
+            return self.handle_declaration(statement);
+        }
         
-        self.output.push_str(content);
-        self.output.push('\n');
-        Ok(())
-    }
+        // Handle assignments with compound operators
+        if self.find_compound_assignment(statement).is_some() {
+            return self.handle_compound_assignment(statement);
+        }
 
-    fn handle_gets(&mut self, statement: &str) -> Result<(), String> {
-        // Parse gets(variable)
-        let start = statement.find('(').ok_or("Invalid gets syntax")?;
-        let end = statement.rfind(')').ok_or("Invalid gets syntax")?;
-        let var_name = statement[start + 1..end].trim();
-        
-        // Check if we have input in the buffer
-        if self.input_buffer.is_empty() {
-            return Err("INPUT_NEEDED:Enter a line of text".to_string());
+        // Handle assignments
+        if statement.contains('=') && !self.is_declaration(statement) && 
+           !statement.contains("==") && !statement.contains("!=") &&
+           !statement.contains("<=") && !statement.contains(">=") {
+            return self.handle_assignment(statement);
         }
-        
-        let input_line = self.input_buffer.remove(0);
-        let value = Value::String(input_line);
-        
-        self.variables.insert(var_name.to_string(), value.clone());
-        self.memory.update_variable_address(var_name, &value);
-        
-        Ok(())
-    }
 
-    fn handle_strlen(&mut self, statement: &str) -> Result<(), String> {
-        if statement.contains('=') {
-            let parts: Vec<&str> = statement.split('=').collect();
-            let var_part = parts[0].trim();
-            let var_name = if var_part.contains(' ') {
-                var_part.split_whitespace().last().unwrap()
-            } else {
-                var_part
-            };
-            
-            let strlen_part = parts[1].trim();
-            let start = strlen_part.find('(').ok_or("Invalid strlen syntax")?;
-            let end = strlen_part.rfind(')').ok_or("Invalid strlen syntax")?;
-            let arg = &strlen_part[start + 1..end].trim();
-            
-            let length = if arg.starts_with('"') {
-                arg.trim_matches('"').len() as i64
-            } else {
-                // It's a variable
-                if let Some(Value::String(s)) = self.variables.get(*arg) {
-                    s.len() as i64
-                } else {
-                    0
-                }
-            };
-            
-            self.variables.insert(var_name.to_string(), Value::Int(length));
+        // Handle increment/decrement
+        if statement.contains("++") || statement.contains("--") {
+            return self.handle_increment_decrement(statement);
         }
+
         Ok(())
     }
 
-    fn handle_strcpy(&mut self, statement: &str) -> Result<(), String> {
-        let start = statement.find('(').ok_or("Invalid strcpy syntax")?;
-        let end = statement.rfind(')').ok_or("Invalid strcpy syntax")?;
-        let args_str = &statement[start + 1..end];
-        let args = self.split_args(args_str);
-        
-        if args.len() >= 2 {
-            let dest = args[0].trim();
-            let src = args[1].trim();
-            
-            let src_value = if src.starts_with('"') {
-                Value::String(src.trim_matches('"').to_string())
-            } else {
-                self.variables.get(src).cloned().unwrap_or(Value::String(String::new()))
-            };
-            
-            self.variables.insert(dest.to_string(), src_value);
+    fn is_declaration(&self, statement: &str) -> bool {
+        self.starts_with_keyword(statement, "typedef") || self.split_type_prefix(statement).is_some()
+    }
+
+    /// Split a declaration into its canonical type and the declarators,
+    /// e.g. `unsigned long long x = 1` into `unsigned long` and `x = 1`
+    fn split_type_prefix<'a>(&self, statement: &'a str) -> Option<(String, &'a str)> {
+        let mut rest = statement;
+        let mut type_end = 0;
+        loop {
+            let word_len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            // `struct Point` names one type
+            if &rest[..word_len] == "struct" {
+                let tag = rest[word_len..].trim_start();
+                let tag_len = tag.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(tag.len());
+                if tag_len == 0 {
+                    break;
+                }
+                type_end = statement.len() - tag.len() + tag_len;
+                rest = tag[tag_len..].trim_start();
+                continue;
+            }
+            let is_type_word = matches!(&rest[..word_len],
+                "unsigned" | "signed" | "int" | "long" | "short" | "char" | "float" | "double" | "bool" | "_Bool" |
+                "const" | "static" | "volatile" | "register") || self.typedefs.contains_key(&rest[..word_len]);
+            if !is_type_word || !rest[word_len..].starts_with([' ', '*']) {
+                break;
+            }
+            type_end = statement.len() - rest.len() + word_len;
+            rest = rest[word_len..].trim_start();
         }
-        Ok(())
+        let var_type = self.resolve_type(&statement[..type_end])?;
+        Some((var_type, rest))
     }
 
-    fn handle_strcmp(&mut self, _statement: &str) -> Result<(), String> {
-        // This would typically return a value for comparison
-        // For now, simplified implementation
-        Ok(())
+    /// Canonical name of a type that may be written with a typedef name:
+    /// after `typedef struct Node *Link;`, `const Link` is `struct Node*`
+    fn resolve_type(&self, type_name: &str) -> Option<String> {
+        let mut words = type_name.split_whitespace()
+            .filter(|word| !matches!(*word, "const" | "static" | "volatile" | "register"));
+        if let (Some(word), None) = (words.next(), words.next()) {
+            if let Some(resolved) = self.typedefs.get(word) {
+                return Some(resolved.clone());
+            }
+        }
+        Self::canonical_type(type_name)
     }
 
-    fn handle_strcat(&mut self, statement: &str) -> Result<(), String> {
-        let start = statement.find('(').ok_or("Invalid strcat syntax")?;
-        let end = statement.rfind(')').ok_or("Invalid strcat syntax")?;
-        let args_str = &statement[start + 1..end];
-        let args = self.split_args(args_str);
-        
-        if args.len() >= 2 {
-            let dest = args[0].trim();
-            let src = args[1].trim();
-            
-            if let Some(Value::String(dest_str)) = self.variables.get(dest).cloned() {
-                let src_str = if src.starts_with('"') {
-                    src.trim_matches('"').to_string()
-                } else {
-                    match self.variables.get(src) {
-                        Some(Value::String(s)) => s.clone(),
-                        _ => String::new(),
-                    }
-                };
-                
-                let result = format!("{}{}", dest_str, src_str);
-                self.variables.insert(dest.to_string(), Value::String(result));
+    /// Canonical name of a basic type written with sign and size keywords:
+    /// `unsigned` is `unsigned int`, `long long int` is `long` and
+    /// `signed char` is `char`. Qualifiers such as `const` are dropped, and
+    /// a struct type is `struct Tag`.
+    fn canonical_type(type_name: &str) -> Option<String> {
+        let mut unsigned = false;
+        let mut size = None;
+        let mut base = None;
+        let mut tag = None;
+        let mut words = type_name.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "struct" => tag = Some(words.next()?),
+                "unsigned" => unsigned = true,
+                "signed" | "int" | "const" | "static" | "volatile" | "register" => {}
+                "long" | "short" => size = Some(word),
+                "char" | "float" | "double" | "bool" => base = Some(word),
+                "_Bool" => base = Some("bool"),
+                _ => return None,
             }
         }
-        Ok(())
+        if let Some(tag) = tag {
+            return (!unsigned && size.is_none() && base.is_none()).then(|| format!("struct {}", tag));
+        }
+        let base = match (base, size) {
+            _ if type_name.trim().is_empty() => return None,
+            (Some("double"), Some("long")) => "double",
+            (Some(base), None) => base,
+            (None, Some(size)) => size,
+            (None, None) => "int",
+            _ => return None,
+        };
+        Some(match base {
+            "int" | "long" | "short" | "char" if unsigned => format!("unsigned {}", base),
+            _ => base.to_string(),
+        })
     }
 
-    fn handle_math_function(&mut self, statement: &str) -> Result<(), String> {
-        if !statement.contains('=') {
-            return Ok(());
+    /// An integer as stored in `var_name`: wrapped to its declared width,
+    /// or collapsed to 0 or 1 for a bool
+    fn integer_for(&self, var_name: &str, value: i64) -> Value {
+        let var_type = self.variable_types.get(var_name).map_or("long", String::as_str);
+        Self::convert_value(var_type, Value::Int(value))
+    }
+
+    /// Truncate an integer to the width of its declared type, wrapping
+    /// like C: an `int` keeps 32 bits, an `unsigned short` 16 and a `char` 8
+    fn wrap_integer(type_name: &str, value: i64) -> i64 {
+        match type_name {
+            "int" => value as i32 as i64,
+            "short" => value as i16 as i64,
+            "char" => value as i8 as i64,
+            "unsigned int" => value as u32 as i64,
+            "unsigned short" => value as u16 as i64,
+            "unsigned char" => value as u8 as i64,
+            _ => value,
         }
+    }
 
-        let parts: Vec<&str> = statement.split('=').collect();
-        let var_type_name = parts[0].trim();
-        let var_name = if var_type_name.contains(' ') {
-            var_type_name.split_whitespace().last().unwrap()
-        } else {
-            var_type_name
-        };
+    fn handle_for_loop(&mut self, body: &str) -> Result<(), InterpError> {
+        let for_start = body.find("for").ok_or_else(|| InterpError::parse("Invalid for loop"))?;
+        let paren_start = body[for_start..].find('(').ok_or_else(|| InterpError::parse("Invalid for loop syntax"))? + for_start;
+        let paren_end = self.find_matching_paren(body, paren_start).ok_or_else(|| InterpError::parse("Invalid for loop syntax"))?;
         
-        let expr = parts[1].trim();
+        let for_header = &body[paren_start + 1..paren_end];
+        let parts = self.split_top_level(for_header, ';');
         
-        // Handle sqrt
-        if expr.contains("sqrt") {
-            let start = expr.find('(').ok_or("Invalid sqrt syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid sqrt syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).sqrt();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
+        if parts.len() != 3 {
+            return Err(InterpError::parse("Invalid for loop syntax"));
         }
 
-        // Handle pow
-        if expr.contains("pow") {
-            let start = expr.find('(').ok_or("Invalid pow syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid pow syntax")?;
-            let args_str = &expr[start + 1..end];
-            let args = self.split_args(args_str);
-            if args.len() == 2 {
-                let base = self.evaluate_numeric_expression(args[0].trim())?;
-                let exp = self.evaluate_numeric_expression(args[1].trim())?;
-                let result = (base as f64).powf(exp as f64);
-                self.variables.insert(var_name.to_string(), Value::Float(result));
-            }
-            return Ok(());
+        // Initialize; `i = 0, j = 10` runs each assignment in order, while a
+        // declaration splits its own declarators
+        let init = if self.is_declaration(&parts[0]) {
+            vec![parts[0].clone()]
+        } else {
+            self.split_top_level(&parts[0], ',')
+        };
+        for statement in &init {
+            self.execute_statement(statement)?;
         }
 
-        // Handle abs/fabs
-        if expr.contains("abs") || expr.contains("fabs") {
-            let start = expr.find('(').ok_or("Invalid abs syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid abs syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            if expr.contains("fabs") {
-                self.variables.insert(var_name.to_string(), Value::Float((value as f64).abs()));
-            } else {
-                self.variables.insert(var_name.to_string(), Value::Int(value.abs()));
-            }
-            return Ok(());
-        }
+        let loop_body = self.loop_body(body, paren_end, "for")?;
 
-        // Handle ceil
-        if expr.contains("ceil") {
-            let start = expr.find('(').ok_or("Invalid ceil syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid ceil syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).ceil();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+        // Execute loop
+        let condition = parts[1].as_str();
+        let increments = self.split_top_level(&parts[2], ',');
 
-        // Handle floor
-        if expr.contains("floor") {
-            let start = expr.find('(').ok_or("Invalid floor syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid floor syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).floor();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+        let max_iterations = self.options.max_iterations;
+        let mut iterations = 0;
 
-        // Handle exp
-        if expr.contains("exp") {
-            let start = expr.find('(').ok_or("Invalid exp syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid exp syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).exp();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+        while self.evaluate_condition(condition)? {
+            if iterations >= max_iterations {
+                return Err("Loop exceeded maximum iterations (possible infinite loop)".into());
+            }
+            iterations += 1;
 
-        // Handle log
-        if expr.contains("log") {
-            let start = expr.find('(').ok_or("Invalid log syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid log syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).ln();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+            self.loop_break = false;
+            self.loop_continue = false;
 
-        // Handle sin, cos, tan
-        if expr.contains("sin") {
-            let start = expr.find('(').ok_or("Invalid sin syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid sin syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).sin();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+            self.execute_statements(loop_body)?;
 
-        if expr.contains("cos") {
-            let start = expr.find('(').ok_or("Invalid cos syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid cos syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).cos();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
-        }
+            if self.loop_break {
+                self.loop_break = false;
+                break;
+            }
 
-        if expr.contains("tan") {
-            let start = expr.find('(').ok_or("Invalid tan syntax")?;
-            let end = expr.rfind(')').ok_or("Invalid tan syntax")?;
-            let arg = &expr[start + 1..end];
-            let value = self.evaluate_numeric_expression(arg)?;
-            let result = (value as f64).tan();
-            self.variables.insert(var_name.to_string(), Value::Float(result));
-            return Ok(());
+            if self.returning {
+                break;
+            }
+
+            for increment in &increments {
+                self.execute_statement(increment)?;
+            }
+            self.loop_continue = false;
         }
 
         Ok(())
     }
 
-    fn handle_rand(&mut self, statement: &str) -> Result<(), String> {
-        if statement.contains('=') {
-            let parts: Vec<&str> = statement.split('=').collect();
-            let var_part = parts[0].trim();
-            let var_name = if var_part.contains(' ') {
-                var_part.split_whitespace().last().unwrap()
-            } else {
-                var_part
-            };
-            
-            // Simple pseudo-random number generator
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            let mut hasher = DefaultHasher::new();
-            self.output.len().hash(&mut hasher);
-            let random_val = (hasher.finish() % 32768) as i64;
-            
-            self.variables.insert(var_name.to_string(), Value::Int(random_val));
+    /// The body of a `for` or `while` loop whose header ends at `paren_end`:
+    /// the inside of a braced block, or the single statement that follows,
+    /// as in `for (i = 0; i < 3; i++) sum += i;`
+    fn loop_body<'a>(&self, statement: &'a str, paren_end: usize, kind: &str) -> Result<&'a str, InterpError> {
+        let rest = statement[paren_end + 1..].trim();
+        if rest.starts_with('{') {
+            let body_end = self.find_matching_brace(rest, 0).ok_or_else(|| InterpError::parse("Unmatched braces"))?;
+            return Ok(&rest[1..body_end]);
         }
-        Ok(())
+        if rest.is_empty() {
+            return Err(InterpError::parse(format!("Invalid {} loop body", kind)));
+        }
+        Ok(rest)
     }
 
-    fn handle_srand(&mut self, _statement: &str) -> Result<(), String> {
-        // Acknowledge srand but don't implement seeding for now
-        Ok(())
-    }
+    fn handle_while_loop(&mut self, body: &str) -> Result<(), InterpError> {
+        let while_start = body.find("while").ok_or_else(|| InterpError::parse("Invalid while loop"))?;
+        let paren_start = body[while_start..].find('(').ok_or_else(|| InterpError::parse("Invalid while loop syntax"))? + while_start;
+        let paren_end = self.find_matching_paren(body, paren_start).ok_or_else(|| InterpError::parse("Invalid while loop syntax"))?;
+        
+        let condition = &body[paren_start + 1..paren_end];
 
-    fn split_args(&self, args: &str) -> Vec<String> {
-        let mut result = Vec::new();
-        let mut current = String::new();
-        let mut in_string = false;
-        let mut paren_depth = 0;
+        let loop_body = self.loop_body(body, paren_end, "while")?;
 
-        for ch in args.chars() {
-            match ch {
-                '"' => in_string = !in_string,
-                '(' => paren_depth += 1,
-                ')' => paren_depth -= 1,
-                ',' if !in_string && paren_depth == 0 => {
-                    if !current.is_empty() {
-                        result.push(current.trim().to_string());
-                        current = String::new();
-                    }
-                    continue;
-                }
-                _ => {}
+        let max_iterations = self.options.max_iterations;
+        let mut iterations = 0;
+
+        // Execute loop
+        while self.evaluate_condition(condition)? {
+            if iterations >= max_iterations {
+                return Err("Loop exceeded maximum iterations (possible infinite loop)".into());
+            }
+            iterations += 1;
+
+            self.loop_break = false;
+            self.loop_continue = false;
+
+            self.execute_statements(loop_body)?;
+
+            if self.loop_break {
+                self.loop_break = false;
+                break;
+            }
+
+            if self.returning {
+                break;
             }
-            current.push(ch);
-        }
 
-        if !current.is_empty() {
-            result.push(current.trim().to_string());
+            self.loop_continue = false;
         }
 
-        result
+        Ok(())
     }
 
-    fn handle_declaration(&mut self, statement: &str) -> Result<(), String> {
-        let statement = statement.trim();
+    fn handle_do_while_loop(&mut self, body: &str) -> Result<(), InterpError> {
+        // The statement is `do <body> while (cond)`; the body is a block or
+        // a single statement, and whatever loops it holds come before the
+        // final `while`
+        let rest = body.trim_start()["do".len()..].trim_start();
+        let (while_start, _) = self.find_top_level_operator(rest, &["while"], true)
+            .ok_or_else(|| InterpError::parse("Invalid do-while loop"))?;
+        let mut loop_body = rest[..while_start].trim();
+        if loop_body.starts_with('{') && self.find_matching_brace(loop_body, 0) == Some(loop_body.len() - 1) {
+            loop_body = &loop_body[1..loop_body.len() - 1];
+        }
         
-        let (var_type, rest) = if statement.starts_with("int ") {
-            ("int", statement.strip_prefix("int ").unwrap())
-        } else if statement.starts_with("float ") {
-            ("float", statement.strip_prefix("float ").unwrap())
-        } else if statement.starts_with("double ") {
-            ("double", statement.strip_prefix("double ").unwrap())
-        } else if statement.starts_with("char ") {
-            ("char", statement.strip_prefix("char ").unwrap())
-        } else if statement.starts_with("long ") {
-            ("long", statement.strip_prefix("long ").unwrap())
-        } else if statement.starts_with("short ") {
-            ("short", statement.strip_prefix("short ").unwrap())
-        } else {
-            return Err("Unknown type".to_string());
-        };
+        let paren_start = rest[while_start..].find('(').ok_or_else(|| InterpError::parse("Invalid do-while syntax"))? + while_start;
+        let paren_end = self.find_matching_paren(rest, paren_start).ok_or_else(|| InterpError::parse("Invalid do-while syntax"))?;
+        let condition = &rest[paren_start + 1..paren_end];
 
-        // Handle pointer declarations (e.g., int *ptr or int* ptr)
-        let rest = rest.trim();
-        let is_pointer = rest.starts_with('*');
-        let rest = if is_pointer {
-            rest.trim_start_matches('*').trim()
-        } else {
-            rest
-        };
-
-        // Handle array declarations
-        if rest.contains('[') && !is_pointer {
-            let bracket_pos = rest.find('[').unwrap();
-            let var_name = rest[..bracket_pos].trim().to_string();
-            let bracket_end = rest.find(']').ok_or("Invalid array syntax")?;
-            let size_str = &rest[bracket_pos + 1..bracket_end];
-            let size = if size_str.is_empty() {
-                0
-            } else {
-                self.evaluate_numeric_expression(size_str)? as usize
-            };
-            
-            let default_value = match var_type {
-                "float" | "double" => Value::Float(0.0),
-                "char" => Value::Char('\0'),
-                _ => Value::Int(0),
-            };
-            
-            let array = vec![default_value.clone(); size];
-            let array_value = Value::Array(array);
-            
-            // Store array in memory and create a "pointer" to it
-            let addr = self.memory.allocate(array_value.clone());
-            self.memory.address_map.insert(var_name.clone(), addr);
-            self.variables.insert(var_name, array_value);
-            return Ok(());
-        }
+        let max_iterations = self.options.max_iterations;
+        let mut iterations = 0;
 
-        if let Some(eq_pos) = rest.find('=') {
-            let var_name = rest[..eq_pos].trim().to_string();
-            let expr = rest[eq_pos + 1..].trim();
-            
-            let value = if is_pointer {
-                // Handle pointer initialization
-                self.evaluate_pointer_expression(expr)?
-            } else {
-                match var_type {
-                    "float" | "double" => {
-                        let num = self.evaluate_numeric_expression(expr)? as f64;
-                        Value::Float(num)
-                    },
-                    "char" => {
-                        if expr.starts_with('\'') {
-                            let ch = expr.trim_matches('\'').chars().next().unwrap_or('\0');
-                            Value::Char(ch)
-                        } else {
-                            let num = self.evaluate_numeric_expression(expr)?;
-                            Value::Char(num as u8 as char)
-                        }
-                    },
-                    _ => {
-                        let num = self.evaluate_numeric_expression(expr)?;
-                        Value::Int(num)
-                    }
-                }
-            };
-            
-            if !is_pointer {
-                // For non-pointers, store them in memory so they can be referenced
-                let addr = self.memory.get_address_of(&var_name, &value);
-                self.memory.update_variable_address(&var_name, &value);
-            }
-            
-            self.variables.insert(var_name, value);
-        } else {
-            // Just declaration without initialization
-            let var_name = rest.trim().to_string();
-            let value = if is_pointer {
-                Value::Pointer(0) // NULL pointer
-            } else {
-                match var_type {
-                    "float" | "double" => Value::Float(0.0),
-                    "char" => Value::Char('\0'),
-                    _ => Value::Int(0),
-                }
-            };
-            
-            if !is_pointer {
-                let addr = self.memory.get_address_of(&var_name, &value);
-                self.memory.update_variable_address(&var_name, &value);
+        loop {
+            if iterations >= max_iterations {
+                return Err("Loop exceeded maximum iterations (possible infinite loop)".into());
             }
-            
-            self.variables.insert(var_name, value);
-        }
-        
-        Ok(())
-    }
+            iterations += 1;
 
-    fn evaluate_pointer_expression(&mut self, expr: &str) -> Result<Value, String> {
-        let expr = expr.trim();
-        
-        // Handle NULL or 0
-        if expr == "NULL" || expr == "0" {
-            return Ok(Value::Pointer(0));
-        }
-        
-        // Handle address-of operator: &variable
-        if expr.starts_with('&') {
-            let var_name = expr[1..].trim();
-            
-            // Handle array element: &arr[index]
-            if var_name.contains('[') {
-                let bracket_pos = var_name.find('[').unwrap();
-                let array_name = var_name[..bracket_pos].trim();
-                let bracket_end = var_name.find(']').ok_or("Invalid array syntax")?;
-                let index_expr = &var_name[bracket_pos + 1..bracket_end];
-                let index = self.evaluate_numeric_expression(index_expr)? as usize;
-                
-                // Get base address of array and add offset
-                if let Some(&base_addr) = self.memory.address_map.get(array_name) {
-                    let element_addr = base_addr + (index as i64 * 8);
-                    return Ok(Value::Pointer(element_addr));
-                } else {
-                    return Err(format!("Variable '{}' not found", array_name));
-                }
-            }
-            
-            // Regular variable address
-            if let Some(value) = self.variables.get(var_name) {
-                let addr = self.memory.get_address_of(var_name, value);
-                return Ok(Value::Pointer(addr));
-            } else {
-                return Err(format!("Variable '{}' not found", var_name));
+            self.loop_break = false;
+            self.loop_continue = false;
+
+            self.execute_statements(loop_body)?;
+
+            if self.loop_break {
+                self.loop_break = false;
+                break;
             }
-        }
-        
-        // Handle dereference in expression (this is for pointer assignment)
-        if expr.starts_with('*') {
-            let ptr_expr = expr[1..].trim();
-            if let Some(Value::Pointer(addr)) = self.variables.get(ptr_expr) {
-                let value = self.memory.read(*addr)?;
-                return Ok(Value::Pointer(*addr)); // Return the address for pointer-to-pointer
+
+            if self.returning {
+                break;
             }
-        }
-        
-        // Handle direct pointer variable or expression
-        if let Some(value) = self.variables.get(expr) {
-            if let Value::Pointer(addr) = value {
-                return Ok(Value::Pointer(*addr));
+
+            self.loop_continue = false;
+
+            if !self.evaluate_condition(condition)? {
+                break;
             }
         }
-        
-        // Try to evaluate as numeric expression (cast to pointer)
-        let num = self.evaluate_numeric_expression(expr)?;
-        Ok(Value::Pointer(num))
+
+        Ok(())
     }
 
-    fn handle_compound_assignment(&mut self, statement: &str) -> Result<(), String> {
-        let ops = vec!["+=", "-=", "*=", "/=", "%="];
+    fn handle_if_else_statement(&mut self, body: &str) -> Result<(), InterpError> {
+        let tokens = lexer::tokenize(body);
+        let if_index = tokens.iter().position(|token| token.is_keyword("if"))
+            .ok_or_else(|| InterpError::parse("Invalid if statement"))?;
+        let paren_end = tokens.get(if_index + 1).filter(|token| token.is("("))
+            .and_then(|_| matching_token(&tokens, if_index + 1))
+            .ok_or_else(|| InterpError::parse("Invalid if syntax"))?;
+        let condition = &body[tokens[if_index + 1].end()..tokens[paren_end].start];
+
+        // Each body is a braced block or a single statement, which may be
+        // another if that takes the nearest else
+        let branch = |first: usize| {
+            let last = statement_end(&tokens, first).ok_or_else(|| InterpError::parse("Invalid if body"))?;
+            Ok::<_, InterpError>((&body[tokens[first].start..tokens[last].end()], last))
+        };
+        let (if_body, if_end) = branch(paren_end + 1)?;
+        let has_else = tokens.get(if_end + 1).is_some_and(|token| token.is_keyword("else"));
         
-        for op in ops {
-            if statement.contains(op) {
-                let parts: Vec<&str> = statement.splitn(2, op).collect();
-                if parts.len() == 2 {
-                    let var_name = parts[0].trim();
-                    let expr = parts[1].trim();
-                    
-                    let current_val = self.evaluate_numeric_expression(var_name)?;
-                    let expr_val = self.evaluate_numeric_expression(expr)?;
-                    
-                    let result = match op {
-                        "+=" => current_val + expr_val,
-                        "-=" => current_val - expr_val,
-                        "*=" => current_val * expr_val,
-                        "/=" => {
-                            if expr_val == 0 {
-                                return Err("Division by zero".to_string());
-                            }
-                            current_val / expr_val
-                        },
-                        "%=" => current_val % expr_val,
-                        _ => current_val,
-                    };
-                    
-                    self.variables.insert(var_name.to_string(), Value::Int(result));
-                    return Ok(());
-                }
-            }
+        if self.evaluate_condition(condition)? {
+            self.execute_branch(if_body)?;
+        } else if has_else {
+            let (else_body, _) = branch(if_end + 2)?;
+            self.execute_branch(else_body)?;
         }
-        
+
         Ok(())
     }
 
-    fn handle_assignment(&mut self, statement: &str) -> Result<(), String> {
-        let parts: Vec<&str> = statement.splitn(2, '=').collect();
-        if parts.len() != 2 {
-            return Err("Error: Invalid assignment syntax".to_string());
+    /// Run an if or else body: the statements of a braced block, or one
+    /// statement such as `y = 1;` or a nested `if`
+    fn execute_branch(&mut self, branch: &str) -> Result<(), InterpError> {
+        match branch.strip_prefix('{').and_then(|block| block.strip_suffix('}')) {
+            Some(block) => self.execute_statements(block),
+            None => self.execute_compound_statement(branch),
         }
+    }
+
+    fn handle_switch_statement(&mut self, body: &str) -> Result<(), InterpError> {
+        let switch_start = body.find("switch").ok_or_else(|| InterpError::parse("Invalid switch statement"))?;
+        let paren_start = body[switch_start..].find('(').ok_or_else(|| InterpError::parse("Invalid switch syntax"))? + switch_start;
+        let paren_end = self.find_matching_paren(body, paren_start).ok_or_else(|| InterpError::parse("Invalid switch syntax"))?;
         
-        let left = parts[0].trim();
-        let expr = parts[1].trim();
+        let switch_expr = &body[paren_start + 1..paren_end];
+        let switch_value = self.evaluate_numeric_expression(switch_expr)?;
 
-        // Handle pointer dereference assignment: *ptr = value
-        if left.starts_with('*') {
-            let ptr_name = left[1..].trim();
-            if let Some(Value::Pointer(addr)) = self.variables.get(ptr_name) {
-                let addr = *addr;
-                let value = if expr.starts_with('"') {
-                    Value::String(expr.trim_matches('"').to_string())
-                } else if expr.starts_with('\'') {
-                    Value::Char(expr.trim_matches('\'').chars().next().unwrap_or('\0'))
-                } else if expr.starts_with('&') {
-                    // Assigning an address
-                    self.evaluate_pointer_expression(expr)?
-                } else {
-                    // Try numeric or variable
-                    if let Some(var_value) = self.variables.get(expr) {
-                        var_value.clone()
-                    } else {
-                        let num = self.evaluate_numeric_expression(expr)?;
-                        Value::Int(num)
-                    }
-                };
-                
-                self.memory.write(addr, value.clone())?;
-                
-                // Update the variable map if this address corresponds to a variable
-                for (var_name, &var_addr) in &self.memory.address_map {
-                    if var_addr == addr {
-                        self.variables.insert(var_name.clone(), value.clone());
-                        break;
-                    }
-                }
-                
-                return Ok(());
-            } else {
-                return Err(format!("'{}' is not a valid pointer", ptr_name));
+        let body_start = body[paren_end..].find('{').ok_or_else(|| InterpError::parse("Invalid switch body"))? + paren_end;
+        let body_end = self.find_matching_brace(body, body_start).ok_or_else(|| InterpError::parse("Unmatched braces"))?;
+        let switch_body = &body[body_start + 1..body_end];
+
+        // Flatten the body into labels and the statements they precede
+        let mut labels: Vec<(Option<&str>, usize)> = Vec::new();
+        let mut statements: Vec<&str> = Vec::new();
+        for statement in self.split_statements(switch_body) {
+            let mut statement = statement;
+            while let Some((label, rest)) = self.split_case_label(statement) {
+                labels.push((label, statements.len()));
+                statement = rest;
+            }
+            if !statement.is_empty() {
+                statements.push(statement);
             }
         }
 
-        // Handle array element assignment
-        if left.contains('[') {
-            let bracket_pos = left.find('[').unwrap();
-            let var_name = left[..bracket_pos].trim();
-            let bracket_end = left.find(']').ok_or("Invalid array syntax")?;
-            let index_expr = &left[bracket_pos + 1..bracket_end];
-            let index = self.evaluate_numeric_expression(index_expr)? as usize;
-            
-            let value = if expr.starts_with('"') {
-                Value::String(expr.trim_matches('"').to_string())
-            } else if expr.starts_with('\'') {
-                Value::Char(expr.trim_matches('\'').chars().next().unwrap_or('\0'))
-            } else {
-                let num = self.evaluate_numeric_expression(expr)?;
-                Value::Int(num)
-            };
-            
-            if let Some(Value::Array(arr)) = self.variables.get_mut(var_name) {
-                if index < arr.len() {
-                    arr[index] = value.clone();
-                    
-                    // Update memory if this array has an address
-                    if let Some(&base_addr) = self.memory.address_map.get(var_name) {
-                        let element_addr = base_addr + (index as i64 * 8);
-                        if self.memory.heap.contains_key(&element_addr) {
-                            self.memory.write(element_addr, value)?;
-                        }
-                    }
+        // Jump to the matching case, else to default wherever it appears
+        let mut start = None;
+        for &(label, index) in &labels {
+            if let Some(case_expr) = label {
+                let case_value = if case_expr.starts_with('\'') {
+                    parse_char_literal(case_expr)? as i64
+                } else {
+                    self.evaluate_numeric_expression(case_expr)?
+                };
+                if case_value == switch_value {
+                    start = Some(index);
+                    break;
                 }
             }
-            return Ok(());
         }
-
-        let var_name = left.to_string();
-        
-        // Handle pointer assignment
-        if expr.starts_with('&') || (self.variables.get(&var_name).map(|v| matches!(v, Value::Pointer(_))).unwrap_or(false)) {
-            let value = self.evaluate_pointer_expression(expr)?;
-            self.variables.insert(var_name, value);
+        let start = start.or_else(|| labels.iter().find(|(label, _)| label.is_none()).map(|&(_, index)| index));
+        let Some(start) = start else {
             return Ok(());
-        }
-        
-        // Check if variable exists to determine type
-        if let Some(existing_value) = self.variables.get(&var_name).cloned() {
-            let value = match existing_value {
-                Value::Float(_) => {
-                    let num = self.evaluate_numeric_expression(expr)? as f64;
-                    Value::Float(num)
-                },
-                Value::Char(_) => {
-                    if expr.starts_with('\'') {
-                        let ch = expr.trim_matches('\'').chars().next().unwrap_or('\0');
-                        Value::Char(ch)
-                    } else {
-                        let num = self.evaluate_numeric_expression(expr)?;
-                        Value::Char(num as u8 as char)
-                    }
-                },
-                Value::String(_) => {
-                    if expr.starts_with('"') {
-                        Value::String(expr.trim_matches('"').to_string())
-                    } else {
-                        existing_value
-                    }
-                },
-                Value::Pointer(_) => {
-                    self.evaluate_pointer_expression(expr)?
-                },
-                _ => {
-                    let num = self.evaluate_numeric_expression(expr)?;
-                    Value::Int(num)
-                }
-            };
-            
-            self.variables.insert(var_name.clone(), value.clone());
-            
-            // Update memory
-            if let Some(&addr) = self.memory.address_map.get(&var_name) {
-                self.memory.write(addr, value)?;
+        };
+
+        // Fall through the following statements until a break
+        for statement in &statements[start..] {
+            self.execute_compound_statement(statement)?;
+            // continue belongs to the enclosing loop, so it leaves the
+            // switch with the flag still set for the loop to see
+            if self.returning || self.loop_continue {
+                break;
+            }
+            if self.loop_break {
+                self.loop_break = false;
+                break;
             }
-        } else {
-            // New variable
-            let value = if expr.starts_with('"') {
-                Value::String(expr.trim_matches('"').to_string())
-            } else if expr.starts_with('\'') {
-                Value::Char(expr.trim_matches('\'').chars().next().unwrap_or('\0'))
-            } else if expr.starts_with('&') {
-                self.evaluate_pointer_expression(expr)?
-            } else {
-                let num = self.evaluate_numeric_expression(expr)?;
-                Value::Int(num)
-            };
-            
-            let addr = self.memory.get_address_of(&var_name, &value);
-            self.memory.update_variable_address(&var_name, &value);
-            self.variables.insert(var_name, value);
         }
-        
+
         Ok(())
     }
 
-    fn evaluate_value_expression(&mut self, expr: &str) -> Result<Value, String> {
-        let expr = expr.trim();
-        
-        // Check if it's a string literal
-        if expr.starts_with('"') && expr.ends_with('"') {
-            return Ok(Value::String(expr.trim_matches('"').to_string()));
+    fn evaluate_condition(&mut self, condition: &str) -> Result<bool, InterpError> {
+        let mut condition = condition.trim();
+
+        // Strip redundant parentheses around the whole condition
+        while condition.starts_with('(') && self.find_matching_paren(condition, 0) == Some(condition.len() - 1) {
+            condition = condition[1..condition.len() - 1].trim();
         }
 
-        // Check if it's a char literal
-        if expr.starts_with('\'') && expr.ends_with('\'') {
-            let ch = expr.trim_matches('\'').chars().next().unwrap_or('\0');
-            return Ok(Value::Char(ch));
+        // The comma operator runs every operand but the last for its side
+        // effects; the last one is the condition
+        if self.find_top_level_operator(condition, &[","], false).is_some() {
+            let operands = self.split_top_level(condition, ',');
+            let (last, side_effects) = operands.split_last().expect("split yields at least one part");
+            for operand in side_effects {
+                self.execute_statement(operand)?;
+            }
+            return self.evaluate_condition(last);
         }
 
-        // Check if it's a variable
-        if let Some(value) = self.variables.get(expr) {
-            return Ok(value.clone());
+        // Handle logical operators, lowest precedence first. Both short-circuit.
+        if let Some((pos, op)) = self.find_top_level_operator(condition, &["||"], false) {
+            return Ok(self.evaluate_condition(&condition[..pos])? ||
+                self.evaluate_condition(&condition[pos + op.len()..])?);
         }
 
-        // Otherwise treat as numeric
-        let num = self.evaluate_numeric_expression(expr)?;
-        Ok(Value::Int(num))
-    }
+        if let Some((pos, op)) = self.find_top_level_operator(condition, &["&&"], false) {
+            return Ok(self.evaluate_condition(&condition[..pos])? &&
+                self.evaluate_condition(&condition[pos + op.len()..])?);
+        }
 
-    fn evaluate_numeric_expression(&mut self, expr: &str) -> Result<i64, String> {
-        let expr = expr.trim();
-        
-        // Check if it's a number
-        if let Ok(num) = expr.parse::<i64>() {
-            return Ok(num);
+        // Handle comparison operators; the rightmost split keeps them left-associative
+        let comparison = self.find_top_level_operator(condition, &["==", "!="], true)
+            .or_else(|| self.find_top_level_operator(condition, &["<=", ">=", "<", ">"], true));
+        if let Some((pos, op)) = comparison {
+            let (left, right) = (&condition[..pos], &condition[pos + op.len()..]);
+            if let Some(same) = self.compare_string_addresses(condition, left, right, op)? {
+                return Ok(same == (op == "=="));
+            }
+            if self.is_floating_expression(left) || self.is_floating_expression(right) {
+                let left = self.evaluate_float_expression(left)?;
+                let right = self.evaluate_float_expression(right)?;
+                return Ok(match op {
+                    "==" => left == right,
+                    "!=" => left != right,
+                    "<=" => left <= right,
+                    ">=" => left >= right,
+                    "<" => left < right,
+                    _ => left > right,
+                });
+            }
+            let left = self.evaluate_numeric_expression(left)?;
+            let right = self.evaluate_numeric_expression(right)?;
+            return Ok(match op {
+                "==" => left == right,
+                "!=" => left != right,
+                "<=" => left <= right,
+                ">=" => left >= right,
+                "<" => left < right,
+                _ => left > right,
+            });
         }
 
-        // Check if it's a float
-        if let Ok(num) = expr.parse::<f64>() {
-            return Ok(num as i64);
+        if let Some(inner) = condition.strip_prefix('!') {
+            let inner = self.evaluate_condition(inner.trim())?;
+            return Ok(!inner);
         }
-        
-        // Check if it's a variable
-        if let Some(value) = self.variables.get(expr) {
-            return match value {
-                Value::Int(i) => Ok(*i),
-                Value::Float(f) => Ok(*f as i64),
-                Value::Char(c) => Ok(*c as i64),
-                Value::Bool(b) => Ok(*b as i64),
-                Value::String(_) => Err("Cannot convert string to number".to_string()),
-                Value::Array(_) => Err("Cannot convert array to number".to_string()),
-                Value::Pointer(addr) => Ok(*addr), // Pointer can be used as integer (address)
+
+        // Handle simple boolean values
+        let val = self.evaluate_numeric_expression(condition)?;
+        Ok(val != 0)
+    }
+
+    /// Find one of `operators` outside any parentheses, brackets or literals,
+    /// returning its byte position; the last match if `last`, else the first.
+    /// `<` and `>` don't match inside `<<`, `>>`, `<=`, `>=` or `->`.
+    fn find_top_level_operator(&self, text: &str, operators: &[&'static str], last: bool) -> Option<(usize, &'static str)> {
+        let bytes = text.as_bytes();
+        let mut found = None;
+        let mut depth = 0;
+        let mut quote: Option<u8> = None;
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if let Some(q) = quote {
+                if byte == b'\\' {
+                    i += 1;
+                } else if byte == q {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            match byte {
+                b'"' | b'\'' => quote = Some(byte),
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth -= 1,
+                _ if depth == 0 => {
+                    let prev = if i > 0 { bytes[i - 1] } else { 0 };
+                    let next = bytes.get(i + 1).copied().unwrap_or(0);
+                    let matched = operators.iter().find(|op| {
+                        text[i..].starts_with(**op) && match **op {
+                            "<" => next != b'<' && next != b'=' && prev != b'<',
+                            ">" => next != b'>' && next != b'=' && prev != b'>' && prev != b'-',
+                            "<=" => prev != b'<',
+                            ">=" => prev != b'>',
+                            _ => true,
+                        }
+                    });
+                    if let Some(&op) = matched {
+                        found = Some((i, op));
+                        if !last {
+                            return found;
+                        }
+                        i += op.len();
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        found
+    }
+
+    /// Find the `=` of an assignment outside any parentheses, brackets or
+    /// literals, passing over the comparisons `==`, `!=`, `<=` and `>=`
+    fn find_assignment(&self, expr: &str) -> Option<usize> {
+        let mut pos = 0;
+        while let Some((offset, op)) = self.find_top_level_operator(&expr[pos..], &["==", "!=", "<=", ">=", "="], false) {
+            if op == "=" {
+                return Some(pos + offset);
+            }
+            pos += offset + op.len();
+        }
+        None
+    }
+
+    /// The operator of a compound assignment such as `x += 2` or
+    /// `flags <<= 1`, with the byte position of its `=`
+    fn find_compound_assignment(&self, statement: &str) -> Option<(usize, &'static str)> {
+        let pos = self.find_assignment(statement)?;
+        let target = &statement[..pos];
+        ["<<=", ">>=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^="].into_iter()
+            .find(|op| target.ends_with(&op[..op.len() - 1]))
+            .map(|op| (pos, op))
+    }
+
+    /// Split `cond ? a : b` at its top-level `?` and the `:` that pairs with
+    /// it, skipping the `?`/`:` of conditionals nested in either branch
+    fn split_conditional<'a>(&self, expr: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+        let (q_pos, _) = self.find_top_level_operator(expr, &["?"], false)?;
+        let mut nested = 0;
+        let mut pos = q_pos + 1;
+        while let Some((offset, op)) = self.find_top_level_operator(&expr[pos..], &["?", ":"], false) {
+            let at = pos + offset;
+            pos = at + 1;
+            if op == "?" {
+                nested += 1;
+            } else if expr[pos..].starts_with(':') {
+                // Not a conditional's colon, as in `::`
+                pos += 1;
+            } else if nested > 0 {
+                nested -= 1;
+            } else {
+                return Some((expr[..q_pos].trim(), expr[q_pos + 1..at].trim(), expr[at + 1..].trim()));
+            }
+        }
+        None
+    }
+
+    fn handle_increment_decrement(&mut self, statement: &str) -> Result<(), InterpError> {
+        match self.evaluate_increment(statement)? {
+            Some(_) => Ok(()),
+            None => Err(format!("Error: Cannot evaluate expression: {}", statement).into()),
+        }
+    }
+
+    /// Apply `++`/`--` in front of or after a variable, element or `*p`,
+    /// returning the new value for prefix and the old value for postfix.
+    /// None when `expr` is not an increment or decrement.
+    fn evaluate_increment(&mut self, expr: &str) -> Result<Option<Value>, InterpError> {
+        let expr = expr.trim();
+        let (target, step, postfix) = if let Some(target) = expr.strip_suffix("++") {
+            (target, 1, true)
+        } else if let Some(target) = expr.strip_suffix("--") {
+            (target, -1, true)
+        } else if let Some(target) = expr.strip_prefix("++") {
+            (target, 1, false)
+        } else if let Some(target) = expr.strip_prefix("--") {
+            (target, -1, false)
+        } else {
+            return Ok(None);
+        };
+        let target = target.trim();
+        
+        // Postfix binds tighter than *, so *p++ steps p rather than *p
+        let dereferenced = if postfix { None } else { target.strip_prefix('*').map(str::trim) };
+        let lvalue = dereferenced.unwrap_or(target);
+        let is_lvalue = lvalue.starts_with(|c: char| c.is_alphabetic() || c == '_') &&
+            (lvalue.chars().all(|c| c.is_alphanumeric() || c == '_') || self.parse_postfix(lvalue).is_some());
+        if !is_lvalue {
+            return Ok(None);
+        }
+        
+        // Resolved once, so a subscript such as b[i++] steps i once
+        let Some((place, target_type)) = self.resolve_place(target)? else {
+            return Ok(None);
+        };
+        let old = self.read_place(&place)?;
+        let new = match old {
+            Value::Float(f) => Value::Float(f + step as f64),
+            // A pointer steps by one element
+            Value::Pointer(addr) => Value::Pointer(addr + step * 8),
+            ref value => Value::Int(Self::as_i64(value) + step),
+        };
+        // Stored as the target's type, so a char stays a char and wraps
+        let new = match target_type {
+            Some(type_name) if !matches!(new, Value::Pointer(_)) => Self::convert_value(&type_name, new),
+            _ => new,
+        };
+        self.store_place(target, place, new.clone())?;
+        
+        Ok(Some(if postfix { old } else { new }))
+    }
+
+    fn handle_printf(&mut self, statement: &str) -> Result<(), InterpError> {
+        let start = statement.find('(')
+            .ok_or_else(|| "Error: Invalid printf syntax".to_string())?;
+        let end = statement.rfind(')')
+            .ok_or_else(|| "Error: Invalid printf syntax".to_string())?;
+        
+        let args = &statement[start + 1..end];
+        let parts = self.split_top_level(args, ',');
+        
+        if parts.is_empty() {
+            return Ok(());
+        }
+        let format = parts[0].trim_matches('"').trim_matches('\'');
+        let formatted = self.render_format(format, &parts[1..], "printf")?;
+        self.write_output(&formatted)
+    }
+
+    /// Render `format`, the text of a format literal without its quotes, as
+    /// `function` (printf or sprintf) would: decode its escapes and replace
+    /// each conversion with the value of its argument expression from `args`
+    fn render_format(&mut self, format: &str, args: &[String], function: &str) -> Result<String, InterpError> {
+        let mut result = format.to_string();
+        
+        // Handle escape sequences
+        result = result.replace("\\n", "\n");
+        result = result.replace("\\t", "\t");
+        result = result.replace("\\\\", "\\");
+        result = result.replace("\\r", "\r");
+        result = result.replace("\\0", "\0");
+        
+        // Replace each conversion in a single left-to-right pass, so every
+        // specifier takes the argument in its own position
+        let chars: Vec<char> = result.chars().collect();
+        let mut formatted = String::with_capacity(result.len());
+        let mut next_arg = 0;
+        let mut used = 0; // Arguments up to the last one a conversion has read
+        let mut missing = false;
+        let mut i = 0;
+        
+        while i < chars.len() {
+            if chars[i] != '%' {
+                formatted.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            if chars.get(i + 1) == Some(&'%') {
+                formatted.push('%');
+                i += 2;
+                continue;
+            }
+            
+            let spec_start = i;
+            i += 1;
+            
+            // POSIX positional argument: %2$d uses the second argument
+            let mut position = None;
+            let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 && chars.get(i + digits) == Some(&'$') {
+                let digits: String = chars[i..i + digits].iter().collect();
+                position = digits.parse::<usize>().ok().filter(|&position| position > 0);
+                i += digits.len() + 1;
+            }
+            
+            // Flags, width and precision: %-5d, %05d, %.2f
+            let mut left_align = false;
+            let mut zero_pad = false;
+            while i < chars.len() && matches!(chars[i], '-' | '0') {
+                left_align |= chars[i] == '-';
+                zero_pad |= chars[i] == '0';
+                i += 1;
+            }
+            // A * width or precision takes its value from the next argument;
+            // a negative width left-aligns and a negative precision is ignored
+            let width = if chars.get(i) == Some(&'*') {
+                i += 1;
+                let star_width = self.printf_star_argument(args, &mut next_arg)?;
+                left_align |= star_width < 0;
+                star_width.unsigned_abs() as usize
+            } else {
+                let width_digits: String = chars[i..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                i += width_digits.len();
+                width_digits.parse::<usize>().unwrap_or(0)
+            };
+            let mut precision = None;
+            if chars.get(i) == Some(&'.') && chars.get(i + 1) == Some(&'*') {
+                i += 2;
+                let star_precision = self.printf_star_argument(args, &mut next_arg)?;
+                precision = usize::try_from(star_precision).ok();
+            } else if chars.get(i) == Some(&'.') {
+                let precision_digits: String = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                i += 1 + precision_digits.len();
+                precision = Some(precision_digits.parse::<usize>().unwrap_or(0));
+            }
+            
+            // Length modifiers size integer conversions: %hhu, %hd, %lld, %zu.
+            // They don't change %f, so %lf prints like %f.
+            let length = match (chars.get(i), chars.get(i + 1)) {
+                (Some('h'), Some('h')) => Length::Char,
+                (Some('h'), _) => Length::Short,
+                (Some('l' | 'z' | 'j' | 't'), _) => Length::Long,
+                _ => Length::Int,
+            };
+            let modifier_len = match (chars.get(i), chars.get(i + 1)) {
+                (Some('h'), Some('h')) | (Some('l'), Some('l')) => 2,
+                (Some('h' | 'l' | 'z' | 'j' | 't' | 'L'), _) => 1,
+                _ => 0,
+            };
+            i += modifier_len;
+            
+            let conversion = match chars.get(i) {
+                Some(&c) if "dicfeEgGspuxXo".contains(c) => c,
+                // A conversion such as %a or %n would take an argument, so
+                // printing it as written would shift every later one
+                Some(&c) if c.is_ascii_alphabetic() => {
+                    return Err(InterpError::unsupported(format!("Error: {}: unsupported conversion %{}", function, c)));
+                }
+                _ => {
+                    // Not a conversion we know; print it as written
+                    formatted.extend(&chars[spec_start..i]);
+                    continue;
+                }
+            };
+            i += 1;
+            
+            let index = match position {
+                Some(position) => position - 1,
+                None => {
+                    next_arg += 1;
+                    next_arg - 1
+                }
+            };
+            used = used.max(index + 1);
+            match args.get(index) {
+                Some(arg) => {
+                    // %p reads its argument as an address, so arrays decay
+                    let value = if conversion == 'p' {
+                        self.evaluate_pointer_expression(arg)?
+                    } else {
+                        self.evaluate_value_expression(arg)?
+                    };
+                    let text = self.format_printf_value(conversion, precision, length, value)?;
+                    let padding = width.saturating_sub(text.chars().count());
+                    if left_align {
+                        formatted.push_str(&text);
+                        formatted.extend(std::iter::repeat_n(' ', padding));
+                    } else if zero_pad && "dicfeEgGuxXo".contains(conversion) {
+                        // Zeros go between the sign and the digits
+                        let digits_start = usize::from(text.starts_with('-'));
+                        formatted.push_str(&text[..digits_start]);
+                        formatted.extend(std::iter::repeat_n('0', padding));
+                        formatted.push_str(&text[digits_start..]);
+                    } else {
+                        formatted.extend(std::iter::repeat_n(' ', padding));
+                        formatted.push_str(&text);
+                    }
+                }
+                // No argument for this conversion; C would print garbage
+                None => {
+                    formatted.push_str("(missing)");
+                    missing = true;
+                }
+            }
+        }
+        
+        let used = used.max(next_arg);
+        let warning = if missing {
+            Some(format!("Warning: {} format \"{}\" has more conversions than arguments ({} given)", function, format, args.len()))
+        } else if args.len() > used {
+            Some(format!("Warning: {} format \"{}\" uses {} of the {} arguments given", function, format, used, args.len()))
+        } else {
+            None
+        };
+        if let Some(warning) = warning.filter(|warning| !self.warnings.contains(warning)) {
+            self.warnings.push(warning);
+        }
+        Ok(formatted)
+    }
+
+    /// Run `sprintf(dest, format, args...)`: render the format as printf
+    /// does into a char array, string or char pointer, and return the
+    /// number of chars written, not counting the '\0'
+    fn evaluate_sprintf(&mut self, call: &str) -> Result<i64, InterpError> {
+        let (_, args) = self.parse_library_call(call, &["sprintf"])
+            .ok_or_else(|| InterpError::parse(format!("Invalid sprintf syntax: {}", call)))?;
+        let parts = self.split_top_level(args, ',');
+        if parts.len() < 2 {
+            return Err("sprintf expects a destination and a format".into());
+        }
+        
+        let format = parts[1].trim_matches('"');
+        let text = self.render_format(format, &parts[2..], "sprintf")?;
+        let count = text.chars().count() as i64;
+        let dest = parts[0].trim();
+        self.check_writable(dest)?;
+        self.store_string(dest, text, "sprintf")?;
+        Ok(count)
+    }
+
+    /// Append `text` to the program's output. Past `max_output_bytes` only
+    /// what fits is kept and the program is stopped, so a runaway print loop
+    /// can't exhaust memory.
+    fn write_output(&mut self, text: &str) -> Result<(), InterpError> {
+        let limit = self.options.max_output_bytes;
+        if self.output.len() + text.len() <= limit {
+            self.output.push_str(text);
+            return Ok(());
+        }
+        let mut fits = limit.saturating_sub(self.output.len());
+        while !text.is_char_boundary(fits) {
+            fits -= 1;
+        }
+        self.output.push_str(&text[..fits]);
+        Err(format!("Error: output limit of {} bytes exceeded", limit).into())
+    }
+
+    /// Consume the argument for a `*` width or precision
+    fn printf_star_argument(&mut self, args: &[String], next_arg: &mut usize) -> Result<i64, InterpError> {
+        let arg = args.get(*next_arg)
+            .ok_or_else(|| InterpError::runtime("Error: printf: missing argument for '*'"))?;
+        *next_arg += 1;
+        let value = self.evaluate_value_expression(arg)?;
+        Ok(Self::as_i64(&value))
+    }
+
+    /// Render one printf argument for a conversion character such as 'd' or
+    /// 's'. `precision` is the decimals of %f and %e, the significant digits
+    /// of %g and the maximum length of %s;
+    /// %u, %x and %o reinterpret the value as unsigned at the width `length`
+    /// gives, and %hd and %hhd truncate it to a signed short or char.
+    fn format_printf_value(&self, conversion: char, precision: Option<usize>, length: Length, value: Value) -> Result<String, InterpError> {
+        let unsigned = |i: i64| match length {
+            Length::Char => i as u8 as u64,
+            Length::Short => i as u16 as u64,
+            Length::Int => i as u32 as u64,
+            Length::Long => i as u64,
+        };
+        let signed = |i: i64| match length {
+            Length::Char => i as i8 as i64,
+            Length::Short => i as i16 as i64,
+            Length::Int | Length::Long => i,
+        };
+        let text = match value {
+            Value::Int(_) | Value::Float(_) | Value::Char(_) | Value::Bool(_) if "eEgG".contains(conversion) => {
+                format_exponential(Self::as_f64(&value), conversion, precision)
+            }
+            Value::Int(i) if conversion == 'f' => format!("{:.*}", precision.unwrap_or(6), i as f64),
+            Value::Int(i) => match conversion {
+                'u' => unsigned(i).to_string(),
+                'x' => format!("{:x}", unsigned(i)),
+                'X' => format!("{:X}", unsigned(i)),
+                'c' => char::from_u32(i as u32).unwrap_or('?').to_string(),
+                'o' => format!("{:o}", unsigned(i)),
+                _ => signed(i).to_string(),
+            },
+            Value::Float(f) if conversion == 'f' => format!("{:.*}", precision.unwrap_or(6), f),
+            Value::Float(f) => f.to_string(),
+            Value::Char(c) => match conversion {
+                'd' | 'i' => signed(Self::char_as_i64(c)).to_string(),
+                'u' => unsigned(Self::char_as_i64(c)).to_string(),
+                'x' => format!("{:x}", unsigned(Self::char_as_i64(c))),
+                'X' => format!("{:X}", unsigned(Self::char_as_i64(c))),
+                'o' => format!("{:o}", unsigned(Self::char_as_i64(c))),
+                _ => c.to_string(),
+            },
+            Value::String(s) => s,
+            Value::Bool(b) => (b as i32).to_string(),
+            Value::Array(chars) if conversion == 's' => c_string(&chars),
+            Value::Array(_) => "[array]".to_string(),
+            // glibc prints a null string as "(null)", or nothing when the
+            // precision is too short to fit it
+            Value::Pointer(0) if conversion == 's' => match precision {
+                Some(max_len) if max_len < "(null)".len() => String::new(),
+                _ => "(null)".to_string(),
+            },
+            Value::Pointer(addr) if conversion == 's' => self.read_string(addr)?,
+            Value::Pointer(addr) if conversion == 'x' => format!("{:x}", addr),
+            Value::Pointer(addr) if conversion == 'X' => format!("{:X}", addr),
+            Value::Pointer(addr) => format!("0x{:x}", addr),
+            Value::Struct(_) => {
+                return Err(format!("Error: %{} can't print a whole struct; print its members", conversion).into());
+            }
+        };
+        
+        Ok(match precision {
+            Some(max_len) if conversion == 's' => text.chars().take(max_len).collect(),
+            _ => text,
+        })
+    }
+
+    /// Run `scanf("format", &var1, &var2, ...)` and return how many
+    /// conversions matched and were stored
+    fn evaluate_scanf(&mut self, call: &str) -> Result<i64, InterpError> {
+        let (_, args) = self.parse_library_call(call, &["scanf"])
+            .ok_or_else(|| InterpError::parse(format!("Invalid scanf syntax: {}", call)))?;
+        let parts = self.split_top_level(args, ',');
+        
+        if parts.is_empty() {
+            return Err(InterpError::parse("Invalid scanf: no arguments"));
+        }
+        
+        // First part is the format string
+        let format_str = parts[0].trim_matches('"').to_string();
+        let targets = &parts[1..];
+        
+        let spec_count = format_str.matches('%').count() - 2 * format_str.matches("%%").count();
+        if targets.len() != spec_count {
+            return Err(format!("scanf: format specifier count ({}) doesn't match variable count ({})", 
+                spec_count, targets.len()).into());
+        }
+        
+        // Walk the format string, consuming input as each directive requires
+        let mut var_index = 0;
+        let mut format_chars = format_str.chars().peekable();
+        
+        while let Some(fc) = format_chars.next() {
+            // Whitespace in the format matches any amount of input whitespace
+            if fc.is_whitespace() {
+                self.input.skip_whitespace();
+                continue;
+            }
+            
+            // A literal character (or %%) must match the next input character
+            let literal = if fc != '%' {
+                Some(fc)
+            } else if format_chars.next_if_eq(&'%').is_some() {
+                self.input.skip_whitespace();
+                Some('%')
+            } else {
+                None
+            };
+            if let Some(literal) = literal {
+                if self.input.peek() != Some(literal) {
+                    break;
+                }
+                self.input.next_char();
+                continue;
+            }
+            
+            // Skip length modifiers such as the `l` in %lf
+            let mut conversion = format_chars.next().unwrap_or('%');
+            while matches!(conversion, 'l' | 'h' | 'L') {
+                conversion = format_chars.next().unwrap_or('%');
+            }
+            
+            // Every conversion except %c skips leading whitespace
+            if conversion != 'c' {
+                self.input.skip_whitespace();
+            }
+            
+            if self.input.is_empty() {
+                if var_index == 0 {
+                    // Signal that we need input before anything was read
+                    return Err(InterpError::InputNeeded(format_str.to_string()));
+                }
+                // Out of input: report what matched so far
+                break;
+            }
+            
+            // A conversion that doesn't match leaves its input unread and
+            // ends the scan
+            let value = match conversion {
+                'd' | 'i' => {
+                    let token = self.input.take_while(|c, first| c.is_ascii_digit() || (first && (c == '-' || c == '+')));
+                    self.parse_input_token(token, |t| t.parse::<i64>().ok().map(Value::Int))
+                },
+                'x' | 'X' => {
+                    let token = self.input.take_while(|c, first| {
+                        c.is_ascii_hexdigit() || c == 'x' || c == 'X' || (first && (c == '-' || c == '+'))
+                    });
+                    self.parse_input_token(token, |t| {
+                        let (negative, digits) = match t.strip_prefix('-') {
+                            Some(rest) => (true, rest),
+                            None => (false, t.strip_prefix('+').unwrap_or(t)),
+                        };
+                        let digits = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")).unwrap_or(digits);
+                        let value = i64::from_str_radix(digits, 16).ok()?;
+                        Some(Value::Int(if negative { -value } else { value }))
+                    })
+                },
+                'f' | 'e' | 'g' => {
+                    let token = self.input.take_while(|c, first| {
+                        c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || (first && (c == '-' || c == '+'))
+                    });
+                    self.parse_input_token(token, |t| t.parse::<f64>().ok().map(Value::Float))
+                },
+                // %c takes the very next character, whitespace included
+                'c' => self.input.next_char().map(Value::Char),
+                's' => {
+                    let token = self.input.take_while(|c, _| !c.is_whitespace());
+                    Some(Value::String(token))
+                },
+                _ => return Err(InterpError::unsupported(format!("scanf: unsupported format specifier: %{}", conversion))),
+            };
+            
+            let Some(value) = value else {
+                break;
+            };
+            
+            self.store_scanned(&targets[var_index], value)?;
+            var_index += 1;
+        }
+        
+        Ok(var_index as i64)
+    }
+
+    /// Store a value scanf converted through its argument: `&x`, `&arr[i]`
+    /// or `&p->x` names the place, converted to its declared type, a
+    /// pointer points at it, and %s text fills a char array or buffer
+    fn store_scanned(&mut self, arg: &str, value: Value) -> Result<(), InterpError> {
+        let arg = arg.trim();
+        if let Value::String(text) = value {
+            // &buf is where buf starts, as buf itself is
+            let dest = arg.strip_prefix('&').map(str::trim)
+                .filter(|name| name.chars().all(|c| c.is_alphanumeric() || c == '_'))
+                .unwrap_or(arg);
+            self.check_writable(dest)?;
+            return self.store_string(dest, text, "scanf");
+        }
+        let (place, target_type) = match arg.strip_prefix('&') {
+            Some(target) => self.resolve_place(target)?
+                .ok_or_else(|| InterpError::parse(format!("scanf: invalid argument {}", arg)))?,
+            None => match self.evaluate_pointer_expression(arg)? {
+                Value::Pointer(addr) => (Place::Address(addr), Some(self.buffer_element_type(arg))),
+                _ => return Err(format!("scanf: '{}' is not a pointer", arg).into()),
+            },
+        };
+        let value = match target_type {
+            Some(type_name) => Self::convert_value(&type_name, value),
+            None => value,
+        };
+        self.store_place(arg.trim_start_matches('&'), place, value)
+    }
+
+    /// Convert a scanned token, pushing it back onto the input if it isn't valid
+    fn parse_input_token(&mut self, token: String, parse: impl Fn(&str) -> Option<Value>) -> Option<Value> {
+        let value = parse(&token);
+        if value.is_none() {
+            self.input.rewind(token.chars().count());
+        }
+        value
+    }
+
+    /// ungetc(c, stdin): push `c` back so the next read sees it first.
+    /// Pushing back EOF (-1) leaves the input unchanged, as in C.
+    fn handle_ungetc(&mut self, statement: &str) -> Result<(), InterpError> {
+        let start = statement.find('(').ok_or_else(|| InterpError::parse("Invalid ungetc syntax"))?;
+        let end = statement.rfind(')').ok_or_else(|| InterpError::parse("Invalid ungetc syntax"))?;
+        let args = self.split_top_level(&statement[start + 1..end], ',');
+        if args.len() != 2 || args[1].trim() != "stdin" {
+            return Err(InterpError::unsupported("ungetc: only ungetc(c, stdin) is supported"));
+        }
+        
+        let code = self.evaluate_numeric_expression(&args[0])?;
+        if code == -1 {
+            return Ok(());
+        }
+        let ch = u8::try_from(code).map(char::from)
+            .map_err(|_| format!("ungetc: invalid character {}", code))?;
+        self.input.unread(ch);
+        Ok(())
+    }
+
+    fn handle_puts(&mut self, statement: &str) -> Result<(), InterpError> {
+        let start = statement.find('(').ok_or_else(|| InterpError::parse("Invalid puts syntax"))?;
+        let end = statement.rfind(')').ok_or_else(|| InterpError::parse("Invalid puts syntax"))?;
+        let content = &statement[start + 1..end].trim_matches('"');
+        
+        self.write_output(&format!("{}\n", content))
+    }
+
+    fn handle_gets(&mut self, statement: &str) -> Result<(), InterpError> {
+        // Parse gets(variable)
+        let start = statement.find('(').ok_or_else(|| InterpError::parse("Invalid gets syntax"))?;
+        let end = statement.rfind(')').ok_or_else(|| InterpError::parse("Invalid gets syntax"))?;
+        let var_name = statement[start + 1..end].trim();
+        
+        // Check if we have input in the buffer
+        if self.input.is_empty() {
+            return Err(InterpError::InputNeeded("Enter a line of text".to_string()));
+        }
+        
+        // Read up to and including the newline, which is not stored
+        let value = Value::String(self.input.read_line());
+        
+        self.variables.insert(var_name.to_string(), value.clone());
+        self.memory.update_variable_address(var_name, &value);
+        
+        Ok(())
+    }
+
+    /// Run `strcpy(dest, src)` or `strcat(dest, src)`, or their bounded
+    /// forms `strncpy(dest, src, n)` and `strncat(dest, src, n)` that use at
+    /// most n chars of `src`, and return `dest`, as C returns the destination
+    /// pointer
+    fn evaluate_string_copy(&mut self, call: &str) -> Result<Value, InterpError> {
+        let (name, args) = self.parse_library_call(call, STRING_COPY_FUNCTIONS)
+            .ok_or_else(|| InterpError::parse(format!("Invalid string function syntax: {}", call)))?;
+        let args = self.split_top_level(args, ',');
+        let bounded = name.starts_with("strn");
+        let arity = if bounded { 3 } else { 2 };
+        if args.len() != arity {
+            return Err(format!("{} expects {} arguments", name, arity).into());
+        }
+        
+        let dest = args[0].as_str();
+        let mut src = self.string_argument(&args[1])?;
+        if bounded {
+            let n = self.evaluate_numeric_expression(&args[2])?;
+            let n = usize::try_from(n).map_err(|_| format!("{}: invalid length {}", name, n))?;
+            src = src.chars().take(n).collect();
+            if name == "strncpy" {
+                self.strncpy(dest, src, n)?;
+                return self.string_destination(dest);
+            }
+        }
+        let text = if name.ends_with("cat") { self.string_argument(dest)? + &src } else { src };
+        self.store_string(dest, text, name)?;
+        self.string_destination(dest)
+    }
+
+    /// What a string function returns for `dest`: the array or string
+    /// variable, or the address a pointer expression points at
+    fn string_destination(&mut self, dest: &str) -> Result<Value, InterpError> {
+        match self.variables.get(dest) {
+            Some(value) => Ok(value.clone()),
+            None => self.evaluate_pointer_expression(dest),
+        }
+    }
+
+    /// The address `dest` points at when it is a pointer rather than a char
+    /// array or string variable, so text written to it reaches the buffer
+    fn string_pointer(&mut self, dest: &str) -> Result<Option<i64>, InterpError> {
+        if let Some(Value::Array(_) | Value::String(_)) = self.variables.get(dest) {
+            return Ok(None);
+        }
+        match self.evaluate_pointer_expression(dest)? {
+            Value::Pointer(addr) => Ok(Some(addr)),
+            _ => Err(format!("'{}' is not a buffer", dest).into()),
+        }
+    }
+
+    /// Store strncpy's `text`, at most `n` chars, into `dest`. A char array
+    /// gets exactly `n` chars written, padded with '\0', so a `text` of `n`
+    /// chars leaves it unterminated as in C.
+    fn strncpy(&mut self, dest: &str, text: String, n: usize) -> Result<(), InterpError> {
+        if let Some(addr) = self.string_pointer(dest)? {
+            for (i, c) in text.chars().chain(std::iter::repeat('\0')).take(n).enumerate() {
+                self.write_through_pointer(addr + i as i64 * 8, Value::Char(c))?;
+            }
+            return Ok(());
+        }
+        let Some(Value::Array(chars)) = self.variables.get(dest) else {
+            return self.store_string(dest, text, "strncpy");
+        };
+        if n > chars.len() {
+            return Err(InterpError::memory(format!(
+                "strncpy: '{}' holds {} chars, too small to write {}", dest, chars.len(), n)));
+        }
+        let mut chars = chars.clone();
+        for (slot, c) in chars.iter_mut().zip(text.chars().chain(std::iter::repeat('\0')).take(n)) {
+            *slot = Value::Char(c);
+        }
+        let value = Value::Array(chars);
+        self.memory.update_variable_address(dest, &value);
+        self.variables.insert(dest.to_string(), value);
+        Ok(())
+    }
+
+    /// Run `memset(dest, byte, n)` or `memcpy(dest, src, n)` over an array
+    /// variable or a pointer into the heap, `n` bytes at the destination's
+    /// element size, and return `dest`
+    fn evaluate_memory_call(&mut self, call: &str) -> Result<Value, InterpError> {
+        let (name, args) = self.parse_library_call(call, &["memset", "memcpy"])
+            .ok_or_else(|| InterpError::parse(format!("Invalid memory function syntax: {}", call)))?;
+        let args = self.split_top_level(args, ',');
+        if args.len() != 3 {
+            return Err(format!("{} expects 3 arguments", name).into());
+        }
+        
+        let dest = args[0].as_str();
+        let element_type = self.buffer_element_type(dest);
+        let element_size = self.size_of_type(&element_type);
+        if element_size == 0 {
+            return Err(InterpError::unsupported(format!("Error: {} into a buffer of 0-byte {} elements", name, element_type)));
+        }
+        let bytes = self.evaluate_numeric_expression(&args[2])?;
+        if bytes < 0 || bytes % element_size != 0 {
+            return Err(InterpError::unsupported(format!(
+                "Error: {} of {} bytes covers part of a {}-byte element", name, bytes, element_size)));
+        }
+        let count = (bytes / element_size) as usize;
+        
+        let values = if name == "memset" {
+            // The fill is often a char literal such as '*'
+            let byte = Self::as_i64(&self.evaluate_value_expression(&args[1])?) as u8;
+            vec![self.repeated_byte_value(&element_type, byte); count]
+        } else {
+            self.read_buffer(&args[1], count)?
+        };
+        self.write_buffer(dest, values, name)?;
+        match self.variables.get(dest) {
+            Some(value) => Ok(value.clone()),
+            None => self.evaluate_pointer_expression(dest),
+        }
+    }
+
+    /// The element type of a buffer argument: that of an array, or what a
+    /// pointer points at, including `&arr[i]` and `p + n`
+    fn buffer_element_type(&self, arg: &str) -> String {
+        let arg = arg.trim().trim_start_matches(['&', '(']).trim_start();
+        let name_len = arg.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(arg.len());
+        let name = &arg[..name_len];
+        self.pointee_types.get(name)
+            .or_else(|| self.variable_types.get(name))
+            .cloned()
+            .unwrap_or_else(|| "char".to_string())
+    }
+
+    /// The value of an element of `type_name` whose every byte is `byte`,
+    /// as memset leaves it
+    fn repeated_byte_value(&self, type_name: &str, byte: u8) -> Value {
+        let size = self.size_of_type(type_name) as usize;
+        let bits = (0..size).fold(0u64, |bits, _| bits << 8 | byte as u64);
+        match type_name.trim_start_matches("unsigned ") {
+            "char" => Value::Char(byte as char),
+            "bool" | "_Bool" => Value::Bool(byte != 0),
+            "float" => Value::Float(f32::from_bits(bits as u32) as f64),
+            "double" => Value::Float(f64::from_bits(bits)),
+            _ => Value::Int(Self::wrap_integer(type_name, bits as i64)),
+        }
+    }
+
+    /// The first `count` elements of an array, a heap block or a string
+    /// literal, which includes its '\0'
+    fn read_buffer(&mut self, arg: &str, count: usize) -> Result<Vec<Value>, InterpError> {
+        let arg = arg.trim();
+        let elements: Vec<Value> = if arg.starts_with('"') && arg.ends_with('"') && arg.len() >= 2 {
+            arg[1..arg.len() - 1].chars().chain(std::iter::once('\0')).map(Value::Char).collect()
+        } else if let Some(Value::Array(elements)) = self.variables.get(arg) {
+            flatten_scalars(elements)
+        } else {
+            let Value::Pointer(addr) = self.evaluate_pointer_expression(arg)? else {
+                return Err(format!("'{}' is not a buffer", arg).into());
             };
+            return (0..count as i64).map(|i| self.read_address(addr + i * 8)).collect();
+        };
+        if elements.len() < count {
+            return Err(InterpError::memory(format!(
+                "Error: reading {} elements from '{}', which holds {}", count, arg, elements.len())));
         }
+        Ok(elements[..count].to_vec())
+    }
+
+    /// Overwrite the first elements of an array or heap block with `values`
+    fn write_buffer(&mut self, arg: &str, values: Vec<Value>, function: &str) -> Result<(), InterpError> {
+        let arg = arg.trim();
+        self.check_writable(arg)?;
+        if let Some(Value::Array(elements)) = self.variables.get_mut(arg) {
+            let size: usize = elements.iter().map(scalar_count).sum();
+            if values.len() > size {
+                return Err(InterpError::memory(format!(
+                    "Error: {} writes {} elements into '{}', which holds {}", function, values.len(), arg, size)));
+            }
+            let mut values = values.into_iter();
+            fill_scalars(elements, &mut values);
+            let array = Value::Array(elements.clone());
+            self.memory.update_variable_address(arg, &array);
+            return Ok(());
+        }
+        
+        let Value::Pointer(addr) = self.evaluate_pointer_expression(arg)? else {
+            return Err(format!("'{}' is not a buffer", arg).into());
+        };
+        for (i, value) in values.into_iter().enumerate() {
+            self.write_through_pointer(addr + i as i64 * 8, value)?;
+        }
+        Ok(())
+    }
+
+    /// Split `name(args)` into its parts when `name` is one of the library
+    /// `functions` and the call is the whole expression
+    fn parse_library_call<'a>(&self, expr: &'a str, functions: &[&str]) -> Option<(&'a str, &'a str)> {
+        let expr = expr.trim();
+        let open = expr.find('(')?;
+        let name = expr[..open].trim();
+        if !functions.contains(&name) || self.functions.contains_key(name) ||
+            self.find_matching_paren(expr, open) != Some(expr.len() - 1) {
+            return None;
+        }
+        Some((name, &expr[open + 1..expr.len() - 1]))
+    }
+
+    /// Evaluate `strcmp(a, b)` to -1, 0 or 1 by the first differing byte,
+    /// or `strncmp(a, b, n)` looking at no more than the first n bytes
+    fn evaluate_strcmp(&mut self, call: &str) -> Result<i64, InterpError> {
+        let (name, args) = self.parse_library_call(call, &["strcmp", "strncmp"])
+            .ok_or_else(|| InterpError::parse(format!("Invalid string function syntax: {}", call)))?;
+        let args = self.split_top_level(args, ',');
+        let arity = if name == "strncmp" { 3 } else { 2 };
+        if args.len() != arity {
+            return Err(format!("{} expects {} arguments", name, arity).into());
+        }
+        
+        let left = self.string_argument(&args[0])?;
+        let right = self.string_argument(&args[1])?;
+        let limit = match args.get(2) {
+            Some(n) => {
+                let n = self.evaluate_numeric_expression(n)?;
+                usize::try_from(n).map_err(|_| format!("strncmp: invalid length {}", n))?
+            }
+            None => usize::MAX,
+        };
+        let (left, right) = (&left.as_bytes()[..left.len().min(limit)], &right.as_bytes()[..right.len().min(limit)]);
+        Ok(match left.cmp(right) {
+            std::cmp::Ordering::Less => -1,
+            std::cmp::Ordering::Equal => 0,
+            std::cmp::Ordering::Greater => 1,
+        })
+    }
+
+    /// Evaluate `strspn(s, accept)` or `strcspn(s, reject)` to the length of
+    /// the leading span of `s` inside (or outside) the set, or
+    /// `strpbrk(s, set)` to a pointer to the first char of `s` in the set,
+    /// NULL when there is none
+    fn evaluate_string_scan(&mut self, call: &str) -> Result<Value, InterpError> {
+        let (name, args) = self.parse_library_call(call, STRING_SCAN_FUNCTIONS)
+            .ok_or_else(|| InterpError::parse(format!("Invalid string function syntax: {}", call)))?;
+        let args = self.split_top_level(args, ',');
+        if args.len() != 2 {
+            return Err(format!("{} expects 2 arguments", name).into());
+        }
+        
+        let text = self.string_argument(&args[0])?;
+        let set = self.string_argument(&args[1])?;
+        match name {
+            "strspn" => Ok(Value::Int(text.chars().take_while(|&c| set.contains(c)).count() as i64)),
+            "strcspn" => Ok(Value::Int(text.chars().take_while(|&c| !set.contains(c)).count() as i64)),
+            _ => {
+                let Some(index) = text.chars().position(|c| set.contains(c)) else {
+                    return Ok(Value::Pointer(0));
+                };
+                let base = match self.variables.get(args[0].as_str()) {
+                    Some(&Value::Pointer(addr)) => addr,
+                    Some(Value::Array(_)) => self.memory.address_map.get(args[0].as_str()).copied()
+                        .ok_or_else(|| format!("Variable '{}' not found", args[0]))?,
+                    _ => self.memory.allocate_string_literal(&text),
+                };
+                Ok(Value::Pointer(base + index as i64 * 8))
+            }
+        }
+    }
+
+    /// The text of a string argument: a literal, a string or char array
+    /// variable, a char pointer into memory, or the destination returned by
+    /// a nested strcpy/strcat
+    fn string_argument(&mut self, arg: &str) -> Result<String, InterpError> {
+        let arg = arg.trim();
+        if arg.starts_with('"') && arg.ends_with('"') && arg.len() >= 2 {
+            return Ok(arg[1..arg.len() - 1].to_string());
+        }
+        if self.parse_library_call(arg, STRING_COPY_FUNCTIONS).is_some() {
+            let dest = self.evaluate_string_copy(arg)?;
+            return self.string_of(&dest, arg);
+        }
+        
+        // An element or member such as names[i] or grid[1] or p->name
+        if let Some((base, ops)) = self.parse_postfix(arg) {
+            if let Some(value) = self.read_postfix(base, &ops)? {
+                return self.string_of(&value, arg);
+            }
+        }
+        match self.variables.get(arg) {
+            Some(value) => self.string_of(value, arg),
+            None => Err(format!("'{}' is not a string", arg).into()),
+        }
+    }
+
+    /// The text held by a string, char array or char pointer value
+    fn string_of(&self, value: &Value, arg: &str) -> Result<String, InterpError> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Array(chars) => Ok(c_string(chars)),
+            Value::Pointer(addr) => self.read_string(*addr),
+            _ => Err(format!("'{}' is not a string", arg).into()),
+        }
+    }
+
+    /// Store `text` into `dest` for `function`: a char array keeps its size
+    /// and gets the text plus a '\0', as does the buffer a pointer points
+    /// at, and a string variable is replaced
+    fn store_string(&mut self, dest: &str, text: String, function: &str) -> Result<(), InterpError> {
+        // A member or row such as p->name or names[i], resolved once
+        if self.parse_postfix(dest).is_some() {
+            if let Some((place, _)) = self.resolve_place(dest)? {
+                let value = match self.read_place(&place)? {
+                    Value::Array(chars) => Value::Array(Self::filled_chars(chars, dest, &text, function)?),
+                    Value::String(_) => Value::String(text),
+                    Value::Pointer(addr) => return self.write_string_at(addr, &text),
+                    _ => return Err(format!("'{}' is not a buffer", dest).into()),
+                };
+                return self.store_place(dest, place, value);
+            }
+        }
+        if let Some(addr) = self.string_pointer(dest)? {
+            return self.write_string_at(addr, &text);
+        }
+        let value = match self.variables.get(dest) {
+            Some(Value::Array(chars)) => Value::Array(Self::filled_chars(chars.clone(), dest, &text, function)?),
+            _ => Value::String(text),
+        };
+        self.memory.update_variable_address(dest, &value);
+        self.variables.insert(dest.to_string(), value);
+        Ok(())
+    }
+
+    /// The elements of char array `dest` with `text` and a '\0' written
+    /// from the start, or an error when they don't fit
+    fn filled_chars(mut chars: Vec<Value>, dest: &str, text: &str, function: &str) -> Result<Vec<Value>, InterpError> {
+        if text.chars().count() >= chars.len() {
+            return Err(InterpError::memory(format!(
+                "{}: '{}' holds {} chars, too small for \"{}\"", function, dest, chars.len(), text)));
+        }
+        for (slot, c) in chars.iter_mut().zip(text.chars().chain(std::iter::once('\0'))) {
+            *slot = Value::Char(c);
+        }
+        Ok(chars)
+    }
+
+    /// Write `text` and a '\0' to the chars from `addr` on
+    fn write_string_at(&mut self, addr: i64, text: &str) -> Result<(), InterpError> {
+        for (i, c) in text.chars().chain(std::iter::once('\0')).enumerate() {
+            self.write_through_pointer(addr + i as i64 * 8, Value::Char(c))?;
+        }
+        Ok(())
+    }
+
+    /// Split `sqrt(x)` into a <math.h> (or <stdlib.h> abs) function and
+    /// its arguments when the call is the whole expression
+    fn parse_math_call<'a>(&self, expr: &'a str) -> Option<(&'a str, &'a str)> {
+        let open = expr.find('(')?;
+        let name = expr[..open].trim();
+        let known = matches!(name, "abs" | "labs") || Self::is_double_math_function(name);
+        if !known || self.functions.contains_key(name) || self.find_matching_paren(expr, open) != Some(expr.len() - 1) {
+            return None;
+        }
+        Some((name, &expr[open + 1..expr.len() - 1]))
+    }
+
+    /// Whether `name` is a <math.h> function returning double
+    fn is_double_math_function(name: &str) -> bool {
+        matches!(name, "sqrt" | "pow" | "fabs" | "ceil" | "floor" | "exp" | "log" | "log10" | "log2" |
+            "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "fmod")
+    }
+
+    /// Call a math function; abs and labs return an integer, the rest a
+    /// double computed from double arguments
+    fn evaluate_math_call(&mut self, name: &str, args: &str) -> Result<Value, InterpError> {
+        let args = self.split_top_level(args, ',');
+        let arity = if matches!(name, "pow" | "fmod" | "atan2") { 2 } else { 1 };
+        if args.len() != arity {
+            return Err(format!("Error: {} expects {} argument(s) but {} were given", name, arity, args.len()).into());
+        }
+        if matches!(name, "abs" | "labs") {
+            return Ok(Value::Int(self.evaluate_numeric_expression(&args[0])?.wrapping_abs()));
+        }
+        
+        let x = self.evaluate_float_expression(&args[0])?;
+        Ok(Value::Float(match name {
+            "sqrt" => x.sqrt(),
+            "pow" => x.powf(self.evaluate_float_expression(&args[1])?),
+            "fmod" => match self.evaluate_float_expression(&args[1])? {
+                0.0 => return Err("Error: fmod by zero".into()),
+                y => x % y,
+            },
+            "fabs" => x.abs(),
+            "ceil" => x.ceil(),
+            "floor" => x.floor(),
+            "exp" => x.exp(),
+            "log" => x.ln(),
+            "log10" => x.log10(),
+            "log2" => x.log2(),
+            "sin" => x.sin(),
+            "cos" => x.cos(),
+            "asin" => x.asin(),
+            "acos" => x.acos(),
+            "atan" => x.atan(),
+            // atan2(y, x): the angle of the point (x, y)
+            "atan2" => x.atan2(self.evaluate_float_expression(&args[1])?),
+            _ => x.tan(),
+        }))
+    }
+
+    fn handle_free(&mut self, statement: &str) -> Result<(), InterpError> {
+        let start = statement.find('(').ok_or_else(|| InterpError::parse("Invalid free syntax"))?;
+        let end = statement.rfind(')').ok_or_else(|| InterpError::parse("Invalid free syntax"))?;
+        let arg = &statement[start + 1..end];
+        
+        match self.evaluate_pointer_expression(arg)? {
+            Value::Pointer(addr) => self.memory.free(addr),
+            _ => Err(InterpError::memory("free(): argument is not a pointer")),
+        }
+    }
+
+    /// Seed rand() from `srand(seed)`; the same seed gives the same sequence
+    fn handle_srand(&mut self, statement: &str) -> Result<(), InterpError> {
+        let start = statement.find('(').ok_or_else(|| InterpError::parse("Invalid srand syntax"))?;
+        let end = statement.rfind(')').ok_or_else(|| InterpError::parse("Invalid srand syntax"))?;
+        self.rand_state = self.evaluate_numeric_expression(&statement[start + 1..end])? as u32;
+        Ok(())
+    }
+
+    /// Advance rand()'s generator and return its next value in
+    /// [0, RAND_MAX], using the linear congruential generator from the C
+    /// standard's example implementation
+    fn next_random(&mut self) -> i64 {
+        self.rand_state = self.rand_state.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.rand_state / 65536 % (RAND_MAX as u32 + 1)) as i64
+    }
+
+    /// Split on `separator` where it is not nested in brackets or literals
+    fn split_top_level(&self, text: &str, separator: char) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        let mut prev_char = '\0';
+
+        for ch in text.chars() {
+            match quote {
+                Some(q) if ch == q && prev_char != '\\' => quote = None,
+                Some(_) => {}
+                None => match ch {
+                    '"' | '\'' => quote = Some(ch),
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => depth -= 1,
+                    _ if ch == separator && depth == 0 => {
+                        parts.push(current.trim().to_string());
+                        current.clear();
+                        prev_char = ch;
+                        continue;
+                    }
+                    _ => {}
+                },
+            }
+            // A backslash escaping another backslash does not escape the next char
+            prev_char = if prev_char == '\\' && ch == '\\' { '\0' } else { ch };
+            current.push(ch);
+        }
+        parts.push(current.trim().to_string());
+
+        parts
+    }
+
+    fn handle_declaration(&mut self, statement: &str) -> Result<(), InterpError> {
+        let statement = statement.trim();
+        if self.starts_with_keyword(statement, "typedef") {
+            return self.define_typedef(statement["typedef".len()..].trim());
+        }
+        if self.is_struct_definition(statement) {
+            return self.define_struct(statement);
+        }
+        
+        let (var_type, rest) = self.split_type_prefix(statement)
+            .ok_or_else(|| InterpError::parse("Unknown type"))?;
+        // A pointer typedef, as in `typedef int *IntPtr`, declares pointers
+        let typedef_pointer = var_type.ends_with('*');
+        let var_type = var_type.trim_end_matches('*');
+        let specifiers = &statement[..statement.len() - rest.len()];
+        let is_const = specifiers.split_whitespace().any(|word| word == "const");
+
+        // Declare each of `int i, j = 2, *p;` separately
+        let declarators = self.split_top_level(rest, ',');
+        if declarators.len() > 1 {
+            for declarator in declarators {
+                self.handle_declaration(&format!("{} {}", specifiers, declarator))?;
+            }
+            return Ok(());
+        }
+
+        // Handle pointer declarations (e.g., int *ptr or int* ptr)
+        let rest = rest.trim();
+        let is_pointer = typedef_pointer || rest.starts_with('*');
+        let rest = if is_pointer {
+            rest.trim_start_matches('*').trim()
+        } else {
+            rest
+        };
+        let declared_name = rest.split(['=', '[']).next().unwrap_or(rest).trim();
+        let undefined_struct = var_type.strip_prefix("struct ").is_some_and(|tag| !self.structs.contains_key(tag));
+        if undefined_struct && !is_pointer {
+            return Err(InterpError::parse(format!("Error: storage size of '{}' isn't known", declared_name)));
+        }
+        // A '[' after the '=' is part of the initializer
+        let is_array = match (rest.find('['), rest.find('=')) {
+            (Some(bracket), Some(eq)) => bracket < eq,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if is_pointer && !is_array {
+            self.pointee_types.insert(declared_name.to_string(), var_type.to_string());
+        } else if is_pointer {
+            // `char *names[3]` is an array whose elements are char pointers
+            self.variable_types.insert(declared_name.to_string(), format!("{}*", var_type));
+        } else {
+            self.variable_types.insert(declared_name.to_string(), var_type.to_string());
+        }
+        // `const char *s` points at constant chars; s itself may change
+        if is_const && !is_pointer {
+            self.constants.insert(declared_name.to_string());
+        } else {
+            self.constants.remove(declared_name);
+        }
+
+        // Handle array declarations
+        if is_array {
+            let var_type = self.variable_types[declared_name].clone();
+            let var_type = var_type.as_str();
+            let bracket_pos = rest.find('[').unwrap();
+            let var_name = rest[..bracket_pos].trim().to_string();
+            // Each [size] after the first is an inner dimension: grid[3][4]
+            let mut sizes = Vec::new();
+            let mut after = &rest[bracket_pos..];
+            while let Some(size_str) = after.strip_prefix('[') {
+                let bracket_end = size_str.find(']').ok_or_else(|| InterpError::parse("Invalid array syntax"))?;
+                sizes.push(&size_str[..bracket_end]);
+                after = size_str[bracket_end + 1..].trim_start();
+            }
+            let mut inner_dims = Vec::new();
+            for size_str in &sizes[1..] {
+                inner_dims.push(self.array_length(&var_name, size_str, None)?);
+            }
+            let initializer = match after.strip_prefix('=') {
+                Some(init) => Some(self.evaluate_array_initializer(var_type, init.trim(), &inner_dims)?),
+                None => None,
+            };
+            let size = self.array_length(&var_name, sizes[0], initializer.as_deref())?;
+            
+            let mut array = initializer.unwrap_or_default();
+            array.resize(size, self.zero_array(var_type, &inner_dims));
+            let array_value = Value::Array(array);
+            
+            // Store array in memory and create a "pointer" to it. The slots
+            // &arr[1], &arr[2], ... point at are reserved so that nothing
+            // else is placed there.
+            let addr = self.memory.allocate(array_value.clone());
+            self.memory.next_address += 8 * (scalar_count(&array_value).max(1) - 1) as i64;
+            self.memory.address_map.insert(var_name.clone(), addr);
+            self.variables.insert(var_name, array_value);
+            return Ok(());
+        }
+
+        if let Some(eq_pos) = rest.find('=') {
+            let var_name = rest[..eq_pos].trim().to_string();
+            let expr = rest[eq_pos + 1..].trim();
+            
+            let value = if is_pointer {
+                // Handle pointer initialization
+                self.evaluate_pointer_initializer(&var_name, expr)?
+            } else {
+                self.evaluate_typed_initializer(var_type, expr)?
+            };
+            
+            if !is_pointer {
+                // For non-pointers, store them in memory so they can be referenced
+                self.memory.get_address_of(&var_name, &value);
+                self.memory.update_variable_address(&var_name, &value);
+            }
+            
+            self.variables.insert(var_name, value);
+        } else {
+            // Just declaration without initialization
+            let var_name = rest.trim().to_string();
+            let value = if is_pointer {
+                Value::Pointer(0) // NULL pointer
+            } else {
+                match var_type.trim_start_matches("unsigned ") {
+                    "float" | "double" => Value::Float(0.0),
+                    "char" => Value::Char('\0'),
+                    "bool" => Value::Bool(false),
+                    _ if var_type.starts_with("struct ") => self.zero_struct(var_type),
+                    _ => Value::Int(0),
+                }
+            };
+            
+            if !is_pointer {
+                self.memory.get_address_of(&var_name, &value);
+                self.memory.update_variable_address(&var_name, &value);
+            }
+            
+            self.variables.insert(var_name, value);
+        }
+        
+        Ok(())
+    }
+
+    /// Store `value` at `addr`, updating the variable that lives there,
+    /// which may belong to a caller
+    fn write_through_pointer(&mut self, addr: i64, value: Value) -> Result<(), InterpError> {
+        if let Some((frame, name, index)) = self.array_slot(addr) {
+            let variables = match frame {
+                Some(frame) => &mut self.call_stack[frame].variables,
+                None => &mut self.variables,
+            };
+            if let Some(slot) = variables.get_mut(&name).and_then(|array| scalar_at_mut(array, index)) {
+                *slot = value;
+            }
+            return Ok(());
+        }
+        self.memory.write(addr, value.clone())?;
+        
+        for (var_name, &var_addr) in &self.memory.address_map {
+            if var_addr == addr {
+                self.variables.insert(var_name.clone(), value.clone());
+                break;
+            }
+        }
+        for frame in &mut self.call_stack {
+            for (var_name, &var_addr) in &frame.address_map {
+                if var_addr == addr {
+                    frame.variables.insert(var_name.clone(), value.clone());
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The array whose elements span `addr`, as the caller frame holding it
+    /// (None for the current scope), its name and the element's position
+    /// among its scalars. Arrays live in their variables, so pointers into
+    /// them are resolved here rather than through the heap.
+    fn array_slot(&self, addr: i64) -> Option<(Option<usize>, String, usize)> {
+        let scopes = std::iter::once((None, &self.variables, &self.memory.address_map))
+            .chain(self.call_stack.iter().enumerate().rev()
+                .map(|(i, frame)| (Some(i), &frame.variables, &frame.address_map)));
+        for (frame, variables, address_map) in scopes {
+            for (name, &base) in address_map {
+                if let Some(array @ Value::Array(_)) = variables.get(name) {
+                    let offset = addr - base;
+                    if offset >= 0 && offset % 8 == 0 && ((offset / 8) as usize) < scalar_count(array) {
+                        return Some((frame, name.clone(), (offset / 8) as usize));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether `operand` is an address: a pointer, an array name or `&x`
+    fn is_address_operand(&self, operand: &str) -> bool {
+        let operand = operand.trim();
+        operand.starts_with('&') || matches!(self.variables.get(operand), Some(Value::Pointer(_) | Value::Array(_)))
+    }
+
+    /// The value at `addr`, which may be an element of an array variable
+    fn read_address(&self, addr: i64) -> Result<Value, InterpError> {
+        let Some((frame, name, index)) = self.array_slot(addr) else {
+            return self.memory.read(addr);
+        };
+        let variables = match frame {
+            Some(frame) => &self.call_stack[frame].variables,
+            None => &self.variables,
+        };
+        Ok(scalar_at(&variables[&name], index).cloned().unwrap_or(Value::Int(0)))
+    }
+
+    /// Read the NUL-terminated string starting at `addr`
+    fn read_string(&self, addr: i64) -> Result<String, InterpError> {
+        let mut result = String::new();
+        let mut addr = addr;
+        loop {
+            match self.read_address(addr)? {
+                Value::Char('\0') => break,
+                Value::Char(c) => result.push(c),
+                Value::Int(0) => break,
+                Value::Int(i) => result.push(char::from_u32(i as u32).unwrap_or('?')),
+                _ => return Err(InterpError::memory(format!("Invalid string data at address 0x{:x}", addr))),
+            }
+            addr += 8;
+        }
+        Ok(result)
+    }
+
+    /// The members of the struct of type `struct_type` at `addr`. A malloc'd
+    /// block not yet written reads as a struct of zeros.
+    fn read_struct(&self, addr: i64, struct_type: &str) -> Result<HashMap<String, Value>, InterpError> {
+        match self.read_address(addr)? {
+            Value::Struct(fields) => Ok(fields),
+            _ if !self.memory.heap.contains_key(&addr) => match self.zero_struct(struct_type) {
+                Value::Struct(fields) => Ok(fields),
+                _ => unreachable!("zero_struct returns a struct"),
+            },
+            _ => Err(InterpError::memory(format!("Error: 0x{:x} does not hold a {}", addr, struct_type))),
+        }
+    }
+
+    /// Evaluate a value stored into pointer `var_name`; calloc zeroes the
+    /// block with the zero of the pointer's element type
+    fn evaluate_pointer_initializer(&mut self, var_name: &str, expr: &str) -> Result<Value, InterpError> {
+        let mut call = expr.trim();
+        if call.starts_with('(') {
+            if let Some(close) = self.find_matching_paren(call, 0) {
+                call = call[close + 1..].trim();
+            }
+        }
+        if !self.starts_with_keyword(call, "calloc") {
+            return self.evaluate_pointer_expression(expr);
+        }
+
+        let start = call.find('(').ok_or_else(|| InterpError::parse("Invalid calloc syntax"))?;
+        let end = call.rfind(')').ok_or_else(|| InterpError::parse("Invalid calloc syntax"))?;
+        let args = self.split_top_level(&call[start + 1..end], ',');
+        if args.len() != 2 {
+            return Err("calloc expects 2 arguments".into());
+        }
+        let count = self.evaluate_numeric_expression(&args[0])?;
+        let size = self.evaluate_numeric_expression(&args[1])?;
+        if count < 0 || size < 0 {
+            return Err(InterpError::memory(format!("calloc: invalid size {} x {}", count, size)));
+        }
+        let zero = match self.pointee_types.get(var_name).map(String::as_str) {
+            Some("float") | Some("double") => Value::Float(0.0),
+            Some("char") => Value::Char('\0'),
+            _ => Value::Int(0),
+        };
+        Ok(Value::Pointer(self.memory.calloc(count as usize, size as usize, zero)))
+    }
+
+    /// Evaluate an initializer and convert it to the declared scalar type
+    fn evaluate_typed_initializer(&mut self, var_type: &str, expr: &str) -> Result<Value, InterpError> {
+        Ok(match var_type.trim_start_matches("unsigned ") {
+            "float" | "double" => Value::Float(self.evaluate_float_expression(expr)?),
+            "char" => {
+                let value = self.evaluate_value_expression(expr)?;
+                Self::convert_value(var_type, value)
+            },
+            // Any nonzero value converts to true
+            "bool" => Value::Bool(self.evaluate_condition(expr)?),
+            _ if var_type.starts_with("struct ") => self.evaluate_struct_initializer(var_type, expr)?,
+            _ if var_type.ends_with('*') => self.evaluate_pointer_expression(expr)?,
+            _ => Value::Int(Self::wrap_integer(var_type, self.evaluate_numeric_expression(expr)?)),
+        })
+    }
+
+    /// Evaluate an array initializer: `{1, 2, 3}`, or a string literal for
+    /// a char array, which includes the terminating '\0'. With `inner_dims`,
+    /// each element is itself an array of those dimensions, written as
+    /// `{1, 2}` or, like C allows, as that many elements left unbraced.
+    fn evaluate_array_initializer(&mut self, var_type: &str, init: &str, inner_dims: &[usize]) -> Result<Vec<Value>, InterpError> {
+        if var_type.trim_start_matches("unsigned ") == "char" && init.starts_with('"') && init.ends_with('"') && init.len() >= 2 {
+            return Ok(init[1..init.len() - 1].chars().chain(std::iter::once('\0')).map(Value::Char).collect());
+        }
+        
+        let inner = init.strip_prefix('{').and_then(|init| init.strip_suffix('}'))
+            .ok_or_else(|| format!("Invalid array initializer: {}", init))?;
+        let mut elements = self.split_top_level(inner, ',');
+        // A trailing comma as in {1, 2, 3,} adds no element
+        if elements.last().is_some_and(|element| element.is_empty()) {
+            elements.pop();
+        }
+        let Some((&row_len, rest_dims)) = inner_dims.split_first() else {
+            return elements.iter()
+                .map(|element| self.evaluate_typed_initializer(var_type, element))
+                .collect();
+        };
+        
+        let flat_len: usize = inner_dims.iter().product();
+        let mut rows = Vec::new();
+        let mut i = 0;
+        while i < elements.len() {
+            let row_init = if elements[i].starts_with('{') || elements[i].starts_with('"') {
+                i += 1;
+                elements[i - 1].clone()
+            } else {
+                let end = (i + flat_len).min(elements.len());
+                let row = format!("{{{}}}", elements[i..end].join(", "));
+                i = end;
+                row
+            };
+            let mut row = self.evaluate_array_initializer(var_type, &row_init, rest_dims)?;
+            if row.len() > row_len {
+                return Err(format!("Error: excess elements in array initializer {}", row_init).into());
+            }
+            row.resize(row_len, self.zero_array(var_type, rest_dims));
+            rows.push(Value::Array(row));
+        }
+        Ok(rows)
+    }
+
+    /// The zero of `var_type`, or an array of them with dimensions `dims`
+    fn zero_array(&self, var_type: &str, dims: &[usize]) -> Value {
+        match dims.split_first() {
+            Some((&len, rest)) => Value::Array(vec![self.zero_array(var_type, rest); len]),
+            None if var_type.ends_with('*') => Value::Pointer(0),
+            None => match var_type.trim_start_matches("unsigned ") {
+                "float" | "double" => Value::Float(0.0),
+                "char" => Value::Char('\0'),
+                _ if var_type.starts_with("struct ") => self.zero_struct(var_type),
+                _ => Value::Int(0),
+            },
+        }
+    }
+
+    /// A struct of `type_name` with every member zero, as members left out
+    /// of an initializer are
+    fn zero_struct(&self, type_name: &str) -> Value {
+        let members = type_name.strip_prefix("struct ").and_then(|tag| self.structs.get(tag));
+        Value::Struct(members.into_iter().flatten()
+            .map(|member| (member.name.clone(), self.zero_array(&member.type_name, &member.dims)))
+            .collect())
+    }
+
+    /// Whether `item` defines a struct, as `struct Point { int x, y; }` does,
+    /// rather than declaring a variable of one
+    fn is_struct_definition(&self, item: &str) -> bool {
+        if !self.starts_with_keyword(item, "struct") {
+            return false;
+        }
+        let head = &item["struct".len()..];
+        head.find('{').is_some_and(|open| head[..open].trim().chars().all(|c| c.is_alphanumeric() || c == '_'))
+    }
+
+    /// Record the members of `struct Tag { ... }`, then declare any variables
+    /// that follow the closing brace, as in `struct Point { int x, y; } origin`
+    fn define_struct(&mut self, item: &str) -> Result<(), InterpError> {
+        let open = item.find('{').ok_or_else(|| InterpError::parse("Invalid struct definition"))?;
+        let close = self.find_matching_brace(item, open)
+            .ok_or_else(|| InterpError::parse("Error: unterminated struct definition"))?;
+        let tag = item["struct".len()..open].trim();
+        if tag.is_empty() {
+            return Err(InterpError::unsupported("Error: a struct without a tag can't be named; write struct Tag { ... }"));
+        }
+        
+        let mut members: Vec<StructMember> = Vec::new();
+        for declaration in self.split_top_level(&item[open + 1..close], ';') {
+            if declaration.is_empty() {
+                continue;
+            }
+            let (member_type, declarators) = self.split_type_prefix(&declaration)
+                .ok_or_else(|| InterpError::parse(format!("Error: unknown type in member declaration '{}'", declaration)))?;
+            for declarator in self.split_top_level(declarators, ',') {
+                let name_start = declarator.trim_start_matches(['*', ' ']);
+                let pointers = declarator[..declarator.len() - name_start.len()].matches('*').count();
+                let type_name = format!("{}{}", member_type, "*".repeat(pointers));
+                let (name, mut after) = name_start.split_at(name_start.find('[').unwrap_or(name_start.len()));
+                let name = name.trim().to_string();
+                // Each [size] is one dimension of an array member
+                let mut dims = Vec::new();
+                while let Some(size_str) = after.strip_prefix('[') {
+                    let bracket_end = size_str.find(']').ok_or_else(|| InterpError::parse("Invalid array syntax"))?;
+                    dims.push(self.array_length(&name, &size_str[..bracket_end], None)?);
+                    after = size_str[bracket_end + 1..].trim_start();
+                }
+                
+                let incomplete = type_name.strip_prefix("struct ").is_some_and(|inner| !self.structs.contains_key(inner));
+                if incomplete && !type_name.ends_with('*') {
+                    return Err(InterpError::parse(format!("Error: field '{}' has incomplete type '{}'", name, type_name)));
+                }
+                if members.iter().any(|member| member.name == name) {
+                    return Err(InterpError::parse(format!("Error: duplicate member '{}' in struct {}", name, tag)));
+                }
+                members.push(StructMember { name, type_name, dims });
+            }
+        }
+        self.structs.insert(tag.to_string(), members);
+        
+        let declarators = item[close + 1..].trim();
+        if !declarators.is_empty() {
+            self.handle_declaration(&format!("struct {} {}", tag, declarators))?;
+        }
+        Ok(())
+    }
+
+    /// Record the names declared by `typedef unsigned int uint, *uint_ptr`
+    /// (given without the `typedef`) as aliases of their types. A struct may
+    /// be defined in place, as in `typedef struct { int x, y; } Point`,
+    /// where a struct without a tag takes the typedef's name as its tag.
+    fn define_typedef(&mut self, definition: &str) -> Result<(), InterpError> {
+        let (type_name, declarators) = if self.is_struct_definition(definition) {
+            let open = definition.find('{').unwrap_or_default();
+            let close = self.find_matching_brace(definition, open)
+                .ok_or_else(|| InterpError::parse("Error: unterminated struct definition"))?;
+            let declarators = definition[close + 1..].trim();
+            let tag = match definition["struct".len()..open].trim() {
+                "" => declarators.split(',').map(str::trim).find(|name| !name.starts_with('*'))
+                    .ok_or_else(|| InterpError::unsupported("Error: a struct without a tag needs a typedef name"))?,
+                tag => tag,
+            };
+            self.define_struct(&format!("struct {} {}", tag, &definition[open..=close]))?;
+            (format!("struct {}", tag), declarators)
+        } else {
+            let (type_name, declarators) = self.split_type_prefix(definition)
+                .ok_or_else(|| InterpError::parse(format!("Error: unknown type in typedef '{}'", definition)))?;
+            (type_name, declarators)
+        };
+        
+        for declarator in self.split_top_level(declarators, ',') {
+            let name = declarator.trim_start_matches(['*', ' ']);
+            let pointers = declarator[..declarator.len() - name.len()].matches('*').count();
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(InterpError::unsupported(format!("Error: unsupported typedef declarator '{}'", declarator)));
+            }
+            let aliased = format!("{}{}", type_name, "*".repeat(pointers));
+            if self.typedefs.get(name).is_some_and(|existing| *existing != aliased) {
+                return Err(InterpError::parse(format!("Error: conflicting types for '{}'", name)));
+            }
+            self.typedefs.insert(name.to_string(), aliased);
+        }
+        Ok(())
+    }
+
+    /// The members of struct type `type_name`, or an error if it isn't one
+    /// that has been defined
+    fn struct_members(&self, type_name: &str) -> Result<&[StructMember], InterpError> {
+        type_name.strip_prefix("struct ")
+            .and_then(|tag| self.structs.get(tag))
+            .map(Vec::as_slice)
+            .ok_or_else(|| format!("Error: '{}' is not a defined struct type", type_name).into())
+    }
+
+    /// Evaluate a struct initializer: `{1, 2}` in member order, members
+    /// named by designators as in `{.y = 2, .x = 1}`, or another struct of
+    /// the same type. Members not given are zero.
+    fn evaluate_struct_initializer(&mut self, type_name: &str, init: &str) -> Result<Value, InterpError> {
+        let Some(inner) = init.strip_prefix('{').and_then(|init| init.strip_suffix('}')) else {
+            return match self.evaluate_value_expression(init)? {
+                value @ Value::Struct(_) => Ok(value),
+                _ => Err(format!("Error: incompatible types when initializing type '{}' using '{}'", type_name, init).into()),
+            };
+        };
+        let members = self.struct_members(type_name)?.to_vec();
+        let Value::Struct(mut fields) = self.zero_struct(type_name) else {
+            unreachable!("zero_struct returns a struct");
+        };
+        
+        let mut elements = self.split_top_level(inner, ',');
+        if elements.last().is_some_and(|element| element.is_empty()) {
+            elements.pop();
+        }
+        let mut next = 0;
+        for element in &elements {
+            let (index, init) = match element.strip_prefix('.') {
+                Some(designated) => {
+                    let (name, init) = designated.split_once('=')
+                        .ok_or_else(|| InterpError::parse(format!("Invalid designated initializer: {}", element)))?;
+                    let index = members.iter().position(|member| member.name == name.trim())
+                        .ok_or_else(|| format!("Error: '{}' has no member named '{}'", type_name, name.trim()))?;
+                    (index, init.trim())
+                }
+                None => (next, element.as_str()),
+            };
+            let member = members.get(index)
+                .ok_or_else(|| format!("Error: excess elements in struct initializer for '{}'", type_name))?;
+            
+            let value = if let Some((&len, inner_dims)) = member.dims.split_first() {
+                let mut array = self.evaluate_array_initializer(&member.type_name, init, inner_dims)?;
+                if array.len() > len {
+                    return Err(format!("Error: excess elements in array initializer for '{}'", member.name).into());
+                }
+                array.resize(len, self.zero_array(&member.type_name, inner_dims));
+                Value::Array(array)
+            } else if member.type_name.ends_with('*') {
+                self.evaluate_pointer_expression(init)?
+            } else {
+                self.evaluate_typed_initializer(&member.type_name, init)?
+            };
+            fields.insert(member.name.clone(), value);
+            next = index + 1;
+        }
+        Ok(Value::Struct(fields))
+    }
+
+    /// The length of an array declaration: the size in brackets, or the
+    /// number of initializer elements when the brackets are empty
+    fn array_length(&mut self, var_name: &str, size_str: &str, initializer: Option<&[Value]>) -> Result<usize, InterpError> {
+        let initialized = initializer.map(<[Value]>::len);
+        if size_str.trim().is_empty() {
+            return initialized.ok_or_else(|| InterpError::parse(format!("Error: array size missing in '{}'", var_name)));
+        }
+        
+        let size = self.evaluate_numeric_expression(size_str)?;
+        let size = usize::try_from(size).map_err(|_| format!("Error: size of array '{}' is negative", var_name))?;
+        if initialized.is_some_and(|len| len > size) {
+            return Err(format!("Error: excess elements in array initializer for '{}'", var_name).into());
+        }
+        Ok(size)
+    }
+
+    /// Size in bytes of a basic or struct type name as used by sizeof
+    fn size_of_type(&self, type_name: &str) -> i64 {
+        let type_name = self.resolve_type(type_name).unwrap_or_else(|| type_name.trim().to_string());
+        if type_name.ends_with('*') {
+            return 8;
+        }
+        if let Some(tag) = type_name.strip_prefix("struct ") {
+            return self.struct_layout(tag).0;
+        }
+        match type_name.trim_start_matches("unsigned ") {
+            "char" | "bool" => 1,
+            "short" => 2,
+            "int" | "float" => 4,
+            _ => 8, // long, double
+        }
+    }
+
+    /// Size and alignment of struct `tag`. Like a C compiler, each member
+    /// starts at a multiple of its own alignment and the size is padded to
+    /// a multiple of the largest one, so arrays of the struct stay aligned.
+    fn struct_layout(&self, tag: &str) -> (i64, i64) {
+        let round_up = |offset: i64, align: i64| (offset + align - 1) / align * align;
+        let mut size = 0;
+        let mut align = 1;
+        for member in self.structs.get(tag).into_iter().flatten() {
+            let (member_size, member_align) = match member.type_name.strip_prefix("struct ") {
+                Some(inner) if !member.type_name.ends_with('*') => self.struct_layout(inner),
+                _ => {
+                    let member_size = self.size_of_type(&member.type_name);
+                    (member_size, member_size)
+                }
+            };
+            let count: usize = member.dims.iter().product();
+            size = round_up(size, member_align) + member_size * count as i64;
+            align = align.max(member_align);
+        }
+        (round_up(size, align), align)
+    }
+
+    /// Evaluate `sizeof(type)`, `sizeof(var)`, `sizeof(arr[i])` or `sizeof var`
+    fn evaluate_sizeof(&mut self, operand: &str) -> Result<i64, InterpError> {
+        let mut operand = operand.trim();
+        while operand.starts_with('(') && self.find_matching_paren(operand, 0) == Some(operand.len() - 1) {
+            operand = operand[1..operand.len() - 1].trim();
+        }
+        
+        let base_type = operand.trim_end_matches(['*', ' ']);
+        if self.is_declaration(&format!("{} ", base_type)) {
+            return Ok(self.size_of_type(operand));
+        }
+        if self.pointee_types.contains_key(operand) {
+            return Ok(8);
+        }
+        
+        let (name, ops) = match self.parse_postfix(operand) {
+            Some((base, ops)) => (base, ops),
+            None => (operand, Vec::new()),
+        };
+        // A member has its member's type, and an array member is counted whole
+        if ops.iter().any(|op| !matches!(op, Postfix::Index(_))) {
+            let member_type = self.postfix_type(name, &ops)
+                .ok_or_else(|| format!("Error: sizeof of unknown operand '{}'", operand))?;
+            let count = self.read_postfix(name, &ops)?.as_ref().map_or(1, scalar_count);
+            return Ok(count as i64 * self.size_of_type(&member_type));
+        }
+        let element_size = match self.variable_types.get(name) {
+            Some(var_type) => self.size_of_type(var_type),
+            None if self.pointee_types.contains_key(name) => {
+                self.size_of_type(&self.pointee_types[name])
+            }
+            None => return Err(format!("Error: sizeof of unknown operand '{}'", operand).into()),
+        };
+        
+        // Each subscript selects a row of a multi-dimensional array
+        let mut value = self.variables.get(name);
+        for _ in 0..ops.len() {
+            value = match value {
+                Some(Value::Array(elements)) => elements.first(),
+                _ => None,
+            };
+        }
+        Ok(value.map_or(1, scalar_count) as i64 * element_size)
+    }
+
+    fn evaluate_pointer_expression(&mut self, expr: &str) -> Result<Value, InterpError> {
+        let expr = expr.trim();
+        
+        // Handle NULL or 0
+        if expr == "NULL" || expr == "0" {
+            return Ok(Value::Pointer(0));
+        }
+        
+        // Either branch of a conditional, such as a choice of two strings
+        if let Some((condition, true_expr, false_expr)) = self.split_conditional(expr) {
+            return if self.evaluate_condition(condition)? {
+                self.evaluate_pointer_expression(true_expr)
+            } else {
+                self.evaluate_pointer_expression(false_expr)
+            };
+        }
+        
+        // A string literal decays to a pointer to its first char
+        if expr.starts_with('"') && expr.ends_with('"') && expr.len() >= 2 {
+            let literal = &expr[1..expr.len() - 1];
+            return Ok(Value::Pointer(self.memory.allocate_string_literal(literal)));
+        }
+        
+        // Parentheses around the whole expression, as in *(p + 2)
+        if expr.starts_with('(') && self.find_matching_paren(expr, 0) == Some(expr.len() - 1) {
+            return self.evaluate_pointer_expression(&expr[1..expr.len() - 1]);
+        }
+        
+        // Drop a pointer cast such as (int *) in front of malloc
+        if expr.starts_with('(') {
+            if let Some(close) = self.find_matching_paren(expr, 0) {
+                let cast = expr[1..close].trim();
+                if self.typedefs.get(cast).map_or(cast, String::as_str).ends_with('*') {
+                    return self.evaluate_pointer_expression(&expr[close + 1..]);
+                }
+            }
+        }
+        
+        if self.parse_library_call(expr, &["strpbrk"]).is_some() {
+            return self.evaluate_string_scan(expr);
+        }
+        
+        // Handle heap allocation: malloc(size)
+        if self.starts_with_keyword(expr, "malloc") {
+            let start = expr.find('(').ok_or_else(|| InterpError::parse("Invalid malloc syntax"))?;
+            let end = expr.rfind(')').ok_or_else(|| InterpError::parse("Invalid malloc syntax"))?;
+            let size = self.evaluate_numeric_expression(&expr[start + 1..end])?;
+            if size < 0 {
+                return Err(InterpError::memory(format!("malloc: invalid size {}", size)));
+            }
+            return Ok(Value::Pointer(self.memory.malloc(size as usize)));
+        }
+        
+        // Handle address-of operator: &variable
+        if let Some(var_name) = expr.strip_prefix('&').filter(|_| self.is_single_operand(expr)) {
+            let var_name = var_name.trim();
+            
+            // Handle array element: &arr[index]
+            if var_name.contains('[') {
+                let bracket_pos = var_name.find('[').unwrap();
+                let array_name = var_name[..bracket_pos].trim();
+                let bracket_end = var_name.find(']').ok_or_else(|| InterpError::parse("Invalid array syntax"))?;
+                let index_expr = &var_name[bracket_pos + 1..bracket_end];
+                let index = self.evaluate_numeric_expression(index_expr)?;
+                // The address one past the last element is still valid
+                if let Some(Value::Array(elements)) = self.variables.get(array_name) {
+                    checked_index(index, elements.len() + 1)
+                        .map_err(|_| InterpError::from(format!("Error: array index {} out of bounds (size {})", index, elements.len())))?;
+                }
+                
+                // Get base address of array and add offset
+                if let Some(&base_addr) = self.memory.address_map.get(array_name) {
+                    let element_addr = base_addr + index * 8;
+                    return Ok(Value::Pointer(element_addr));
+                } else {
+                    return Err(format!("Variable '{}' not found", array_name).into());
+                }
+            }
+            
+            // Regular variable address
+            if let Some(value) = self.variables.get(var_name) {
+                let addr = self.memory.get_address_of(var_name, value);
+                return Ok(Value::Pointer(addr));
+            } else {
+                return Err(format!("Variable '{}' not found", var_name).into());
+            }
+        }
+        
+        // Handle dereference in expression (this is for pointer assignment)
+        if let Some(ptr_expr) = expr.strip_prefix('*') {
+            let ptr_expr = ptr_expr.trim();
+            if let Some(Value::Pointer(addr)) = self.variables.get(ptr_expr) {
+                self.read_address(*addr)?;
+                return Ok(Value::Pointer(*addr)); // Return the address for pointer-to-pointer
+            }
+        }
+        
+        // Handle direct pointer variable or expression
+        if let Some(Value::Pointer(addr)) = self.variables.get(expr) {
+            return Ok(Value::Pointer(*addr));
+        }
+        
+        // An array name decays to a pointer to its first element
+        if let Some(array @ Value::Array(_)) = self.variables.get(expr) {
+            let array = array.clone();
+            return Ok(Value::Pointer(self.memory.get_address_of(expr, &array)));
+        }
+        
+        // Pointer arithmetic steps by whole elements, as in p + 2 or arr - 1
+        if let Some((pos, op)) = self.find_binary_operator(expr, &['+', '-']) {
+            let base = expr[..pos].trim();
+            if self.is_address_operand(base) && !self.is_address_operand(&expr[pos + 1..]) {
+                if let Value::Pointer(addr) = self.evaluate_pointer_expression(base)? {
+                    let step = self.evaluate_numeric_expression(&expr[pos + 1..])?;
+                    return Ok(Value::Pointer(if op == '+' { addr + step * 8 } else { addr - step * 8 }));
+                }
+            }
+        }
+        
+        // A pointer held in an element or member, as in cur = cur->next
+        if let Some((base, ops)) = self.parse_postfix(expr) {
+            if let Some(value @ Value::Pointer(_)) = self.read_postfix(base, &ops)? {
+                return Ok(value);
+            }
+        }
+        
+        // Try to evaluate as numeric expression (cast to pointer)
+        let num = self.evaluate_numeric_expression(expr)?;
+        Ok(Value::Pointer(num))
+    }
+
+    /// Resolve an assignment target such as `x`, `*p`, `grid[i][j]` or
+    /// `list->items[i].name` to the place it names and its declared type,
+    /// evaluating each subscript once. None when `target` is not one.
+    fn resolve_place<'a>(&mut self, target: &'a str) -> Result<Option<(Place<'a>, Option<String>)>, InterpError> {
+        let target = target.trim();
+        if let Some(ptr_name) = target.strip_prefix('*') {
+            let ptr_name = ptr_name.trim();
+            // A parenthesized target such as *(p + 2) is pointer arithmetic,
+            // and an array name stands for its first element
+            let addr = match self.variables.get(ptr_name) {
+                Some(&Value::Pointer(addr)) => addr,
+                Some(Value::Array(_)) => match self.memory.address_map.get(ptr_name) {
+                    Some(&base_addr) => {
+                        self.check_writable(ptr_name)?;
+                        let element_type = self.variable_types.get(ptr_name).cloned();
+                        return Ok(Some((Place::Address(base_addr), element_type)));
+                    }
+                    None => return Err(format!("'{}' is not a valid pointer", ptr_name).into()),
+                },
+                _ if ptr_name.starts_with('(') => match self.evaluate_pointer_expression(ptr_name)? {
+                    Value::Pointer(addr) => addr,
+                    _ => return Err(format!("'{}' is not a valid pointer", ptr_name).into()),
+                },
+                _ => return Err(format!("'{}' is not a valid pointer", ptr_name).into()),
+            };
+            return Ok(Some((Place::Address(addr), self.pointee_types.get(ptr_name).cloned())));
+        }
+        if target.starts_with(|c: char| c.is_alphabetic() || c == '_') && target.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            self.check_writable(target)?;
+            return Ok(Some((Place::Variable(target), self.variable_types.get(target).cloned())));
+        }
+        
+        let Some((var_name, ops)) = self.parse_postfix(target) else {
+            return Ok(None);
+        };
+        self.check_writable(var_name)?;
+        // Past the last ->, the place is in a struct in memory
+        let arrow = ops.iter().rposition(|op| matches!(op, Postfix::Arrow(_)));
+        // One index per dimension and a name per member
+        let mut path = Vec::new();
+        for (i, op) in ops.iter().enumerate().skip(arrow.unwrap_or(0)) {
+            match op {
+                Postfix::Index(index_expr) => path.push(Step::Index(self.evaluate_numeric_expression(index_expr)?)),
+                Postfix::Member(member) | Postfix::Arrow(member) if self.postfix_type(var_name, &ops[..=i]).is_some() => {
+                    path.push(Step::Member(member));
+                }
+                Postfix::Member(member) | Postfix::Arrow(member) => return Err(self.member_error(var_name, &ops[..i], member)),
+            }
+        }
+        let target_type = self.postfix_type(var_name, &ops);
+        
+        if let Some(arrow) = arrow {
+            let struct_type = self.postfix_type(var_name, &ops[..arrow]).unwrap_or_default();
+            let addr = match self.read_postfix(var_name, &ops[..arrow])? {
+                Some(Value::Pointer(addr)) => addr,
+                _ => return Err(format!("Error: invalid type argument of '->' (have '{}')", struct_type).into()),
+            };
+            let struct_type = struct_type.trim_end_matches('*').to_string();
+            return Ok(Some((Place::Pointee(addr, struct_type, path), target_type)));
+        }
+        if let (Some(&Value::Pointer(addr)), [Step::Index(index)]) = (self.variables.get(var_name), path.as_slice()) {
+            return Ok(Some((Place::Address(addr + index * 8), target_type)));
+        }
+        Ok(Some((Place::Element(var_name, path), target_type)))
+    }
+
+    /// The value stored at `place`
+    fn read_place(&mut self, place: &Place) -> Result<Value, InterpError> {
+        match place {
+            Place::Variable(name) => match self.variables.get(*name) {
+                Some(value) => Ok(value.clone()),
+                None => self.evaluate_arithmetic(name),
+            },
+            Place::Element(name, path) => {
+                let value = self.variables.get(*name)
+                    .ok_or_else(|| format!("Variable '{}' not found", name))?;
+                load_at_path(value, name, path)
+            }
+            Place::Address(addr) => self.read_address(*addr),
+            Place::Pointee(addr, struct_type, path) => {
+                let target = Value::Struct(self.read_struct(*addr, struct_type)?);
+                load_at_path(&target, struct_type, path)
+            }
+        }
+    }
+
+    /// Store `value` at `place`, which `resolve_place` made of `target`,
+    /// keeping memory in step with the variable it lives in
+    fn store_place(&mut self, target: &str, place: Place, value: Value) -> Result<(), InterpError> {
+        match place {
+            Place::Variable(name) => {
+                self.memory.update_variable_address(name, &value);
+                self.variables.insert(name.to_string(), value);
+                Ok(())
+            }
+            Place::Element(var_name, path) => {
+                self.store_element(var_name, &path, value.clone())?;
+                // Update memory if this array or struct has an address
+                if let Some(whole @ Value::Struct(_)) = self.variables.get(var_name) {
+                    let whole = whole.clone();
+                    self.memory.update_variable_address(var_name, &whole);
+                } else if let (Some(&base_addr), [Step::Index(index)]) = (self.memory.address_map.get(var_name), path.as_slice()) {
+                    let element_addr = base_addr + index * 8;
+                    if self.memory.heap.contains_key(&element_addr) {
+                        self.memory.write(element_addr, value)?;
+                    }
+                }
+                Ok(())
+            }
+            Place::Address(addr) => self.write_through_pointer(addr, value),
+            Place::Pointee(addr, struct_type, path) => {
+                let mut whole = Value::Struct(self.read_struct(addr, &struct_type)?);
+                store_at_path(&mut whole, target, &path, value)?;
+                self.write_through_pointer(addr, whole)
+            }
+        }
+    }
+
+    /// Store `value` at `path` in variable `var_name`
+    fn store_element(&mut self, var_name: &str, path: &[Step], value: Value) -> Result<(), InterpError> {
+        let slot = self.variables.get_mut(var_name)
+            .ok_or_else(|| format!("Variable '{}' not found", var_name))?;
+        store_at_path(slot, var_name, path, value)
+    }
+
+    /// Reject a write to a variable declared const
+    fn check_writable(&self, var_name: &str) -> Result<(), InterpError> {
+        if self.constants.contains(var_name) {
+            return Err(format!("Error: assignment of read-only variable '{}'", var_name).into());
+        }
+        Ok(())
+    }
+
+    fn handle_compound_assignment(&mut self, statement: &str) -> Result<(), InterpError> {
+        let Some((pos, op)) = self.find_compound_assignment(statement) else {
+            return Ok(());
+        };
+        let target = statement[..pos + 1 - op.len()].trim();
+        let expr = statement[pos + 1..].trim();
+        
+        if op == "%=" {
+            self.check_modulo_operands(target, expr)?;
+        }
+        
+        // Resolved once, so a subscript such as b[i++] steps i once
+        let (place, target_type) = self.resolve_place(target)?
+            .ok_or_else(|| InterpError::parse(format!("Error: invalid target of {}: {}", op, target)))?;
+        let current = self.read_place(&place)?;
+        
+        // A pointer steps by whole elements
+        if let (Value::Pointer(addr), "+=" | "-=") = (&current, op) {
+            let offset = self.evaluate_numeric_expression(expr)? * 8;
+            let addr = if op == "+=" { addr + offset } else { addr - offset };
+            return self.store_place(target, place, Value::Pointer(addr));
+        }
+        
+        // A float target or a fractional right side makes the arithmetic
+        // floating-point; the result is then converted to the target's type
+        let floating = matches!(current, Value::Float(_)) || self.is_floating_expression(expr);
+        let result = if matches!(op, "+=" | "-=" | "*=" | "/=") && floating {
+            let current_val = match current {
+                Value::Float(f) => f,
+                ref value => Self::as_i64(value) as f64,
+            };
+            let expr_val = self.evaluate_float_expression(expr)?;
+            Value::Float(match op {
+                "+=" => current_val + expr_val,
+                "-=" => current_val - expr_val,
+                "*=" => current_val * expr_val,
+                _ => current_val / expr_val,
+            })
+        } else {
+            let current_val = Self::as_i64(&current);
+            let expr_val = self.evaluate_numeric_expression(expr)?;
+            Value::Int(match op {
+                "+=" | "-=" | "*=" | "/=" | "%=" => arithmetic(current_val, op.chars().next().unwrap_or('+'), expr_val)?,
+                "&=" => current_val & expr_val,
+                "|=" => current_val | expr_val,
+                "^=" => current_val ^ expr_val,
+                "<<=" | ">>=" => shift(current_val, op, expr_val)?,
+                _ => current_val,
+            })
+        };
+        
+        let value = match target_type {
+            Some(type_name) => Self::convert_value(&type_name, result),
+            None => result,
+        };
+        self.store_place(target, place, value)
+    }
+
+    fn handle_assignment(&mut self, statement: &str) -> Result<(), InterpError> {
+        let parts: Vec<&str> = statement.splitn(2, '=').collect();
+        if parts.len() != 2 {
+            return Err(InterpError::parse("Error: Invalid assignment syntax"));
+        }
+        
+        let left = parts[0].trim();
+        let expr = parts[1].trim();
+
+        // Handle pointer dereference assignment: *ptr = value
+        if left.starts_with('*') {
+            let (place, pointee) = self.resolve_place(left)?.ok_or_else(|| InterpError::parse("Invalid dereference syntax"))?;
+            let value = if expr.starts_with('"') {
+                Value::String(expr.trim_matches('"').to_string())
+            } else if expr.starts_with('\'') {
+                Value::Char(parse_char_literal(expr)?)
+            } else if expr.starts_with('&') {
+                // Assigning an address
+                self.evaluate_pointer_expression(expr)?
+            } else {
+                // Stored as the pointee type, so a float * keeps fractions
+                let value = self.evaluate_value_expression(expr)?;
+                match pointee {
+                    Some(pointee) => Self::convert_value(&pointee, value),
+                    None => value,
+                }
+            };
+            return self.store_place(left, place, value);
+        }
+
+        // Handle element and member assignment: grid[i][j], p.x, pts[i].x
+        if left.contains(['[', '.']) || left.contains("->") {
+            let (place, target_type) = self.resolve_place(left)?.ok_or_else(|| InterpError::parse("Invalid array syntax"))?;
+            let value = if target_type.as_ref().is_some_and(|t| t.ends_with('*')) {
+                self.evaluate_pointer_expression(expr)?
+            } else if expr.starts_with('"') {
+                Value::String(expr.trim_matches('"').to_string())
+            } else if expr.starts_with('\'') {
+                Value::Char(parse_char_literal(expr)?)
+            } else {
+                // Stored as the element type, so a double array keeps fractions
+                let value = self.evaluate_value_expression(expr)?;
+                match target_type {
+                    Some(element_type) => Self::convert_value(&element_type, value),
+                    None => value,
+                }
+            };
+            return self.store_place(left, place, value);
+        }
+
+        let var_name = left.to_string();
+        self.check_writable(&var_name)?;
+        
+        // Handle pointer assignment
+        if expr.starts_with('&') || (self.variables.get(&var_name).map(|v| matches!(v, Value::Pointer(_))).unwrap_or(false)) {
+            let value = self.evaluate_pointer_initializer(&var_name, expr)?;
+            self.variables.insert(var_name, value);
+            return Ok(());
+        }
+        
+        // Check if variable exists to determine type
+        if let Some(existing_value) = self.variables.get(&var_name).cloned() {
+            let value = match existing_value {
+                Value::Float(_) => Value::Float(self.evaluate_float_expression(expr)?),
+                Value::Char(_) => {
+                    let value = self.evaluate_value_expression(expr)?;
+                    let var_type = self.variable_types.get(&var_name).map_or("char", String::as_str);
+                    Self::convert_value(var_type, value)
+                },
+                Value::String(_) => match self.evaluate_value_expression(expr)? {
+                    value @ Value::String(_) => value,
+                    _ => existing_value,
+                },
+                Value::Pointer(_) => {
+                    self.evaluate_pointer_expression(expr)?
+                },
+                Value::Bool(_) => Value::Bool(self.evaluate_condition(expr)?),
+                Value::Struct(_) => {
+                    let struct_type = self.variable_types.get(&var_name).cloned().unwrap_or_default();
+                    self.evaluate_struct_initializer(&struct_type, expr)?
+                }
+                _ => {
+                    let num = self.evaluate_numeric_expression(expr)?;
+                    self.integer_for(&var_name, num)
+                }
+            };
+            
+            self.variables.insert(var_name.clone(), value.clone());
+            
+            // Update memory
+            if let Some(&addr) = self.memory.address_map.get(&var_name) {
+                self.memory.write(addr, value)?;
+            }
+        } else {
+            // New variable
+            let value = if expr.starts_with('"') {
+                Value::String(expr.trim_matches('"').to_string())
+            } else if expr.starts_with('\'') {
+                Value::Char(parse_char_literal(expr)?)
+            } else if expr.starts_with('&') {
+                self.evaluate_pointer_expression(expr)?
+            } else {
+                let num = self.evaluate_numeric_expression(expr)?;
+                Value::Int(num)
+            };
+            
+            self.memory.get_address_of(&var_name, &value);
+            self.memory.update_variable_address(&var_name, &value);
+            self.variables.insert(var_name, value);
+        }
+        
+        Ok(())
+    }
+
+    fn evaluate_value_expression(&mut self, expr: &str) -> Result<Value, InterpError> {
+        let expr = expr.trim();
+        
+        // Check if it's a string literal
+        if expr.starts_with('"') && expr.ends_with('"') {
+            return Ok(Value::String(expr.trim_matches('"').to_string()));
+        }
+
+        // Check if it's a char literal
+        if expr.starts_with('\'') && expr.ends_with('\'') {
+            return Ok(Value::Char(parse_char_literal(expr)?));
+        }
+
+        // Check if it's a variable
+        if let Some(value) = self.variables.get(expr) {
+            return Ok(value.clone());
+        }
+
+        // The selected branch of a conditional keeps its own type
+        if let Some((condition, true_expr, false_expr)) = self.split_conditional(expr) {
+            return if self.evaluate_condition(condition)? {
+                self.evaluate_value_expression(true_expr)
+            } else {
+                self.evaluate_value_expression(false_expr)
+            };
+        }
+
+        // Keep the element type when reading through a subscript
+        if let Some((base, ops)) = self.parse_postfix(expr) {
+            if let Some(value) = self.read_postfix(base, &ops)? {
+                return Ok(value);
+            }
+        }
+
+        // A call returns a value of the function's return type
+        if let Some((name, args)) = self.parse_call(expr) {
+            return self.call_function(name, args);
+        }
+        if self.parse_library_call(expr, STRING_COPY_FUNCTIONS).is_some() {
+            return self.evaluate_string_copy(expr);
+        }
+        if self.parse_library_call(expr, &["memset", "memcpy"]).is_some() {
+            return self.evaluate_memory_call(expr);
+        }
+
+        // A floating literal such as 3.14 keeps its fraction
+        if expr.contains(['.', 'e', 'E']) && !expr.starts_with("0x") && !expr.starts_with("0X") {
+            if let Ok(num) = expr.parse::<f64>() {
+                return Ok(Value::Float(num));
+            }
+        }
+
+        // Arithmetic involving a float keeps its fraction
+        if self.is_floating_expression(expr) {
+            return self.evaluate_arithmetic(expr);
+        }
+
+        // Otherwise treat as numeric
+        let num = self.evaluate_numeric_expression(expr)?;
+        Ok(Value::Int(num))
+    }
+
+    /// Evaluate arithmetic with C's usual conversions: an operation on two
+    /// integers stays integral, one with a float operand is done in floating
+    /// point. Integer-only operators go through `evaluate_numeric_expression`.
+    fn evaluate_arithmetic(&mut self, expr: &str) -> Result<Value, InterpError> {
+        let expr = expr.trim();
+        if !self.is_floating_expression(expr) {
+            return Ok(Value::Int(self.evaluate_numeric_expression(expr)?));
+        }
+        
+        // A float literal, with an optional f suffix
+        if expr.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            if let Ok(num) = expr.trim_end_matches(['f', 'F']).parse::<f64>() {
+                return Ok(Value::Float(num));
+            }
+        }
+        
+        if expr.starts_with('(') {
+            if let Some(close) = self.find_matching_paren(expr, 0) {
+                // Parentheses around the whole expression
+                if close == expr.len() - 1 {
+                    return self.evaluate_arithmetic(&expr[1..close]);
+                }
+                // A cast such as (double) or (int), which binds tighter than
+                // any binary operator
+                let cast = self.basic_cast(&expr[1..close], &["int", "long", "short", "char", "float", "double"]);
+                if let Some(cast) = cast.filter(|_| self.is_single_operand(&expr[close + 1..])) {
+                    let value = self.evaluate_arithmetic(&expr[close + 1..])?;
+                    return Ok(Self::convert_value(cast, value));
+                }
+            }
+        }
+        
+        // Conditional, logical and comparison operators
+        if let Some((condition, true_expr, false_expr)) = self.split_conditional(expr) {
+            return if self.evaluate_condition(condition)? {
+                self.evaluate_arithmetic(true_expr)
+            } else {
+                self.evaluate_arithmetic(false_expr)
+            };
+        }
+        if self.find_top_level_operator(expr, &["||", "&&", "==", "!=", "<=", ">=", "<", ">"], false).is_some() {
+            return Ok(Value::Int(self.evaluate_condition(expr)? as i64));
+        }
+        
+        // Binary operators, lowest precedence first; the rightmost split
+        // keeps them left-associative
+        for operators in [&['+', '-'][..], &['*', '/', '%'][..]] {
+            if let Some((pos, op)) = self.find_binary_operator(expr, operators) {
+                if op == '%' {
+                    self.check_modulo_operands(&expr[..pos], &expr[pos + 1..])?;
+                }
+                let left = self.evaluate_arithmetic(&expr[..pos])?;
+                let right = self.evaluate_arithmetic(&expr[pos + 1..])?;
+                if matches!(left, Value::Float(_)) || matches!(right, Value::Float(_)) {
+                    let (l, r) = (Self::as_f64(&left), Self::as_f64(&right));
+                    return Ok(Value::Float(match op {
+                        '+' => l + r,
+                        '-' => l - r,
+                        '*' => l * r,
+                        _ => l / r,
+                    }));
+                }
+                return Ok(Value::Int(arithmetic(Self::as_i64(&left), op, Self::as_i64(&right))?));
+            }
+        }
+        
+        if let Some(value) = self.evaluate_increment(expr)? {
+            return Ok(value);
+        }
+        
+        // Unary minus and plus
+        if let Some(operand) = expr.strip_prefix('-') {
+            return Ok(match self.evaluate_arithmetic(operand)? {
+                Value::Float(f) => Value::Float(-f),
+                value => Value::Int(Self::as_i64(&value).wrapping_neg()),
+            });
+        }
+        if let Some(operand) = expr.strip_prefix('+') {
+            return self.evaluate_arithmetic(operand);
+        }
+        
+        // Operands that keep their type: variables, elements, calls and
+        // dereferenced pointers
+        if let Some(value) = self.variables.get(expr) {
+            return Ok(value.clone());
+        }
+        if let Some((base, ops)) = self.parse_postfix(expr) {
+            if let Some(value) = self.read_postfix(base, &ops)? {
+                return Ok(value);
+            }
+        }
+        if let Some((name, args)) = self.parse_call(expr) {
+            return self.call_function(name, args);
+        }
+        if let Some((name, args)) = self.parse_math_call(expr) {
+            return self.evaluate_math_call(name, args);
+        }
+        if let Some(ptr_expr) = expr.strip_prefix('*') {
+            if let Value::Pointer(addr) = self.evaluate_pointer_expression(ptr_expr)? {
+                return self.read_address(addr);
+            }
+        }
+        
+        Ok(Value::Int(self.compute_numeric_expression(expr)?))
+    }
+
+    /// Which of the basic `types` the parenthesized `cast` names, either
+    /// directly or through a typedef
+    fn basic_cast(&self, cast: &str, types: &[&'static str]) -> Option<&'static str> {
+        let cast = cast.trim();
+        let cast = self.typedefs.get(cast).map_or(cast, String::as_str);
+        types.iter().copied().find(|basic| *basic == cast)
+    }
+
+    /// Whether `text` is one operand, possibly with unary operators in front,
+    /// so a cast before it applies to all of it
+    fn is_single_operand(&self, text: &str) -> bool {
+        let text = text.trim_start_matches(|c: char| c.is_whitespace() || "-+!~*&".contains(c)).trim_end();
+        if text.starts_with('(') {
+            return self.find_matching_paren(text, 0) == Some(text.len() - 1);
+        }
+        if let Some(open) = text.find('(') {
+            return text[..open].trim().chars().all(|c| c.is_alphanumeric() || c == '_') &&
+                self.find_matching_paren(text, open) == Some(text.len() - 1);
+        }
+        !text.is_empty() && (text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') || self.parse_postfix(text).is_some())
+    }
+
+    /// Evaluate `expr` for a float or double destination
+    fn evaluate_float_expression(&mut self, expr: &str) -> Result<f64, InterpError> {
+        Ok(Self::as_f64(&self.evaluate_arithmetic(expr)?))
+    }
+
+    /// A scalar operand converted for floating-point arithmetic
+    fn as_f64(value: &Value) -> f64 {
+        match value {
+            Value::Float(f) => *f,
+            value => Self::as_i64(value) as f64,
+        }
+    }
+
+    /// A scalar operand converted for integer arithmetic; a float truncates
+    fn as_i64(value: &Value) -> i64 {
+        match value {
+            Value::Int(i) | Value::Pointer(i) => *i,
+            Value::Float(f) => *f as i64,
+            Value::Char(c) => Self::char_as_i64(*c),
+            Value::Bool(b) => *b as i64,
+            Value::String(_) | Value::Array(_) | Value::Struct(_) => 0,
+        }
+    }
+
+    /// The integer value of a `char`, which is signed: a byte past 127
+    /// such as 200 reads as -56
+    fn char_as_i64(c: char) -> i64 {
+        match u8::try_from(c) {
+            Ok(byte) => byte as i8 as i64,
+            Err(_) => c as i64,
+        }
+    }
+
+    /// Rightmost top-level binary use of one of `operators`, skipping
+    /// unary signs, `++`/`--`, `->` and exponents like the one in 1e-5
+    fn find_binary_operator(&self, expr: &str, operators: &[char]) -> Option<(usize, char)> {
+        let bytes = expr.as_bytes();
+        let mut found = None;
+        let mut depth = 0;
+        let mut quote: Option<u8> = None;
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if let Some(q) = quote {
+                if byte == b'\\' {
+                    i += 1;
+                } else if byte == q {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            match byte {
+                b'"' | b'\'' => quote = Some(byte),
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth -= 1,
+                _ if depth == 0 && operators.contains(&(byte as char)) => {
+                    let prev = expr[..i].trim_end();
+                    let next = bytes.get(i + 1).copied().unwrap_or(0);
+                    let after_operand = prev.ends_with(|c: char| c.is_alphanumeric() || "_)].'".contains(c)) ||
+                        prev.ends_with("++") || prev.ends_with("--");
+                    let doubled = matches!(byte, b'+' | b'-') &&
+                        (next == byte || (i > 0 && bytes[i - 1] == byte) || (byte == b'-' && next == b'>'));
+                    let word_start = prev.rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).map_or(0, |p| p + 1);
+                    let word = &prev[word_start..];
+                    let exponent = matches!(byte, b'+' | b'-') && word.ends_with(['e', 'E']) &&
+                        word.starts_with(|c: char| c.is_ascii_digit() || c == '.') && !word.starts_with("0x") && !word.starts_with("0X");
+                    if after_operand && !doubled && !exponent {
+                        found = Some((i, byte as char));
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        found
+    }
+
+    /// Split a postfix expression such as `list->items[i].name` into its
+    /// base identifier and the chain of subscripts and member accesses
+    /// applied to it left to right. Returns None unless the whole expression
+    /// is such a chain with at least one operator.
+    fn parse_postfix<'a>(&self, expr: &'a str) -> Option<(&'a str, Vec<Postfix<'a>>)> {
+        let (base, ops, rest) = self.parse_postfix_prefix(expr)?;
+        (rest.is_empty() && !ops.is_empty()).then_some((base, ops))
+    }
+
+    /// Parse the postfix chain at the start of `expr`, returning its base,
+    /// its operators and the text after it
+    fn parse_postfix_prefix<'a>(&self, expr: &'a str) -> Option<(&'a str, Vec<Postfix<'a>>, &'a str)> {
+        let identifier_len = |text: &str| {
+            if !text.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                return 0;
+            }
+            text.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(text.len())
+        };
+        
+        let base_len = identifier_len(expr);
+        if base_len == 0 {
+            return None;
+        }
+        let base = &expr[..base_len];
+        let mut rest = &expr[base_len..];
+        let mut ops = Vec::new();
+        
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            if rest.starts_with('[') {
+                let mut depth = 0;
+                let close = rest.char_indices().find(|&(_, ch)| {
+                    match ch {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })?.0;
+                ops.push(Postfix::Index(&rest[1..close]));
+                rest = &rest[close + 1..];
+            } else {
+                let (member, is_arrow) = if let Some(member) = rest.strip_prefix("->") {
+                    (member.trim_start(), true)
+                } else if let Some(member) = rest.strip_prefix('.') {
+                    (member.trim_start(), false)
+                } else {
+                    break;
+                };
+                let len = identifier_len(member);
+                if len == 0 {
+                    break;
+                }
+                ops.push(if is_arrow { Postfix::Arrow(&member[..len]) } else { Postfix::Member(&member[..len]) });
+                rest = &member[len..];
+            }
+        }
+        
+        Some((base, ops, rest))
+    }
+
+    /// The declared type of a postfix chain such as `pts[i].x`: an element's
+    /// type through a subscript and a member's through `.` or `->`, with
+    /// pointer types ending in `*`. None if a member doesn't exist.
+    fn postfix_type(&self, base: &str, ops: &[Postfix]) -> Option<String> {
+        // Subscripts select from array dimensions first, then through pointers,
+        // so in `char *names[3]`, names[0] is a char* and names[0][1] a char
+        let array_depth = |mut value: Option<&Value>| {
+            let mut depth = 0;
+            while let Some(Value::Array(elements)) = value {
+                depth += 1;
+                value = elements.first();
+            }
+            depth
+        };
+        let (mut type_name, mut dims) = match self.pointee_types.get(base) {
+            Some(pointee) => (format!("{}*", pointee), 0),
+            None => (self.variable_types.get(base)?.clone(), array_depth(self.variables.get(base))),
+        };
+        for op in ops {
+            let (struct_type, member) = match op {
+                Postfix::Index(_) if dims > 0 => {
+                    dims -= 1;
+                    continue;
+                }
+                Postfix::Index(_) => {
+                    type_name = type_name.strip_suffix('*').map(str::to_string).unwrap_or(type_name);
+                    continue;
+                }
+                Postfix::Member(member) => (type_name.as_str(), member),
+                Postfix::Arrow(member) => (type_name.strip_suffix('*')?, member),
+            };
+            let member = self.struct_members(struct_type).ok()?
+                .iter().find(|candidate| candidate.name == *member)?;
+            (type_name, dims) = (member.type_name.clone(), member.dims.len());
+        }
+        Some(type_name)
+    }
+
+    /// The error for `.member` or `->member` applied to the result of
+    /// `base` followed by `ops`, which is not a struct with that member
+    fn member_error(&self, base: &str, ops: &[Postfix], member: &str) -> InterpError {
+        match self.postfix_type(base, ops) {
+            Some(type_name) if type_name.starts_with("struct ") => {
+                format!("Error: '{}' has no member named '{}'", type_name.trim_end_matches('*'), member).into()
+            }
+            _ => InterpError::unsupported(format!("Error: request for member '{}' in something not a structure", member)),
+        }
+    }
+
+    /// Evaluate a postfix chain from `parse_postfix`. Subscripts index arrays
+    /// directly or through a pointer, and `.` selects a struct member.
+    /// Returns None when the base is not a variable.
+    fn read_postfix(&mut self, base: &str, ops: &[Postfix]) -> Result<Option<Value>, InterpError> {
+        let mut current = match self.variables.get(base) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
+        
+        for (i, op) in ops.iter().enumerate() {
+            current = match (op, current) {
+                (Postfix::Index(index_expr), Value::Array(mut arr)) => {
+                    let index = self.evaluate_numeric_expression(index_expr)?;
+                    arr.swap_remove(checked_index(index, arr.len())?)
+                }
+                (Postfix::Index(index_expr), Value::Pointer(addr)) => {
+                    let index = self.evaluate_numeric_expression(index_expr)?;
+                    self.read_address(addr + index * 8)?
+                }
+                (Postfix::Index(_), _) => return Ok(None),
+                (Postfix::Member(member), Value::Struct(mut fields)) => match fields.remove(*member) {
+                    Some(value) => value,
+                    None => return Err(self.member_error(base, &ops[..i], member)),
+                },
+                // p->x reads the struct p points at; NULL faults like *p
+                (Postfix::Arrow(member), Value::Pointer(addr)) => {
+                    let struct_type = self.postfix_type(base, &ops[..i]).unwrap_or_default();
+                    match self.read_struct(addr, struct_type.trim_end_matches('*'))?.remove(*member) {
+                        Some(value) => value,
+                        None => return Err(self.member_error(base, &ops[..i], member)),
+                    }
+                }
+                (Postfix::Member(member), _) | (Postfix::Arrow(member), _) => {
+                    return Err(self.member_error(base, &ops[..i], member));
+                }
+            };
+        }
+        Ok(Some(current))
+    }
+
+    /// Whether `expr` is a string: a literal, or a variable holding a string
+    /// or char array
+    fn is_string_operand(&self, expr: &str) -> bool {
+        let expr = expr.trim();
+        if expr.len() >= 2 && expr.starts_with('"') && expr.ends_with('"') {
+            return true;
+        }
+        match self.variables.get(expr) {
+            Some(Value::String(_)) => true,
+            Some(Value::Array(_)) => self.variable_types.get(expr).is_some_and(|t| t == "char"),
+            _ => false,
+        }
+    }
+
+    /// `==` and `!=` on strings compare addresses, as in C, so two arrays are
+    /// equal only if they are the same array and a literal equals nothing
+    /// else. Returns whether the operands are the same string, or None when
+    /// neither is a string; warns that contents need strcmp.
+    fn compare_string_addresses(&mut self, condition: &str, left: &str, right: &str, op: &str) -> Result<Option<bool>, InterpError> {
+        if !self.is_string_operand(left) && !self.is_string_operand(right) {
+            return Ok(None);
+        }
+        if op != "==" && op != "!=" {
+            return Err(format!("Error: '{}' orders strings by address; use strcmp({}, {}) to compare their contents",
+                condition.trim(), left.trim(), right.trim()).into());
+        }
+        let warning = format!("Warning: '{}' compares string addresses, not contents; use strcmp({}, {}) {} 0",
+            condition.trim(), left.trim(), right.trim(), op);
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+        
+        let mut address = |expr: &str| -> Result<Option<i64>, InterpError> {
+            let expr = expr.trim();
+            Ok(match self.variables.get(expr).cloned() {
+                Some(Value::Pointer(addr)) => Some(addr),
+                Some(value @ (Value::Array(_) | Value::String(_))) => Some(self.memory.get_address_of(expr, &value)),
+                _ if expr.starts_with('"') => None, // Each literal is a distinct object
+                _ => Some(self.evaluate_numeric_expression(expr)?),
+            })
+        };
+        Ok(Some(match (address(left)?, address(right)?) {
+            (Some(left), Some(right)) => left == right,
+            _ => false,
+        }))
+    }
+
+    /// Reject `%` on floating-point operands the way a C compiler does,
+    /// rather than silently truncating them to integers
+    fn check_modulo_operands(&self, left: &str, right: &str) -> Result<(), InterpError> {
+        let type_name = |expr: &str| if self.is_floating_expression(expr) { "double" } else { "int" };
+        let (left_type, right_type) = (type_name(left), type_name(right));
+        
+        if left_type == "double" || right_type == "double" {
+            return Err(format!("Error: invalid operands to binary % (have '{}' and '{}')", left_type, right_type).into());
+        }
+        Ok(())
+    }
+
+    /// Whether an arithmetic expression has floating-point type, i.e. it
+    /// mentions a float literal, a float variable, an element of a float
+    /// array or pointer, a call to a function returning float, or a cast to
+    /// float or double
+    fn is_floating_expression(&self, expr: &str) -> bool {
+        let bytes = expr.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if byte == b'"' || byte == b'\'' {
+                // Skip the literal
+                i += 1;
+                while i < bytes.len() && bytes[i] != byte {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+                continue;
+            }
+            let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'.';
+            // An integer cast hides the type of its operand: skip both
+            if byte == b'(' {
+                if let Some(close) = self.find_matching_paren(expr, i) {
+                    if self.basic_cast(&expr[i + 1..close], &["int", "long", "short", "char"]).is_some() {
+                        let operand = expr[close + 1..].trim_start();
+                        i = bytes.len() - operand.len();
+                        if operand.starts_with('(') {
+                            i = self.find_matching_paren(expr, i).map_or(bytes.len(), |end| end + 1);
+                        } else {
+                            while i < bytes.len() && is_word(bytes[i]) {
+                                i += 1;
+                            }
+                            while bytes.get(i) == Some(&b'[') {
+                                i += expr[i..].find(']').map_or(bytes.len(), |end| end + 1);
+                            }
+                        }
+                        continue;
+                    }
+                }
+            }
+            if !is_word(byte) {
+                i += 1;
+                continue;
+            }
+            // A member has its member's type: p.x, pts[i].x
+            if byte.is_ascii_alphabetic() || byte == b'_' {
+                if let Some((base, ops, rest)) = self.parse_postfix_prefix(&expr[i..]) {
+                    if ops.iter().any(|op| !matches!(op, Postfix::Index(_))) {
+                        if matches!(self.postfix_type(base, &ops).as_deref(), Some("float" | "double")) {
+                            return true;
+                        }
+                        i = bytes.len() - rest.len();
+                        continue;
+                    }
+                }
+            }
+            let start = i;
+            while i < bytes.len() && (is_word(bytes[i]) || (matches!(bytes[i], b'+' | b'-') && matches!(bytes[i - 1], b'e' | b'E') && bytes[start].is_ascii_digit())) {
+                i += 1;
+            }
+            let word = &expr[start..i];
+            let dereferenced = expr[..start].trim_end().ends_with('*') || expr[i..].trim_start().starts_with('[');
+            let is_float = if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                !word.starts_with("0x") && !word.starts_with("0X") && word.contains(['.', 'e', 'E'])
+            } else if word == "float" || word == "double" {
+                true
+            } else if let Some(function) = self.functions.get(word) {
+                matches!(function.return_type.as_str(), "float" | "double")
+            } else if Self::is_double_math_function(word) && expr[i..].trim_start().starts_with('(') {
+                true
+            } else {
+                match self.variables.get(word) {
+                    Some(Value::Float(_)) => true,
+                    Some(Value::Array(_)) => dereferenced && matches!(
+                        self.variable_types.get(word).map(String::as_str), Some("float" | "double")),
+                    Some(Value::Pointer(_)) => dereferenced && matches!(
+                        self.pointee_types.get(word).map(String::as_str), Some("float" | "double")),
+                    _ => false,
+                }
+            };
+            if is_float {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn evaluate_numeric_expression(&mut self, expr: &str) -> Result<i64, InterpError> {
+        let expr = expr.trim();
+        
+        // Check if it's a number
+        if let Some(num) = parse_integer_literal(expr) {
+            return Ok(num);
+        }
+        
+        // A char literal is its character code, so 'Z' - 'A' is 25
+        if expr.starts_with('\'') && matches!(lexer::tokenize(expr)[..], [token] if token.kind == TokenKind::CharLiteral) {
+            return Ok(parse_char_literal(expr)? as i64);
+        }
+
+        // Float arithmetic is done in floating point, then truncated
+        if self.is_floating_expression(expr) {
+            return Ok(Self::as_i64(&self.evaluate_arithmetic(expr)?));
+        }
+
+        self.compute_numeric_expression(expr)
+    }
+
+    /// Evaluate `expr` in integer arithmetic, past the literal and float
+    /// cases of `evaluate_numeric_expression`
+    fn compute_numeric_expression(&mut self, expr: &str) -> Result<i64, InterpError> {
+        // Check if it's a float
+        if let Ok(num) = expr.parse::<f64>() {
+            return Ok(num as i64);
+        }
+
+        // stdbool's true/false, stdlib's RAND_MAX, and stdio's EOF and NULL
+        match expr {
+            "true" => return Ok(1),
+            "false" => return Ok(0),
+            "RAND_MAX" => return Ok(RAND_MAX),
+            "EOF" => return Ok(-1),
+            "NULL" => return Ok(0),
+            _ => {}
+        }
+
+        // An assignment used as a value, as in while ((c = getchar()) != EOF),
+        // yields what was stored in its target
+        if let Some(pos) = self.find_assignment(expr) {
+            let target = expr[..pos].trim_end_matches(|c| "+-*/%<>&|^".contains(c)).trim();
+            self.handle_variable_statement(expr)?;
+            return self.evaluate_numeric_expression(target);
+        }
+        
+        // Check if it's a variable
+        if let Some(value) = self.variables.get(expr) {
+            return match value {
+                Value::Int(i) => Ok(*i),
+                Value::Float(f) => Ok(*f as i64),
+                Value::Char(c) => Ok(Self::char_as_i64(*c)),
+                Value::Bool(b) => Ok(*b as i64),
+                Value::String(_) => Err("Cannot convert string to number".into()),
+                Value::Array(_) => Err("Cannot convert array to number".into()),
+                Value::Struct(_) => Err("Cannot convert struct to number".into()),
+                Value::Pointer(addr) => Ok(*addr), // Pointer can be used as integer (address)
+            };
+        }
+
+        // Handle array element and member access, directly or through a pointer
+        if let Some((base, ops)) = self.parse_postfix(expr) {
+            if let Some(element) = self.read_postfix(base, &ops)? {
+                return match element {
+                    Value::Int(i) | Value::Pointer(i) => Ok(i),
+                    Value::Float(f) => Ok(f as i64),
+                    Value::Char(c) => Ok(Self::char_as_i64(c)),
+                    Value::Bool(b) => Ok(b as i64),
+                    _ => Err("Invalid array element type".into()),
+                };
+            }
+        }
+
+        // Handle pointer dereference: *ptr, or *p++ reading before the step.
+        // In *p + 1 the * applies to p alone, so that is left to the operators.
+        let dereferenced = expr.strip_prefix('*')
+            .filter(|ptr_expr| self.is_single_operand(ptr_expr.trim_end_matches(['+', '-'])));
+        if let Some(ptr_expr) = dereferenced {
+            let ptr_expr = ptr_expr.trim();
+            let addr = match self.variables.get(ptr_expr) {
+                Some(&Value::Pointer(addr)) => Some(addr),
+                // An array name stands for its first element
+                Some(Value::Array(_)) => self.memory.address_map.get(ptr_expr).copied(),
+                _ if ptr_expr.ends_with("++") || ptr_expr.ends_with("--") => {
+                    self.evaluate_increment(ptr_expr)?.map(|value| Self::as_i64(&value))
+                }
+                _ if ptr_expr.starts_with('(') => match self.evaluate_pointer_expression(ptr_expr)? {
+                    Value::Pointer(addr) => Some(addr),
+                    _ => None,
+                },
+                // A pointer held in an element, as in *names[1]
+                _ => match self.parse_postfix(ptr_expr) {
+                    Some((base, ops)) => match self.read_postfix(base, &ops)? {
+                        Some(Value::Pointer(addr)) => Some(addr),
+                        _ => None,
+                    },
+                    None => None,
+                },
+            };
+            if let Some(addr) = addr {
+                let value = self.read_address(addr)?;
+                return match value {
+                    Value::Int(i) => Ok(i),
+                    Value::Float(f) => Ok(f as i64),
+                    Value::Char(c) => Ok(Self::char_as_i64(c)),
+                    Value::Bool(b) => Ok(b as i64),
+                    _ => Err("Cannot dereference to numeric value".into()),
+                };
+            } else {
+                return Err(format!("'{}' is not a valid pointer", ptr_expr).into());
+            }
+        }
+
+        // Handle address-of operator: &variable or &arr[i] (returns address as number)
+        if expr.starts_with('&') && self.is_single_operand(expr) {
+            return Ok(Self::as_i64(&self.evaluate_pointer_expression(expr)?));
+        }
+
+        // Handle parentheses around the whole expression, but not `(a) + (b)`
+        if expr.starts_with('(') && self.find_matching_paren(expr, 0) == Some(expr.len() - 1) {
+            return self.evaluate_numeric_expression(&expr[1..expr.len() - 1]);
+        }
+
+        // Handle an integer cast such as (int)x, truncating a float operand
+        if expr.starts_with('(') {
+            if let Some(close) = self.find_matching_paren(expr, 0) {
+                let cast = self.basic_cast(&expr[1..close], &["int", "long", "short", "char"]);
+                if let Some(cast) = cast.filter(|_| self.is_single_operand(&expr[close + 1..])) {
+                    let value = self.evaluate_arithmetic(&expr[close + 1..])?;
+                    return Ok(Self::as_i64(&Self::convert_value(cast, value)));
+                }
+            }
+        }
+
+        // Handle sizeof applied to a type or an object
+        if self.starts_with_keyword(expr, "sizeof") {
+            let operand = expr["sizeof".len()..].trim_start();
+            let whole = if operand.starts_with('(') {
+                self.find_matching_paren(operand, 0) == Some(operand.len() - 1)
+            } else {
+                // Without parentheses the operand is a variable, possibly
+                // subscripted: sizeof arr[0]
+                !operand.is_empty() && (operand.chars().all(|c| c.is_alphanumeric() || c == '_') ||
+                    self.parse_postfix(operand).is_some())
+            };
+            if whole {
+                return self.evaluate_sizeof(operand);
+            }
+        }
+
+        if self.parse_library_call(expr, &["rand"]).is_some_and(|(_, args)| args.trim().is_empty()) {
+            return Ok(self.next_random());
+        }
+
+        // scanf(...) reads input and counts the conversions it stored
+        if self.parse_library_call(expr, &["scanf"]).is_some() {
+            return self.evaluate_scanf(expr);
+        }
+
+        // getchar() reads the next input character, -1 (EOF) when there is none
+        if self.parse_library_call(expr, &["getchar"]).is_some_and(|(_, args)| args.trim().is_empty()) {
+            return Ok(self.input.next_char().map_or(-1, |c| c as i64));
+        }
+
+        // putchar(c) writes c as an unsigned char and returns it
+        if let Some((_, arg)) = self.parse_library_call(expr, &["putchar"]) {
+            let byte = Self::as_i64(&self.evaluate_value_expression(arg)?) as u8;
+            self.write_output(char::from(byte).encode_utf8(&mut [0; 4]))?;
+            return Ok(byte as i64);
+        }
+
+        // Handle a strcmp or strncmp call used as a value
+        if self.parse_library_call(expr, &["strcmp", "strncmp"]).is_some() {
+            return self.evaluate_strcmp(expr);
+        }
+
+        // abs(), or a double math function whose result is truncated
+        if let Some((name, args)) = self.parse_math_call(expr) {
+            return Ok(Self::as_i64(&self.evaluate_math_call(name, args)?));
+        }
+
+        // strlen's byte count, and the destination strcpy/strcat return
+        if let Some((_, arg)) = self.parse_library_call(expr, &["strlen"]) {
+            return Ok(self.string_argument(arg)?.len() as i64);
+        }
+        if self.parse_library_call(expr, &["sprintf"]).is_some() {
+            return self.evaluate_sprintf(expr);
+        }
+        if self.parse_library_call(expr, STRING_COPY_FUNCTIONS).is_some() {
+            return Ok(Self::as_i64(&self.evaluate_string_copy(expr)?));
+        }
+
+        // strspn/strcspn lengths, and strpbrk's pointer as an address
+        if self.parse_library_call(expr, STRING_SCAN_FUNCTIONS).is_some() {
+            return Ok(Self::as_i64(&self.evaluate_string_scan(expr)?));
+        }
+
+        // Handle a call to a user-defined function
+        if let Some((name, args)) = self.parse_call(expr) {
+            return match self.call_function(name, args)? {
+                Value::Int(i) => Ok(i),
+                Value::Float(f) => Ok(f as i64),
+                Value::Char(c) => Ok(Self::char_as_i64(c)),
+                Value::Bool(b) => Ok(b as i64),
+                Value::Pointer(addr) => Ok(addr),
+                _ => Err(format!("Error: '{}' does not return a number", name).into()),
+            };
+        }
+
+        // Handle ternary operator
+        if let Some((condition, true_expr, false_expr)) = self.split_conditional(expr) {
+            if self.evaluate_condition(condition)? {
+                return self.evaluate_numeric_expression(true_expr);
+            } else {
+                return self.evaluate_numeric_expression(false_expr);
+            }
+        }
+
+        // Comparisons and logical operators yield 0 or 1
+        if self.find_top_level_operator(expr, &["||", "&&", "==", "!=", "<=", ">=", "<", ">"], false).is_some() {
+            return Ok(self.evaluate_condition(expr)? as i64);
+        }
+
+        // Handle bitwise operators
+        let chars: Vec<char> = expr.chars().collect();
+        let mut depth = 0;
+        
+        // Handle bitwise OR
+        for i in (0..chars.len()).rev() {
+            match chars[i] {
+                ')' => depth += 1,
+                '(' => depth -= 1,
+                '|' if depth == 0 && (i == 0 || chars[i-1] != '|') && (i == chars.len()-1 || chars[i+1] != '|') => {
+                    let left = self.evaluate_numeric_expression(&expr[..i])?;
+                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
+                    return Ok(left | right);
+                }
+                _ => {}
+            }
+        }
+
+        // Handle bitwise AND; an & with no operand before it is address-of
+        depth = 0;
+        for i in (0..chars.len()).rev() {
+            match chars[i] {
+                ')' => depth += 1,
+                '(' => depth -= 1,
+                '&' if depth == 0 && (i == 0 || chars[i-1] != '&') && (i == chars.len()-1 || chars[i+1] != '&')
+                    && expr[..i].trim_end().ends_with(|c: char| c.is_alphanumeric() || "_)]'".contains(c)) => {
+                    let left = self.evaluate_numeric_expression(&expr[..i])?;
+                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
+                    return Ok(left & right);
+                }
+                _ => {}
+            }
+        }
+
+        // Handle bitwise XOR
+        depth = 0;
+        for i in (0..chars.len()).rev() {
+            match chars[i] {
+                ')' => depth += 1,
+                '(' => depth -= 1,
+                '^' if depth == 0 => {
+                    let left = self.evaluate_numeric_expression(&expr[..i])?;
+                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
+                    return Ok(left ^ right);
+                }
+                _ => {}
+            }
+        }
+
+        // Handle bit shifts outside parentheses; the rightmost split keeps
+        // them left-associative
+        if let Some((pos, op)) = self.find_top_level_operator(expr, &["<<", ">>"], true) {
+            let left = self.evaluate_numeric_expression(&expr[..pos])?;
+            let right = self.evaluate_numeric_expression(&expr[pos + op.len()..])?;
+            return shift(left, op, right);
+        }
+        
+        // Handle arithmetic, lowest precedence first; the rightmost split
+        // keeps it left-associative
+        if let Some((i, op)) = self.find_binary_operator(expr, &['+', '-']) {
+            if op == '+' && self.is_string_operand(&expr[..i]) && self.is_string_operand(&expr[i + 1..]) {
+                return Err(format!("Error: invalid operands to binary + in '{}': C has no string \
+                    concatenation operator; use strcat to append one string to another", expr.trim()).into());
+            }
+            // Addresses step by whole elements, and two of them differ by a count of elements
+            match (self.is_address_operand(&expr[..i]), self.is_address_operand(&expr[i + 1..])) {
+                (true, false) => return Ok(Self::as_i64(&self.evaluate_pointer_expression(expr)?)),
+                (true, true) if op == '-' => {
+                    let left = Self::as_i64(&self.evaluate_pointer_expression(&expr[..i])?);
+                    let right = Self::as_i64(&self.evaluate_pointer_expression(&expr[i + 1..])?);
+                    return Ok((left - right) / 8);
+                }
+                _ => {}
+            }
+            let left = self.evaluate_numeric_expression(&expr[..i])?;
+            let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
+            return arithmetic(left, op, right);
+        }
+        if let Some((i, op)) = self.find_binary_operator(expr, &['*', '/', '%']) {
+            if op == '%' {
+                self.check_modulo_operands(&expr[..i], &expr[i + 1..])?;
+            }
+            let left = self.evaluate_numeric_expression(&expr[..i])?;
+            let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
+            return arithmetic(left, op, right);
+        }
+
+        // Handle ++/-- applied to a variable, element or dereference
+        if let Some(value) = self.evaluate_increment(expr)? {
+            return Ok(Self::as_i64(&value));
+        }
+
+        // Handle unary minus
+        if let Some(operand) = expr.strip_prefix('-') {
+            let val = self.evaluate_numeric_expression(operand)?;
+            return Ok(val.wrapping_neg());
+        }
+
+        // Handle logical NOT
+        if let Some(operand) = expr.strip_prefix('!') {
+            return Ok(!self.evaluate_condition(operand)? as i64);
+        }
+
+        // Handle bitwise NOT
+        if let Some(operand) = expr.strip_prefix('~') {
+            let val = self.evaluate_numeric_expression(operand)?;
+            return Ok(!val);
+        }
+        
+        let is_identifier = expr.starts_with(|c: char| c.is_alphabetic() || c == '_') &&
+            expr.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if self.options.lenient && is_identifier {
+            let warning = format!("Warning: '{}' undeclared, treating it as 0", expr);
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+            return Ok(0);
+        }
+        
+        Err(format!("Error: Cannot evaluate expression: {}", expr).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_hello_world() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                printf("Hello, World!\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Hello, World!"));
+    }
+
+    #[test]
+    fn test_for_loop_with_break() {
+        let code = r#"
+            int main() {
+                for(int i = 0; i < 10; i++) {
+                    if(i == 5) {
+                        break;
+                    }
+                    printf("%d ", i);
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("0 1 2 3 4"));
+    }
+
+    #[test]
+    fn test_else_statement() {
+        let code = r#"
+            int main() {
+                int x = 5;
+                if(x > 10) {
+                    printf("Greater\n");
+                } else {
+                    printf("Smaller\n");
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Smaller"));
+    }
+
+    #[test]
+    fn test_scanf_char_reads_whitespace() {
+        let code = r#"
+            int main() {
+                int n;
+                char c;
+                scanf("%d%c", &n, &c);
+                printf("[%c]", c);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("needs_input"));
+        let result = provide_input("5 x");
+        assert!(result.contains("[ ]"));
+    }
+
+    #[test]
+    fn test_scanf_char_with_leading_space_skips_whitespace() {
+        let code = r#"
+            int main() {
+                int n;
+                char c;
+                scanf("%d %c", &n, &c);
+                printf("[%c]", c);
+                return 0;
+            }
+        "#;
+        
+        compile_and_run_c(code);
+        let result = provide_input("5   x");
+        assert!(result.contains("[x]"));
+    }
+
+    #[test]
+    fn test_scanf_partial_match_leaves_rest_of_input() {
+        let code = r#"
+            int main() {
+                int a = 0, b = 0;
+                char word[10];
+                int n = scanf("%d %d", &a, &b);
+                printf("%d %d %d\n", n, a, b);
+                scanf("%s", word);
+                printf("%s\n", word);
+                return 0;
+            }
+        "#;
+        
+        compile_and_run_c(code);
+        let result = provide_input("5 abc");
+        assert!(result.contains(r#""output":"1 5 0\nabc\n""#));
+    }
+
+    #[test]
+    fn test_return_inside_switch_case_stops_main() {
+        let code = r#"
+            int main() {
+                int x = 2;
+                switch (x) {
+                    case 1:
+                        printf("one\n");
+                        break;
+                    case 2:
+                        printf("two\n");
+                        return 0;
+                    case 3:
+                        printf("three\n");
+                        break;
+                }
+                printf("after switch\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("two"));
+        assert!(!result.contains("three"));
+        assert!(!result.contains("after switch"));
+    }
+
+    #[test]
+    fn test_exit_inside_nested_block() {
+        let code = r#"
+            int main() {
+                for (int i = 0; i < 5; i++) {
+                    if (i == 2) {
+                        printf("exiting\n");
+                        exit(0);
+                    }
+                    printf("%d\n", i);
+                }
+                printf("unreachable\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0\n1\nexiting\n""#));
+        assert!(!result.contains("unreachable"));
+    }
+
+    #[test]
+    fn test_modulo_rejects_floating_operands() {
+        let code = r#"
+            int main() {
+                double r = 5.5 % 2.0;
+                printf("%f\n", r);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""success":false"#));
+        assert!(result.contains("invalid operands to binary % (have 'double' and 'double')"));
+    }
+
+    #[test]
+    fn test_modulo_on_integers() {
+        let code = r#"
+            int main() {
+                int a = 17;
+                int r = a % 5;
+                printf("%d\n", r);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"2\n""#));
+    }
+
+    #[test]
+    fn test_leak_report_counts_unfreed_blocks() {
+        let code = r#"
+            int main() {
+                int *p = malloc(40);
+                int *q = (int *)malloc(8);
+                free(q);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""leaked_blocks":1"#));
+        assert!(result.contains(r#""leaked_bytes":40"#));
+    }
+
+    #[test]
+    fn test_leak_report_is_zero_when_everything_is_freed() {
+        let code = r#"
+            int main() {
+                int *p = malloc(40);
+                *p = 7;
+                printf("%d\n", *p);
+                free(p);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"7\n""#));
+        assert!(result.contains(r#""leaked_blocks":0"#));
+        assert!(result.contains(r#""leaked_bytes":0"#));
+    }
+
+    #[test]
+    fn test_string_literal_pointer_indexing() {
+        let code = r#"
+            int main() {
+                char *s = "hello";
+                printf("%c%c\n", s[1], s[4]);
+                printf("%s\n", s);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"eo\nhello\n""#));
+    }
+
+    #[test]
+    fn test_string_literal_is_read_only() {
+        let code = r#"
+            int main() {
+                char *s = "hello";
+                s[0] = 'j';
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("read-only string literal"));
+    }
+
+    #[test]
+    fn test_bool_prints_as_zero_or_one() {
+        let code = r#"
+            #include <stdbool.h>
+            int main() {
+                bool flag = false;
+                printf("%d\n", flag);
+                flag = !flag;
+                printf("%d\n", flag);
+                if (flag) {
+                    printf("on\n");
+                }
+                flag = 5 > 10;
+                printf("%d\n", flag);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0\n1\non\n0\n""#));
+    }
+
+    #[test]
+    fn test_bubble_sort_program() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int arr[7];
+                arr[0] = 64; arr[1] = 34; arr[2] = 25; arr[3] = 12;
+                arr[4] = 22; arr[5] = 11; arr[6] = 90;
+                int n = 7;
+                int i, j, temp;
+
+                for (i = 0; i < n - 1; i++) {
+                    for (j = 0; j < n - i - 1; j++) {
+                        if (arr[j] > arr[j + 1]) {
+                            temp = arr[j];
+                            arr[j] = arr[j + 1];
+                            arr[j + 1] = temp;
+                        }
+                    }
+                }
+
+                printf("Sorted array: ");
+                for (i = 0; i < n; i++) {
+                    printf("%d ", arr[i]);
+                }
+                printf("\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"Sorted array: 11 12 22 25 34 64 90 \n""#));
+    }
+
+    #[test]
+    fn test_multiple_declarators_in_one_declaration() {
+        let code = r#"
+            int main() {
+                int count, total = 5, *p;
+                printf("%d %d\n", count, total);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0 5\n""#));
+    }
+
+    #[test]
+    fn test_undef_removes_macro() {
+        let code = r#"
+            #include <stdio.h>
+            #define LIMIT 10
+            #define TWICE LIMIT * 2
+            int main() {
+                printf("%d %d\n", LIMIT, TWICE);
+            #undef LIMIT
+            #ifdef LIMIT
+                printf("still defined\n");
+            #else
+                printf("undefined\n");
+            #endif
+                int LIMIT = 3;
+                printf("%d\n", LIMIT);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"10 20\nundefined\n3\n""#));
+    }
+
+    #[test]
+    fn test_block_bodies_are_parsed_once() {
+        let code = r#"
+            int main() {
+                int total = 0;
+                for (int i = 0; i < 50; i++) {
+                    for (int j = 0; j < 50; j++) {
+                        total += j;
+                    }
+                }
+                printf("%d\n", total);
+                return 0;
+            }
+        "#;
+        
+        let mut interpreter = CInterpreter::new();
+        let output = interpreter.execute(code).unwrap();
+        assert_eq!(output, "61250\n");
+        // main's body, the outer loop body and the inner loop body, each
+        // split once no matter how many times they ran
+        assert_eq!(interpreter.statement_cache.len(), 3);
+    }
+
+    #[test]
+    fn test_calloc_reads_typed_zeros() {
+        let code = r#"
+            int main() {
+                int *a = calloc(4, 4);
+                double *d = (double *)calloc(3, 8);
+                printf("%d %d\n", a[0], a[3]);
+                printf("%f %f\n", d[0], d[2]);
+                free(a);
+                free(d);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0 0\n0.000000 0.000000\n""#));
+        assert!(result.contains(r#""leaked_blocks":0"#));
+    }
+
+    #[test]
+    fn test_postfix_chain_rejects_member_of_non_struct() {
+        let code = r#"
+            int main() {
+                int nums[3];
+                nums[2] = 6;
+                int *p = malloc(3 * 4);
+                p[1] = nums[ 2 ];
+                printf("%d\n", p[1]);
+                free(p);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"6\n""#));
+
+        let code = r#"
+            int main() {
+                int count = 3;
+                printf("%d\n", count.value);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("request for member 'value' in something not a structure"));
+    }
+
+    #[test]
+    fn test_lenient_mode_defaults_undeclared_to_zero() {
+        let code = r#"
+            int main() {
+                int total = count + 5;
+                printf("%d\n", total);
+                return 0;
+            }
+        "#;
+        
+        let strict = compile_and_run_c(code);
+        assert!(strict.contains(r#""success":false"#));
+        assert!(strict.contains("Cannot evaluate expression: count"));
+        
+        let lenient = compile_and_run_c_with_options(code, "", r#"{"lenient": true}"#);
+        assert!(lenient.contains(r#""success":true"#));
+        assert!(lenient.contains(r#""output":"5\n""#));
+        assert!(lenient.contains(r#""warnings":["Warning: 'count' undeclared, treating it as 0"]"#));
+    }
+
+    #[test]
+    fn test_do_while_zero_swap_macro() {
+        let code = r#"
+            #define SWAP(a, b) do { int t = a; a = b; b = t; } while (0)
+            #define SQUARE(x) \
+                ((x) * (x))
+
+            int main() {
+                int x = 3;
+                int y = 8;
+                SWAP(x, y);
+                printf("%d %d\n", x, y);
+                printf("%d\n", SQUARE(y + 1));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"8 3\n16\n""#));
+    }
+
+    #[test]
+    fn test_ungetc_pushes_char_back() {
+        let code = r#"
+            int main() {
+                char c;
+                char again;
+                scanf("%c", &c);
+                ungetc(c, stdin);
+                scanf("%c", &again);
+                printf("%c%c\n", c, again);
+                return 0;
+            }
+        "#;
+        
+        compile_and_run_c(code);
+        let result = provide_input("q");
+        assert!(result.contains(r#""output":"qq\n""#));
+    }
+
+    #[test]
+    fn test_printf_positional_arguments() {
+        let code = r#"
+            int main() {
+                int day = 17;
+                int month = 10;
+                printf("%2$d/%1$d\n", day, month);
+                printf("%s is %d%%\n", "progress", 50);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"10/17\nprogress is 50%\n""#));
+    }
+
+    #[test]
+    fn test_printf_width_and_precision() {
+        let code = r#"
+            int main() {
+                printf("%.2f\n", 3.14159);
+                printf("[%5d][%-5d][%05d]\n", 42, 42, -42);
+                printf("[%.3s][%6s]\n", "abcdef", "hi");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"3.14\n[   42][42   ][-0042]\n[abc][    hi]\n""#));
+    }
+
+    #[test]
+    fn test_over_parenthesized_conditions() {
+        let code = r#"
+            int main() {
+                int x = 5;
+                int a = 1;
+                int b = 0;
+                if (((x > 0))) {
+                    printf("positive\n");
+                }
+                if ( ( a && b ) || ((x - 2) * (x + 1) >= 18) ) {
+                    printf("compound\n");
+                }
+                if (!(a && b) && (x << 1) > 8) {
+                    printf("negated\n");
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"positive\ncompound\nnegated\n""#));
+    }
+
+    #[test]
+    fn test_scanf_converts_each_specifier() {
+        let code = r#"
+            int main() {
+                int n = 7;
+                float f = 1.5;
+                char c = 'z';
+                char word[20];
+                int got = scanf("%f %s %c", &f, word, &c);
+                printf("%d %.2f %s %c\n", got, f, word, c);
+                got = scanf("%d", &n);
+                printf("%d %d\n", got, n);
+                return 0;
+            }
+        "#;
+        
+        compile_and_run_c(code);
+        let result = provide_input("2.5 hello x oops");
+        assert!(result.contains(r#""output":"3 2.50 hello x\n0 7\n""#));
+    }
+
+    #[test]
+    fn test_run_with_stdin() {
+        let code = r#"
+            int main() {
+                int a, b;
+                scanf("%d", &a);
+                scanf("%d", &b);
+                printf("%d\n", a + b);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_input(code, "4\n5\n");
+        assert!(result.contains(r#""output":"9\n""#));
+        
+        // Input that runs out asks for more, and the answer is added to it
+        let result = compile_and_run_c_with_input(code, "4\n");
+        assert!(result.contains("needs_input"));
+        let result = provide_input("6");
+        assert!(result.contains(r#""output":"10\n""#));
+    }
+
+    #[test]
+    fn test_continue_inside_switch_continues_loop() {
+        let code = r#"
+            int main() {
+                int i;
+                for (i = 0; i < 4; i++) {
+                    switch (i) {
+                        case 1:
+                            printf("skip\n");
+                            continue;
+                            printf("unreachable\n");
+                        case 2:
+                            printf("two\n");
+                            break;
+                    }
+                    printf("after %d\n", i);
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"after 0\nskip\ntwo\nafter 2\nafter 3\n""#));
+    }
+
+    #[test]
+    fn test_printf_string_precision_truncates() {
+        let code = r#"
+            int main() {
+                char *word = "hello";
+                printf("[%.3s]\n", word);
+                printf("[%-10.3s]\n", "hello");
+                printf("[%10.3s]\n", word);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"[hel]\n[hel       ]\n[       hel]\n""#));
+    }
+
+    #[test]
+    fn test_strcmp_result_is_assigned_and_usable_in_conditions() {
+        let code = r#"
+            int main() {
+                char *name = "apple";
+                int c = strcmp(name, "apricot");
+                int same = strcmp("pear", "pear");
+                printf("%d %d %d\n", c, same, strcmp("b", "a"));
+                if (strcmp(name, "apple") == 0) {
+                    printf("equal\n");
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"-1 0 1\nequal\n""#));
+    }
+
+    #[test]
+    fn test_array_length_inferred_from_initializer() {
+        let code = r#"
+            int main() {
+                int a[] = {1, 2, 3, 4};
+                int n = sizeof(a) / sizeof(a[0]);
+                int i;
+                for (i = 0; i < n; i++) {
+                    printf("%d ", a[i]);
+                }
+                printf("\n%d %d\n", n, sizeof(a));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"1 2 3 4 \n4 16\n""#));
+    }
+
+    #[test]
+    fn test_user_defined_functions() {
+        let code = r#"
+            int calls = 0;
+
+            int add(int a, int b) {
+                calls++;
+                return a + b;
+            }
+
+            void swap(int *a, int *b) {
+                int t = *a;
+                *a = *b;
+                *b = t;
+            }
+
+            void fill_squares(int arr[], int n) {
+                int i;
+                for (i = 0; i < n; i++) {
+                    arr[i] = i * i;
+                }
+            }
+
+            int main() {
+                int x = 3, y = 9;
+                int t = 100;
+                int sum = add(x, y);
+                printf("%d %d\n", sum, add(1, add(2, 3)));
+                swap(&x, &y);
+                printf("%d %d %d\n", x, y, t);
+                int squares[4];
+                fill_squares(squares, 4);
+                printf("%d %d %d\n", squares[2], squares[3], calls);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"12 6\n9 3 100\n4 9 3\n""#));
+    }
+
+    #[test]
+    fn test_recursive_functions() {
+        let code = r#"
+            int fib(int n) {
+                if (n < 2) {
+                    return n;
+                }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            int forever(int n) {
+                return forever(n + 1);
+            }
+
+            int main() {
+                printf("%d\n", fib(15));
+                forever(0);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("maximum call depth of 200 exceeded in 'forever'"));
+        
+        let result = compile_and_run_c(&code.replace("forever(0);", ""));
+        assert!(result.contains(r#""output":"610\n""#));
+    }
+
+    #[test]
+    fn test_exit_inside_function_stops_program() {
+        let code = r#"
+            void stop() {
+                printf("stopping\n");
+                exit(1);
+                printf("unreachable\n");
+            }
+
+            int main() {
+                stop();
+                printf("unreachable\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"stopping\n""#));
+    }
+
+    #[test]
+    fn test_error_category_and_line() {
+        let code = r#"
+            int main() {
+                int zero = 0;
+                printf("before\n");
+                int x = 10 / zero;
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Division by zero"));
+        assert!(result.contains(r#""error_category":"runtime""#));
+        assert!(result.contains(r#""error_line":5"#));
+        assert!(result.contains(r#""error_column":17"#));
+        
+        // The column is that of the failing statement, not the line
+        let result = compile_and_run_c("int main() {\n    int a = 1; int b = a / 0;\n    return 0;\n}");
+        assert!(result.contains(r#""error_line":2,"error_column":16"#));
+        
+        let result = compile_and_run_c("int helper() { return 1; }");
+        assert!(result.contains(r#""error_category":"parse""#));
+        assert!(result.contains("No main function found"));
+    }
+
+    #[test]
+    fn test_return_halts_loops_and_main() {
+        let code = r#"
+            int find(int target) {
+                for (int i = 0; i < 10; i++) {
+                    while (1) {
+                        if (i == target) {
+                            return i * 10;
+                        }
+                        break;
+                    }
+                }
+                return -1;
+            }
+
+            int main() {
+                printf("%d %d\n", find(3), find(42));
+                for (int i = 0; i < 5; i++) {
+                    if (i == 2) {
+                        return 7;
+                    }
+                    printf("i=%d\n", i);
+                }
+                printf("unreachable\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"30 -1\ni=0\ni=1\n""#));
+    }
+
+    #[test]
+    fn test_comments_are_stripped() {
+        let code = r#"
+            // int main() { a stray { and ; in a comment
+            int main() {
+                /* a block comment with { braces; and ;
+                   spanning lines } */
+                int x = 5; // trailing ; comment }
+                printf("url: http://example.com /* not a comment */ %d\n", x);
+                char c = '/'; /* } */
+                printf("%c%c\n", c, '"');
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"url: http://example.com /* not a comment */ 5\n/\"\n""#));
+        
+        let result = compile_and_run_c("int main() {\n    int x = 1;\n    /* never closed\n    return 0;\n}\n");
+        assert!(result.contains("unterminated /* comment"));
+        assert!(result.contains(r#""error_line":3"#));
+    }
+
+    #[test]
+    fn test_comma_operator_in_for_condition() {
+        let code = r#"
+            int main() {
+                int checks = 0;
+                int n = 3;
+                for (int i = 0; printf("x"), checks++, i < n; i++) {
+                    printf("%d", i);
+                }
+                printf("\n%d\n", checks);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"x0x1x2x\n4\n""#));
+    }
+
+    #[test]
+    fn test_getchar_sees_newline_left_by_scanf() {
+        let code = r#"
+            int main() {
+                int n;
+                scanf("%d", &n);
+                int c = getchar();
+                char d = getchar();
+                getchar();
+                int e = getchar();
+                int end = getchar();
+                printf("%d %d %c %c %d\n", n, c, d, e, end);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_input(code, "42\nxyz");
+        assert!(result.contains(r#""output":"42 10 x z -1\n""#));
+    }
+
+    #[test]
+    fn test_nested_macro_expansion() {
+        let code = r#"
+            #define MAX 100
+            #define TWICE_MAX (MAX * 2)
+            #define SQUARE(x) ((x)*(x))
+            #define SUM_SQ(a, b) (SQUARE(a) + SQUARE(b))
+            int main() {
+                printf("%d %d %d\n", TWICE_MAX, SQUARE(3 + 1), SUM_SQ(2, MAX / 50));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"200 16 8\n""#));
+    }
+
+    #[test]
+    fn test_printf_null_string() {
+        let code = r#"
+            int main() {
+                char *s = 0;
+                printf("[%s] [%8s] [%-7s] [%.3s]\n", s, s, s, s);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"[(null)] [  (null)] [(null) ] []\n""#));
+    }
+
+    #[test]
+    fn test_constant_expressions_are_folded_once() {
+        let code = r#"
+            #define SIZE 100
+            int main() {
+                int arr[10];
+                for (int i = 0; i < 10; i++) {
+                    arr[i] = 2 * 3 + SIZE;
+                }
+                printf("%d %d\n", arr[0], arr[9] + (4 + 2) % 5);
+                int x = 10;
+                int y = x * 2 + 3;
+                int z = x - 2 + 3;
+                int w = 20;
+                w = 5 - 2;
+                w -= 2 * 2;
+                if (x < 0) {
+                    x = 1 / 0;
+                }
+                printf("%d %d %d %d\n", y, z, w, -(7 - 9));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"106 107\n23 11 -1 2\n""#));
+        
+        // Whole operands fold; ones bound by a tighter operator, assignments
+        // and a division by zero stay for the run to evaluate
+        let mut interpreter = CInterpreter::new();
+        assert_eq!(interpreter.fold_constants("arr[i] = 2 * 3 + 100").as_deref(), Some("arr[i] = 106"));
+        assert_eq!(interpreter.fold_constants("y = x + (4 + 2) % 5").as_deref(), Some("y = x + 1"));
+        assert_eq!(interpreter.fold_constants("y = x * 2 + 3"), None);
+        assert_eq!(interpreter.fold_constants("y = x - 2 + 3"), None);
+        assert_eq!(interpreter.fold_constants("x = 1 / 0"), None);
+        assert_eq!(interpreter.fold_constants("f(1 + 2, sizeof (8))").as_deref(), Some("f(3, sizeof (8))"));
+    }
+
+    #[test]
+    fn test_partial_and_string_array_initializers() {
+        let code = r#"
+            int main() {
+                int b[5] = {4, 2 * 3};
+                char s[] = "hi";
+                printf("%d %d %d %d\n", b[0], b[1], b[4], sizeof(b) / sizeof(int));
+                printf("%d %c %c %d\n", sizeof(s), s[0], s[1], s[2]);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"4 6 0 5\n3 h i 0\n""#));
+    }
+
+    #[test]
+    fn test_break_and_continue_with_space_before_semicolon() {
+        let code = r#"
+            int main() {
+                for (int i = 0; i < 10; i++) {
+                    if (i == 1) {
+                        continue /* skip */ ;
+                    }
+                    if (i == 3) {
+                        break ;
+                    }
+                    printf("%d ", i);
+                }
+                printf("\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0 2 \n""#));
+    }
+
+    #[test]
+    fn test_char_arrays_as_strings() {
+        let code = r#"
+            int main() {
+                char name[20];
+                strcpy(name, "Alice");
+                strcat(name, " B");
+                int n = strlen(name);
+                printf("%s|%d|%c\n", name, n, name[6]);
+                char small[3];
+                strcpy(small, name);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(&code.replace("strcpy(small, name);", ""));
+        assert!(result.contains(r#""output":"Alice B|7|B\n""#));
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("strcpy: 'small' holds 3 chars"));
+        assert!(result.contains(r#""error_category":"memory""#));
+    }
+
+    #[test]
+    fn test_free_null_is_a_no_op() {
+        let code = r#"
+            int main() {
+                int *p = NULL;
+                free(p);
+                free(0);
+                printf("ok\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""success":true"#));
+        assert!(result.contains(r#""output":"ok\n""#));
+    }
+
+    #[test]
+    fn test_double_free_is_detected() {
+        let code = r#"
+            int main() {
+                int *p = malloc(4 * sizeof(int));
+                int *q = p;
+                free(p);
+                free(q);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("double free detected"));
+        assert!(result.contains(r#""error_category":"memory""#));
+    }
+
+    #[test]
+    fn test_condition_precedence_and_grouping() {
+        let code = r#"
+            int main() {
+                int a = 0, b = 1, c = 0;
+                int x = 1, y = 5, z = 3;
+                if ((a || b) && c) { printf("A"); } else { printf("a"); }
+                if (a == 1 || b == 2 && c == 3) { printf("B"); } else { printf("b"); }
+                if (x < y && z < x || b) { printf("C"); } else { printf("c"); }
+                if (x == 1 || y == 2 && z == 0) { printf("D"); } else { printf("d"); }
+                if ((x < y) == (z < y) && !(a || c)) { printf("E"); } else { printf("e"); }
+                if (x + 1 < y - 1 && (y > 4 || a)) { printf("F"); } else { printf("f"); }
+                printf("\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"abCDEF\n""#));
+    }
+
+    #[test]
+    fn test_switch_statements_on_one_line_and_char_cases() {
+        let code = r#"
+            #define TWO 2
+            int main() {
+                char grades[] = "AB;Cx";
+                for (int i = 0; i < 5; i++) {
+                    switch (grades[i]) {
+                        default: printf("other "); break;
+                        case 'A': printf("A "); case 'B': printf("AB "); break;
+                        case ';': printf("semi "); break;
+                        case 'C': {
+                            printf("C ");
+                            if (i > 0) { printf("late "); }
+                        }
+                    }
+                }
+                printf("\n");
+                int n = 2;
+                switch (n) { case 1 + 1: case 3: printf("two-or-three "); n++; printf("%d", n); break; case TWO * 2: printf("four"); }
+                printf("\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"A AB AB semi C late other \ntwo-or-three 3\n""#));
+    }
+
+    #[test]
+    fn test_scanf_hex_and_double() {
+        let code = r#"
+            int main() {
+                int a, b;
+                double d;
+                float f;
+                scanf("%x %X %lf %f", &a, &b, &d, &f);
+                printf("%d %d %.3f %.1f\n", a, b, d, f);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_input(code, "1f 0xFF 2.5e1 -0.5\n");
+        assert!(result.contains(r#""output":"31 255 25.000 -0.5\n""#));
+    }
+
+    #[test]
+    fn test_exit_code_from_main_and_exit() {
+        let result = compile_and_run_c("int main() {\n    printf(\"hi\\n\");\n    return 42;\n}\n");
+        assert!(result.contains(r#""exit_code":42"#));
+        
+        let result = compile_and_run_c("void main() {\n    printf(\"hi\\n\");\n}\n");
+        assert!(result.contains(r#""exit_code":0"#));
+        
+        let code = r#"
+            void fail(int code) {
+                exit(code + 1);
+            }
+
+            int main() {
+                fail(2);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""exit_code":3"#));
+    }
+
+    #[test]
+    fn test_errors_are_prefixed_with_their_line() {
+        let code = r#"int divide(int a, int b) {
+    int q = a / b;
+    return q;
+}
+
+int main() {
+    int total = 0;
+    for (int i = 3; i >= 0; i--) {
+        total += 12 % i;
+        total += divide(12, i);
+    }
+    return 0;
+}
+"#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""error":"line 9: Error: Modulo by zero""#));
+        
+        let result = compile_and_run_c(&code.replace("total += 12 % i;", "total += 1;"));
+        assert!(result.contains(r#""error":"line 2: Error: Division by zero""#));
+    }
+
+    #[test]
+    fn test_float_arithmetic_through_pointers_and_casts() {
+        let code = r#"
+            double half(double x) {
+                return x / 2;
+            }
+
+            int main() {
+                float f = 2.5;
+                float *p = &f;
+                printf("%f %.2f\n", *p, *p * 2 + 1);
+                *p = *p + 0.25;
+                printf("%.2f %.2f\n", f, *p);
+                double d = 7 / 2;
+                double e = 7 / 2.0;
+                double g = (double)7 / 2;
+                int n = (int)e * 2;
+                printf("%.1f %.1f %.1f %d\n", d, e, g, n);
+                double sum = half(f) + 1e-1 - -1;
+                printf("%.3f\n", sum);
+                double *q = malloc(2 * sizeof(double));
+                q[0] = 1.5;
+                q[1] = q[0] * 3;
+                printf("%.2f %.2f\n", q[1], q[0] + q[1]);
+                if (f > 2.6 && e < 3.6) { printf("yes\n"); }
+                int k = e;
+                printf("%d %d\n", k, (int)(e * 10) % 7);
+                free(q);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"2.500000 6.00\n2.75 2.75\n3.0 3.5 3.5 6\n2.475\n4.50 6.00\nyes\n3 0\n""#));
+    }
+
+    #[test]
+    fn test_printf_star_width_and_precision() {
+        let code = r#"
+            int main() {
+                int n = 4;
+                double pi = 3.14159;
+                printf("[%*d] [%-*d] [%*d]\n", n + 1, 42, n * 2 - 2, 7, -n, 3);
+                printf("[%.*f] [%*.*f] [%.*s]\n", n - 2, pi, 8, n / 2 + 1, pi, 3, "abcdef");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"[   42] [7     ] [3   ]\n[3.14] [   3.142] [abc]\n""#));
+    }
+
+    #[test]
+    fn test_macro_side_effect_argument_warning() {
+        let code = r#"
+            #define MAX(a, b) ((a) > (b) ? (a) : (b))
+            int main() {
+                int i = 5;
+                int j = 3;
+                int m = MAX(i++, j);
+                return m;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_options(code, "", r#"{"macro_warnings": true}"#);
+        assert!(result.contains(r#""warnings":["Warning: macro 'MAX' evaluates argument 'i++' 2 times"]"#));
+        
+        // The diagnostic is opt-in
+        let result = compile_and_run_c(code);
+        assert!(!result.contains("\"warnings\""));
+        
+        let code = r#"
+            #define MAX(a, b) ((a) > (b) ? (a) : (b))
+            #define SQUARE(x) ((x) * (x))
+            int twice(int n) {
+                printf("call %d\n", n);
+                return n * 2;
+            }
+            int main() {
+                int j = 3;
+                int m = MAX(twice(j), j);
+                int s = SQUARE(j);
+                printf("%d %d\n", m, s);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_options(code, "", r#"{"macro_warnings": true}"#);
+        assert!(result.contains(r#""output":"call 3\ncall 3\n6 9\n""#));
+        assert!(result.contains(r#""warnings":["Warning: macro 'MAX' evaluates argument 'twice(j)' 2 times"]"#));
+    }
+
+    #[test]
+    fn test_integer_widths_and_unsigned() {
+        let code = r#"
+            unsigned int same(unsigned int x) {
+                return x;
+            }
+            int main() {
+                int big = 2147483647;
+                big = big + 1;
+                unsigned int u = -1;
+                short s = 32767;
+                s++;
+                long n = 2147483647;
+                n += 1;
+                printf("%d %u %u %d %ld\n", big, u, same(-2), s, n);
+                printf("%x %lu %d\n", -1, (long)u + 1, (int)sizeof(unsigned short));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"-2147483648 4294967295 4294967294 -32768 2147483648\nffffffff 4294967296 2\n""#));
+    }
+
+    #[test]
+    fn test_double_and_long_array_sums() {
+        let code = r#"
+            int main() {
+                double vals[4] = {1.25, 2.5, 3.125, 0.5};
+                long big[3] = {3000000000, 2, 1};
+                double total = 0;
+                long sum = 0;
+                for (int i = 0; i < 4; i++) {
+                    total = total + vals[i];
+                }
+                for (int i = 0; i < 3; i++) {
+                    sum = sum + big[i] * 2;
+                }
+                printf("%f %.2f %ld\n", total, (vals[0] + vals[2]) / 2, sum);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"7.375000 2.19 6000000006\n""#));
+    }
+
+    #[test]
+    fn test_type_qualifiers_in_declarations() {
+        let code = r#"
+            static int calls = 0;
+            const int LIMIT = 3;
+            int bump(const int step) {
+                calls = calls + step;
+                return calls;
+            }
+            int main() {
+                const double rate = 1.5;
+                volatile int v = 2;
+                register int r = 4;
+                const char *name = "cweb";
+                name = "web";
+                for (int i = 0; i < LIMIT; i++) {
+                    bump(v);
+                }
+                printf("%d %.1f %s\n", calls, rate * r, name);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"6 6.0 web\n""#));
+        
+        let code = r#"
+            int main() {
+                const int limit = 3;
+                limit += 1;
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("assignment of read-only variable 'limit'"));
+    }
+
+    #[test]
+    fn test_strspn_strcspn_strpbrk() {
+        let code = r##"
+            int main() {
+                char *line = "key=value;rest";
+                char word[16] = "abc,def";
+                int digits = strspn("123abc", "0123456789");
+                int n = strcspn(line, "=;") + 1;
+                char *sep = strpbrk(line, ";=");
+                char *none = strpbrk(line, "#");
+                printf("%d %d %s\n", digits, n, sep);
+                if (none == 0 && strpbrk(word, ",")) {
+                    printf("%d %d\n", strcspn(word, ","), strspn(word, "abc"));
+                }
+                return 0;
+            }
+        "##;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"3 4 =value;rest\n3 3\n""#));
+    }
+
+    #[test]
+    fn test_calls_nested_in_expressions() {
+        let code = r#"
+            int max(int a, int b) {
+                return a > b ? a : b;
+            }
+            int main() {
+                int a = 3, b = 4;
+                double h;
+                h = sqrt(a * a + b * b);
+                int p = pow(2, abs(-3));
+                int q = 1 + max(abs(-9), b) * 2;
+                printf("%.1f %.1f %d %d\n", h, sqrt(h + 11) * 2, p, q);
+                printf("%d %.2f\n", abs(a - b) + max(1, 2), fabs(-2.5) + floor(h / 2));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"5.0 8.0 8 19\n3 4.50\n""#));
+    }
+
+    #[test]
+    fn test_for_clauses_with_commas() {
+        let code = r#"
+            int max(int a, int b) {
+                return a > b ? a : b;
+            }
+            int main() {
+                int i, j;
+                for (i = 0, j = 10; i < j; i++, j--) {
+                    printf("%d-%d ", i, j);
+                }
+                for (int k = max(1, 2), n = 0; k < 5; k += max(1, 0), n++) {
+                    printf("[%d %d]", k, n);
+                }
+                printf("\n");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0-10 1-9 2-8 3-7 4-6 [2 0][3 1][4 2]\n""#));
+    }
+
+    #[test]
+    fn test_utf8_literals_pass_through() {
+        let code = r#"
+            int main() {
+                char *word = "café";
+                char name[16] = "naïve 🙂";
+                int n = strlen(word);
+                int m = strlen(name);
+                printf("%s %s %d %d\n", word, name, n, m);
+                puts("→ done");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"café naïve 🙂 5 11\n→ done\n""#));
+    }
+
+    #[test]
+    fn test_run_options_limits() {
+        let code = r#"
+            int depth(int n) {
+                if (n == 0) {
+                    return 0;
+                }
+                return 1 + depth(n - 1);
+            }
+            int main() {
+                int total = 0;
+                for (int i = 0; i < 50; i++) {
+                    total = total + i;
+                }
+                printf("%d %d\n", total, depth(5));
+                return 0;
+            }
+        "#;
+        
+        let defaults = compile_and_run_c_with_options(code, "", "");
+        assert!(defaults.contains(r#""output":"1225 5\n""#));
+        
+        let few_iterations = compile_and_run_c_with_options(code, "", r#"{"max_iterations": 10}"#);
+        assert!(few_iterations.contains("Loop exceeded maximum iterations"));
+        
+        let shallow = compile_and_run_c_with_options(code, "", r#"{"max_call_depth": 3}"#);
+        assert!(shallow.contains("maximum call depth of 3 exceeded in 'depth'"));
+        
+        let invalid = compile_and_run_c_with_options(code, "", r#"{"max_iterations": -1}"#);
+        assert!(invalid.contains("Invalid run options"));
+        assert!(invalid.contains(r#""error_category":"parse""#));
+    }
+
+    #[test]
+    fn test_string_functions_inside_expressions() {
+        let code = r#"
+            int main() {
+                char a[32] = "yes";
+                char b[32];
+                char *s = "hello";
+                if (strcmp(a, "yes") == 0) {
+                    printf("match %d\n", strlen(s));
+                }
+                int total = strlen(a) + strlen("abcd") * 2;
+                printf("%d %d\n", total, strlen(strcpy(b, "copy")));
+                printf("%s\n", strcat(b, "!"));
+                if (strlen(b) > 3 && strcmp(strcat(a, "s"), "yess") == 0) {
+                    printf("%s %s\n", a, b);
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"match 5\n11 4\ncopy!\nyess copy!\n""#));
+    }
+
+    #[test]
+    fn test_prefix_and_postfix_increment_in_expressions() {
+        let code = r#"
+            int main() {
+                int arr[5];
+                int i = 0;
+                int b = 5;
+                int a = b++;
+                int c = ++b * 2;
+                double d = 1.5;
+                double e = d++ + 1;
+                arr[i++] = 10;
+                arr[i++] = 20;
+                printf("%d %d %d %d %.1f %.1f\n", a, b, c, i, d, e);
+                printf("%d %d %d\n", arr[0], arr[--i], i--);
+                int k = 0;
+                while (k++ < 3) {
+                    printf("k=%d ", k);
+                }
+                arr[2] = 7;
+                arr[2]++;
+                --arr[2];
+                ++arr[2];
+                printf("%d %d %d\n", arr[2], -i-- + 1, arr[i + 2] * 2 - arr[i + 1]);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"5 7 14 2 2.5 2.5\n10 20 1\nk=1 k=2 k=3 8 1 30\n""#));
+    }
+
+    #[test]
+    fn test_multi_dimensional_arrays() {
+        let code = r#"
+            int main() {
+                int grid[3][3];
+                grid[1][2] = 5;
+                grid[2][0] += 4;
+                grid[2][0]++;
+                int m[2][2] = {{1, 2}, {3, 4}};
+                int flat[2][3] = {1, 2, 3, 4};
+                int sum = 0;
+                for (int i = 0; i < 2; i++) {
+                    for (int j = 0; j < 2; j++) {
+                        sum += m[i][j];
+                    }
+                }
+                char names[2][8] = {"ab", "cd"};
+                printf("%d %d %d %d\n", grid[1][2], grid[2][0], grid[0][0], sum);
+                printf("%d %d %d\n", flat[1][0], flat[1][2], flat[0][2]);
+                printf("%s %s %d %d\n", names[0], names[1], sizeof(grid), sizeof(grid[0]));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"5 5 0 10\n4 0 3\nab cd 36 12\n""#));
+        
+        let code = r#"
+            int main() {
+                int grid[3][3];
+                grid[3][0] = 1;
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: array index 3 out of bounds (size 3)"));
+    }
+
+    #[test]
+    fn test_array_index_out_of_bounds() {
+        let code = r#"
+            int main() {
+                int arr[3] = {1, 2, 3};
+                printf("%d\n", arr[3]);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: array index 3 out of bounds (size 3)"));
+        
+        let code = r#"
+            int main() {
+                int arr[3];
+                int i = 0;
+                arr[i - 1] = 1;
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: array index -1 out of bounds (size 3)"));
+        
+        let code = r#"
+            int main() {
+                int arr[3];
+                int *end = &arr[3];
+                int *p = &arr[4];
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: array index 4 out of bounds (size 3)"));
+    }
+
+    #[test]
+    fn test_char_literal_escapes() {
+        let code = r#"
+            int main() {
+                char nl = '\n';
+                char bs = '\\';
+                char q = '\'';
+                char hex = '\x41';
+                char oct = '\102';
+                char c = 'x';
+                c = '\"';
+                printf("%d %d %d %d %d %c%c %c|%c", nl, '\t', '\0', bs, q, hex, oct, c, '\n');
+                switch (nl) {
+                    case '\n': printf("newline\n"); break;
+                    default: printf("other\n");
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"10 9 0 92 39 AB \"|\nnewline\n""#));
+    }
+
+    #[test]
+    fn test_conditional_operator_values() {
+        let code = r#"
+            int main() {
+                int x = 5;
+                char c = x > 0 ? 'p' : 'n';
+                char d = 'a';
+                d = x < 0 ? 'n' : 'z';
+                char *word = x > 3 ? "big" : "small";
+                double half = x > 0 ? x / 2.0 : 0;
+                int sign = x > 0 ? 1 : x < 0 ? -1 : 0;
+                int grade = x > 8 ? 3 : x > 4 ? x > 6 ? 2 : 1 : 0;
+                printf("%c %c %s %.1f %d %d %s\n", c, d, word, half, sign, grade, x % 2 ? "odd" : "even");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"p z big 2.5 1 1 odd\n""#));
+    }
+
+    #[test]
+    fn test_interpreter_reuse() {
+        let mut interpreter = Interpreter::new();
+        let first = r#"
+            #define LIMIT 3
+            int total = 10;
+            int twice(int n) { return n * 2; }
+            int main() {
+                int *p = malloc(sizeof(int));
+                printf("%d\n", twice(total) + LIMIT);
+                return 1;
+            }
+        "#;
+        let result = interpreter.run(first, "", "");
+        assert!(result.contains(r#""output":"23\n""#));
+        assert!(result.contains(r#""leaked_blocks":1"#));
+        
+        // Nothing from the first program carries over to the second
+        let second = r#"
+            int main() {
+                int x;
+                scanf("%d", &x);
+                printf("%d\n", x + LIMIT + total);
+                return 0;
+            }
+        "#;
+        let result = interpreter.run(second, "4", r#"{"lenient": true}"#);
+        assert!(result.contains(r#""output":"4\n""#));
+        assert!(result.contains("'LIMIT' undeclared"));
+        assert!(result.contains("'total' undeclared"));
+        assert!(result.contains(r#""leaked_blocks":0"#));
+        assert!(result.contains(r#""exit_code":0"#));
+        
+        let result = interpreter.run("int main() { return twice(1); }", "", "");
+        assert!(result.contains(r#""success":false"#));
+        
+        let result = interpreter.run(first, "", "{");
+        assert!(result.contains("Invalid run options"));
+    }
+
+    #[test]
+    fn test_sizeof_types_and_variables() {
+        let code = r#"
+            int main() {
+                int arr[10];
+                double d = 1.0;
+                char c = 'a';
+                float f = 2.0;
+                int *p = &arr[0];
+                long l = 3;
+                printf("%d %d %d %d %d %d\n", sizeof(int), sizeof(char), sizeof(float), sizeof(double), sizeof(int *), sizeof(long));
+                printf("%d %d %d %d %d\n", sizeof(d), sizeof(c), sizeof f, sizeof arr, sizeof(p));
+                int n = sizeof(arr) / sizeof(arr[0]);
+                int m = sizeof arr / sizeof arr[0];
+                printf("%d %d %d %d\n", n, m, sizeof(l), sizeof(unsigned int));
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"4 1 4 8 8 8\n8 1 4 40 8\n10 10 8 4\n""#));
+    }
+
+    #[test]
+    fn test_memset_and_memcpy() {
+        let code = r#"
+            int main() {
+                int arr[5] = {1, 2, 3, 4, 5};
+                memset(arr, 0, sizeof(arr));
+                printf("%d %d\n", arr[0], arr[4]);
+                int ones[3];
+                memset(ones, -1, sizeof(ones));
+                printf("%d %d\n", ones[0], ones[2]);
+                int copy[5];
+                int src[5] = {9, 8, 7, 6, 5};
+                memcpy(copy, src, 3 * sizeof(int));
+                printf("%d %d %d %d\n", copy[0], copy[1], copy[2], copy[3]);
+                char buf[8];
+                memset(buf, 'x', 3);
+                buf[3] = '\0';
+                char name[10];
+                memcpy(name, "hello", 6);
+                printf("%s %s\n", buf, name);
+                int *p = malloc(4 * sizeof(int));
+                memset(p, 0, 4 * sizeof(int));
+                memcpy(p, src, 2 * sizeof(int));
+                printf("%d %d %d\n", p[0], p[1], p[2]);
+                int grid[2][2];
+                memset(grid, 0, sizeof(grid));
+                printf("%d\n", grid[1][1]);
+                free(p);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0 0\n-1 -1\n9 8 7 0\nxxx hello\n9 8 0\n0\n""#));
+        
+        let result = compile_and_run_c("int main() { int a[3]; memset(a, 0, 5 * sizeof(int)); return 0; }");
+        assert!(result.contains("memset writes 5 elements into 'a', which holds 3"));
+        
+        let result = compile_and_run_c("int main() { int a[5]; int b[2]; memcpy(a, b, sizeof(a)); return 0; }");
+        assert!(result.contains("reading 5 elements from 'b', which holds 2"));
+    }
+
+    #[test]
+    fn test_seeded_rand() {
+        let code = r#"
+            int main() {
+                int first = rand();
+                int dice = rand() % 6 + 1;
+                printf("%d %d\n", first, dice);
+                srand(42);
+                int a = rand();
+                int b = rand();
+                srand(42);
+                int operand = 3;
+                rand();
+                printf("%d %d %d\n", a, b, rand() == b);
+                double r = rand() / (double)RAND_MAX;
+                printf("%d %d\n", r >= 0 && r <= 1, RAND_MAX);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"16838 5\n19081 17033 1\n1 32767\n""#));
+    }
+
+    #[test]
+    fn test_rand_inside_expressions() {
+        // Each rand() call advances the generator exactly once
+        let code = r#"
+            int roll(int sides) { return rand() % sides + 1; }
+            int main() {
+                int arr[5] = {10, 20, 30, 40, 50};
+                srand(7);
+                int d = abs(rand() % 10 - 20);
+                double s = sqrt(rand() % 100);
+                int pick = arr[rand() % 5];
+                int r = roll(rand() % 3 + 4);
+                int heads = 0;
+                for (int i = 0; i < 10; i++) {
+                    if (rand() % 2 == 0) {
+                        heads++;
+                    }
+                }
+                printf("%d %.3f %d %d %d %d\n", d, s, pick, r, heads, rand() % 6 + 1);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"16 2.449 40 4 4 5\n""#));
+    }
+
+    #[test]
+    fn test_do_while_with_nested_loops() {
+        let code = r#"
+            int main() {
+                int i = 0;
+                do {
+                    int j = 0;
+                    while (j < 2) {
+                        j++;
+                    }
+                    printf("i=%d j=%d naïve while}{ ", i, j);
+                    if (i > 0) { printf("(é) "); }
+                    i++;
+                } while (i < 2);
+                int k = 0;
+                do k++; while (k < 3);
+                int done = 0;
+                while (done < 1) { done++; }
+                printf("\n%d %d\n", k, done);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"i=0 j=2 naïve while}{ i=1 j=2 naïve while}{ (é) \n3 1\n""#));
+    }
+
+    #[test]
+    fn test_identifiers_containing_library_names() {
+        let code = r#"
+            int main() {
+                int information = 1;
+                int outputs = 2;
+                int forward = 3;
+                int iffy = 4;
+                int whilethis = 5;
+                int switchValue = 6;
+                int targets = 7;
+                int format = 8;
+                int scanfCount = 0;
+                int x;
+                scanfCount = scanf("%d", &x);
+                iffy = iffy + 1;
+                forward += 2;
+                printf("%d %d %d %d %d %d %d %d %d %d\n", information, outputs, forward, iffy, whilethis, switchValue, targets, format, scanfCount, x);
+                puts("call printf(x) or gets(s)");
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c_with_input(code, "9");
+        assert!(result.contains(r#""output":"1 2 5 5 5 6 7 8 1 9\ncall printf(x) or gets(s)\n""#));
+    }
+
+    #[test]
+    fn test_string_comparison_compares_addresses() {
+        let code = r#"
+            int main() {
+                char name[10] = "alice";
+                char other[10] = "alice";
+                char *p = "bob";
+                char *q = p;
+                printf("%d %d %d %d\n", name == "alice", name == other, name != other, name == name);
+                printf("%d %d\n", p == q, strcmp(name, other) == 0);
+                if (name == "alice") {
+                    printf("never\n");
+                } else {
+                    printf("use strcmp\n");
+                }
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0 0 1 1\n1 1\nuse strcmp\n""#));
+        assert!(result.contains(r#"Warning: 'name == \"alice\"' compares string addresses, not contents; use strcmp(name, \"alice\") == 0"#));
+        assert!(result.contains("use strcmp(name, other) != 0"));
+        assert!(!result.contains("strcmp(p, q)"));
+        
+        let code = r#"
+            int main() {
+                char a[4] = "ab";
+                char b[4] = "cd";
+                printf("%s\n", a + b);
+                return 0;
+            }
+        "#;
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("C has no string concatenation operator; use strcat"));
+    }
+
+    #[test]
+    fn test_printf_literal_percent() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int p = 50;
+                printf("100%%\n");
+                printf("%d%% done, %%d literal\n", p);
+                printf("%%%d\n", p);
+                printf("rate: 5%");
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"100%\n50% done, %d literal\n%50\nrate: 5%""#));
+    }
+
+    #[test]
+    fn test_getchar_putchar_until_eof() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int c;
+                int n = 0;
+                while ((c = getchar()) != EOF) {
+                    if (c >= 97 && c <= 122) {
+                        putchar(c - 32);
+                    } else {
+                        putchar(c);
+                    }
+                    n++;
+                }
+                putchar('!');
+                putchar('\n');
+                int r = putchar(65);
+                printf(" %d %d\n", n, r);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_input(code, "hi x\n");
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"HI X\n!\nA 5 65\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int a, b;
+                a = b = 3;
+                int x = 0;
+                if ((x = a + b) > 5) {
+                    printf("x=%d\n", x);
+                }
+                printf("%d %d\n", (x += 4) * 2, x);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"x=6\n20 10\n""#));
+    }
+
+    #[test]
+    fn test_stdio_and_stdbool_constants() {
+        let code = r#"
+            #include <stdio.h>
+            #include <stdbool.h>
+            bool is_even(int n) { return n % 2 == 0; }
+            int main() {
+                bool done = false;
+                bool ok = true;
+                int *p = NULL;
+                int n = NULL;
+                int e = EOF;
+                if (p == NULL) {
+                    printf("null\n");
+                }
+                if (!done && ok) {
+                    printf("go\n");
+                }
+                printf("%d %d %d %d\n", ok, n, e, true + true);
+                bool b = 5;
+                b += 1;
+                printf("%d %d %d %d\n", b, is_even(4), is_even(3), sizeof(bool));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"null\ngo\n1 0 -1 2\n1 1 0 1\n""#));
+    }
+
+    #[test]
+    fn test_comparisons_and_not_as_integers() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int done = 0;
+                int a = 7;
+                int flag = !done;
+                int b = (a > 5);
+                int d = !a + 1;
+                int e = (a < 3) + (a > 3) * 10;
+                int f = 1 << 3 > 4;
+                int h = (a >> 1) == 3;
+                int j = !(a == 7);
+                printf("%d %d %d %d %d %d %d\n", flag, b, d, e, f, h, j);
+                double x = 3.5;
+                printf("%d %d\n", x > 2.5, (x == 3.5) + 1);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1 1 1 10 1 1 0\n1 2\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int a = 7;
+                printf("%d %d\n", a + (4 << 2), 16 >> 2 << 1);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"23 8\n""#));
+    }
+
+    #[test]
+    fn test_struct_members() {
+        let code = r#"
+            #include <stdio.h>
+            struct Point {
+                int x;
+                int y;
+            };
+            struct Student {
+                char name[20];
+                int scores[3];
+                double gpa;
+                struct Point home;
+            };
+            struct Point make_point(int x, int y) {
+                struct Point p = {x, y};
+                return p;
+            }
+            int manhattan(struct Point a, struct Point b) {
+                int dx = a.x - b.x;
+                int dy = a.y - b.y;
+                return (dx < 0 ? -dx : dx) + (dy < 0 ? -dy : dy);
+            }
+            int main() {
+                struct Point p;
+                p.x = 3;
+                p.y = 4;
+                struct Point q = {1, 2};
+                struct Point r = {.y = 9};
+                printf("%d %d %d %d %d %d\n", p.x, p.y, q.x, q.y, r.x, r.y);
+                q = p;
+                q.x += 10;
+                q.y++;
+                printf("%d %d %d\n", q.x, q.y, p.x);
+                struct Point pts[3] = {{1, 1}, {2, 4}, {3, 9}};
+                int sum = 0;
+                for (int i = 0; i < 3; i++) {
+                    sum += pts[i].y;
+                }
+                pts[1].x = 20;
+                printf("%d %d\n", sum, pts[1].x);
+                struct Student s = {"Ada", {90, 85, 77}, 3.5, {5, 6}};
+                s.scores[1] = 100;
+                s.gpa = s.gpa + 0.25;
+                s.home.x = 50;
+                printf("%s %d %.2f %d %d\n", s.name, s.scores[1], s.gpa, s.home.x, s.home.y);
+                struct Point m = make_point(7, 8);
+                printf("%d %d %d\n", m.x, m.y, manhattan(m, p));
+                printf("%d %d %d\n", sizeof(struct Point), sizeof(s), sizeof(s.name));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"3 4 1 2 0 9\n13 5 3\n14 20\nAda 100 3.75 50 6\n7 8 8\n8 48 20\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            struct Point { int x, y; } origin = {1, 2};
+            int main() {
+                struct Point p = origin;
+                p.z = 1;
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: 'struct Point' has no member named 'z'"));
+        
+        let code = r#"
+            int main() {
+                struct Shape s;
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: storage size of 's' isn't known"));
+    }
+
+    #[test]
+    fn test_struct_pointer_arrow() {
+        let code = r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            struct Point { int x; int y; };
+            struct Node {
+                int value;
+                struct Node *next;
+            };
+            void move(struct Point *p, int dx, int dy) {
+                p->x += dx;
+                p->y = p->y + dy;
+            }
+            struct Node *push(struct Node *head, int value) {
+                struct Node *node = (struct Node *)malloc(sizeof(struct Node));
+                node->value = value;
+                node->next = head;
+                return node;
+            }
+            int main() {
+                struct Point pt = {1, 2};
+                struct Point *pp = &pt;
+                pp->x = 10;
+                printf("%d %d\n", pt.x, pp->y);
+                move(&pt, 5, 5);
+                pt.y = 100;
+                pp->x++;
+                printf("%d %d\n", pt.x, pp->y);
+                struct Node *list = NULL;
+                for (int i = 1; i <= 4; i++) {
+                    list = push(list, i * i);
+                }
+                struct Node *cur = list;
+                while (cur != NULL) {
+                    printf("%d ", cur->value);
+                    cur = cur->next;
+                }
+                printf("%d\n", list->next->value);
+                while (list != NULL) {
+                    struct Node *next = list->next;
+                    free(list);
+                    list = next;
+                }
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"10 2\n16 100\n16 9 4 1 9\n""#));
+        assert!(result.contains("\"leaked_blocks\":0"));
+        
+        let code = r#"
+            #include <stdio.h>
+            struct Item { char name[8]; int qty; double price; };
+            struct List { struct Item items[3]; int count; };
+            int main() {
+                struct List inv = {{{"pen", 2, 1.5}, {"ink", 1, 4.25}}, 2};
+                struct List *list = &inv;
+                for (int i = 0; i < list->count; i++) {
+                    printf("%s x%d\n", list->items[i].name, list->items[i].qty);
+                }
+                list->items[1].qty = 5;
+                list->count++;
+                list->items[2].price = list->items[0].price * 3;
+                printf("%d %d %.2f\n", inv.items[1].qty, inv.count, list->items[2].price);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"pen x2\nink x1\n5 3 4.50\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            struct Node { int value; struct Node *next; };
+            int main() {
+                struct Node *head = NULL;
+                printf("%d\n", head->value);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Segmentation fault: invalid memory address 0x0"));
+        assert!(result.contains("\"error_category\":\"memory\""));
+    }
+
+    #[test]
+    fn test_typedef_aliases() {
+        let code = r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            typedef unsigned int uint;
+            typedef uint count_t;
+            typedef int *IntPtr, Number;
+            typedef struct { int x, y; } Point;
+            typedef struct Node Node;
+            typedef Node *Link;
+            struct Node { int value; Link next; };
+            Point make(int x, int y) {
+                Point p = {x, y};
+                return p;
+            }
+            count_t twice(count_t n) {
+                return n * 2;
+            }
+            int sum(Link head) {
+                int total = 0;
+                while (head != NULL) {
+                    total += head->value;
+                    head = head->next;
+                }
+                return total;
+            }
+            int main() {
+                uint x = 5;
+                count_t big = 4294967295;
+                big = big + 2;
+                Number n = 7;
+                IntPtr a = &n, b = &x;
+                *a = 9;
+                Point p = make(3, 4);
+                Point *pp = &p;
+                pp->x = 12;
+                printf("%u %u %d %d %d %d %d\n", x, big, n, *b, p.x, p.y, twice(21));
+                Link list = NULL;
+                for (int i = 1; i <= 3; i++) {
+                    Link node = (Link)malloc(sizeof(Node));
+                    node->value = i * 10;
+                    node->next = list;
+                    list = node;
+                }
+                printf("%d %lu %lu %lu %d\n", sum(list), sizeof(Point), sizeof(Link), sizeof(count_t), (Number)2.7);
+                while (list != NULL) {
+                    Link next = list->next;
+                    free(list);
+                    list = next;
+                }
+                typedef char letter;
+                letter c = 65;
+                printf("%c\n", c);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"5 1 9 5 12 4 42\n60 8 8 4 2\nA\n""#));
+        assert!(result.contains("\"leaked_blocks\":0"));
+        
+        let code = r#"
+            #include <stdio.h>
+            typedef int T;
+            typedef double T;
+            int main() {
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("Error: conflicting types for 'T'"));
+    }
+
+    #[test]
+    fn test_semicolon_in_string_of_braceless_do_while() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int i = 0;
+                do printf("%d;", i++); while (i < 3);
+                printf("\n");
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"0;1;2;\n""#));
+    }
+
+    #[test]
+    fn test_if_else_without_braces() {
+        let code = r#"
+            #include <stdio.h>
+            int classify(int n) {
+                if (n < 0) return -1;
+                else if (n == 0) return 0;
+                else return 1;
+            }
+            int main() {
+                int x = 3, y = 0;
+                if (x > 2) y = 1; else y = 2;
+                printf("%d ", y);
+                if (x > 5) y = 10; else if (x > 1) y = 20; else y = 30;
+                printf("%d ", y);
+                if (x > 0) if (x > 10) y = 100; else y = 200;
+                printf("%d ", y);
+                if (x < 0) if (x > 10) y = 1; else y = 2; else y = 3;
+                printf("%d\n", y);
+                if (x == 3) { y = 7; printf("braced "); } else y = 8;
+                printf("%d\n", y);
+                if (x != 3) y = 9; else { y = 11; printf("else braced "); }
+                printf("%d\n", y);
+                printf("%d %d %d\n", classify(-5), classify(0), classify(5));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1 20 200 3\nbraced 7\nelse braced 11\n-1 0 1\n""#));
+    }
+
+    #[test]
+    fn test_loops_without_braces() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int i, j, sum = 0;
+                for (i = 0; i < 3; i++) printf("%d", i);
+                printf("\n");
+                for (i = 0; i < 3; i++)
+                    for (j = 0; j < 3; j++)
+                        if (i == j) sum += 10; else sum++;
+                printf("%d\n", sum);
+                i = 0;
+                while (i < 5) i += 2;
+                printf("%d\n", i);
+                while (i-- > 0);
+                printf("%d\n", i);
+                for (i = 0; i < 10; i++) if (i == 4) break;
+                printf("%d\n", i);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"012\n36\n6\n-1\n4\n""#));
+    }
+
+    #[test]
+    fn test_increments_in_conditions_and_indices() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int arr[5] = {1, 2, 3, 4, 5};
+                int n = 3, i = 0, count = 0;
+                while (n--) count++;
+                printf("%d %d\n", count, n);
+                n = 3;
+                while (--n > 0) count++;
+                printf("%d %d\n", count, n);
+                for (n = 5; n-- > 0;) count++;
+                printf("%d %d\n", count, n);
+                int sum = 0;
+                while (i < 5) sum += arr[i++];
+                printf("%d %d\n", sum, i);
+                int out[5];
+                int k = 0;
+                out[k++] = 7;
+                out[k++] = 8;
+                int x = arr[--k];
+                printf("%d %d %d %d\n", out[0], out[1], x, k);
+                i = 0;
+                while (arr[i++] < 3);
+                printf("%d\n", i);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"3 -1\n5 0\n10 -1\n15 5\n7 8 2 1\n3\n""#));
+    }
+
+    #[test]
+    fn test_hex_binary_octal_and_suffixed_literals() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int m = 0xFF;
+                printf("%d %d %d %d %d\n", m, m & 0x0F, 0b1010, 010, 0X1f + 1);
+                long big = 100000L;
+                unsigned u = 7U;
+                unsigned long ul = 42UL;
+                printf("%ld %u %lu\n", big, u, ul);
+                double d = 0x10 + 0.5;
+                int e = 0XE+1;
+                int mask = ~0x0F & 0xFF;
+                long shifted = 1UL << 40;
+                int wrapped = 0xFFFFFFFF;
+                printf("%.1f %d %d %ld %d\n", d, e, mask, shifted, wrapped);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"255 15 10 8 32\n100000 7 42\n16.5 15 240 1099511627776 -1\n""#));
+    }
+
+    #[test]
+    fn test_char_literals_in_integer_arithmetic() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int code = 'A' + 1;
+                int diff = 'Z' - 'A';
+                char c = '7';
+                int digit = c - '0';
+                printf("%d %d %d\n", code, diff, digit);
+                char s[] = "4821";
+                int n = 0;
+                for (int i = 0; s[i] != '\0'; i++) {
+                    n = n * 10 + (s[i] - '0');
+                }
+                printf("%d %d %d %d\n", n, '\n', '\\' + 0, '\x41' * 2);
+                int dot = '.' + 1;
+                int e = 'e' - 1;
+                printf("%d %d %d\n", dot, e, ';');
+                for (char letter = 'a'; letter <= 'e'; letter++) putchar(letter - 'a' + 'A');
+                putchar('\n');
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"66 25 7\n4821 10 92 130\n47 100 59\nABCDE\n""#));
+    }
+
+    #[test]
+    fn test_output_limit_keeps_truncated_output() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                for (int i = 0; i < 100; i++) {
+                    printf("%d,", i);
+                }
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_options(code, "", r#"{"max_output_bytes": 12}"#);
+        assert!(result.contains("\"success\":false"));
+        assert!(result.contains(r#""output":"0,1,2,3,4,5,""#));
+        assert!(result.contains("Error: output limit of 12 bytes exceeded"));
+        
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains("98,99,"));
+        
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                puts("abc");
+                putchar('d');
+                putchar('e');
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_options(code, "", r#"{"max_output_bytes": 5}"#);
+        assert!(result.contains(r#""output":"abc\nd""#));
+        assert!(result.contains("Error: output limit of 5 bytes exceeded"));
+    }
+
+    #[test]
+    fn test_run_with_limits() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                long total = 0;
+                for (long i = 0; i < 2000; i++) {
+                    total += i;
+                }
+                printf("%ld\n", total);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_limits(code, 1000, 1024);
+        assert!(result.contains("Loop exceeded maximum iterations"));
+        
+        let result = compile_and_run_c_with_limits(code, 3000, 1024);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1999000\n""#));
+        
+        let result = compile_and_run_c_with_limits(code, 3000, 4);
+        assert!(result.contains(r#""output":"1999""#));
+        assert!(result.contains("Error: output limit of 4 bytes exceeded"));
+    }
+
+    #[test]
+    fn test_fmod_remainders() {
+        let code = r#"
+            #include <stdio.h>
+            #include <math.h>
+            int main() {
+                double a = fmod(7.5, 2);
+                double b = fmod(-7.5, 2.0);
+                float angle = 370.25;
+                angle = fmod(angle, 360.0);
+                printf("%.2f %.2f %.2f %.2f\n", a, b, angle, fmod(10, 3) + 0.5);
+                int zero = 0;
+                printf("%f\n", fmod(1.0, zero));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"1.50 -1.50 10.25 1.50\n""#));
+        assert!(result.contains(r#""error":"line 11: Error: fmod by zero""#));
+    }
+
+    #[test]
+    fn test_inverse_trig_and_log2() {
+        let code = r#"
+            #include <stdio.h>
+            #include <math.h>
+            int main() {
+                double pi = 4 * atan(1);
+                printf("%.4f %.4f %.4f\n", pi, asin(1) * 2, acos(-1));
+                printf("%.4f %.4f\n", atan2(1, 1), atan2(-1.0, -1.0));
+                printf("%.1f %.1f %.1f\n", log2(1024), log10(1000), log2(8) + 1);
+                printf("%f\n", atan2(1));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#""output":"3.1416 3.1416 3.1416\n0.7854 -2.3562\n10.0 3.0 4.0\n""#));
+        assert!(result.contains("Error: atan2 expects 2 argument(s) but 1 were given"));
+    }
+
+    #[test]
+    fn test_printf_arguments_from_elements_and_ternaries() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                char *names[] = {"ann", "bob", "cy"};
+                char grid[2][4] = {"ab", "cd"};
+                int a = 3, b = 7, arr[3] = {4, 5, 6};
+                double xs[2] = {1.5, 2.5};
+                for (int i = 0; i < 3; i++) {
+                    printf("%s ", names[i]);
+                }
+                printf("\n%d %d %d\n", a > b ? a : b, arr[1] + arr[2], a * (b - arr[0]));
+                printf("%s %s\n", a < b ? "less" : "more", b > 5 ? names[1] : names[2]);
+                printf("%s %c %.1f %.1f\n", grid[1], grid[0][1], xs[1] * 2, a > 1 ? xs[0] : 0.0);
+                printf("%c %d %s\n", names[2][1], names[0][0], names[a - 2]);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"ann bob cy \n7 11 9\nless bob\ncd b 5.0 1.5\ny 97 bob\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            #include <string.h>
+            void show(char *list[], int n) {
+                for (int i = 0; i < n; i++) printf("%s.", list[i]);
+                printf("\n");
+            }
+            int main() {
+                char *names[] = {"ann", "bob", "cy"};
+                names[1] = "zed";
+                show(names, 3);
+                printf("%lu %lu %d\n", sizeof(names), strlen(names[0]), strcmp(names[0], names[1]) < 0);
+                char *p = names[2];
+                printf("%s %c\n", p, *names[1]);
+                int a = 1, b = 2;
+                int *ptrs[2] = {&a, &b};
+                printf("%d %d\n", *ptrs[0], *ptrs[1] + 10);
+                char *empty[2];
+                printf("%d\n", empty[0] == NULL);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"ann.zed.cy.\n24 3 1\ncy z\n1 12\n1\n""#));
+    }
+
+    #[test]
+    fn test_array_names_decay_to_pointers() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int arr[3] = {10, 20, 30};
+                int *p = arr;
+                printf("%d %d %d\n", p == &arr[0], arr + 1 == &arr[1], &arr[2] - p);
+                printf("%d %d %d\n", *p, p[1], *(p + 2));
+                p[1] = 25;
+                *p = 11;
+                *(p + 2) = 33;
+                printf("%d %d %d\n", arr[0], arr[1], arr[2]);
+                p = arr + 1;
+                printf("%d\n", *p + 1);
+                char name[] = "hey";
+                char *s = name;
+                printf("%s %c\n", s, *(s + 1));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1 1 2\n10 20 30\n11 25 33\n26\nhey e\n""#));
+        
+        // %p prints the same base address for an array and its first element
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int arr[2] = {1, 2};
+                int *p = arr;
+                printf("%p %p %p", arr, &arr[0], p);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        let output = result.split("\"output\":\"").nth(1).unwrap().split('"').next().unwrap();
+        let addresses: Vec<&str> = output.split(' ').collect();
+        assert_eq!(addresses.len(), 3);
+        assert!(addresses[0].starts_with("0x"));
+        assert!(addresses.iter().all(|addr| *addr == addresses[0]));
+    }
+
+    #[test]
+    fn test_printf_length_modifiers() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                long long big = 9000000000;
+                int neg = -1;
+                unsigned long n = 5;
+                printf("%lld %d %ld|", big, 7, big);
+                printf("%lu %llu %zu|", n, big, sizeof(int));
+                printf("%hd %hu %hhd %hhu|", 70000, neg, 200, neg);
+                printf("%u %lu %x %X %lx %05X|", neg, neg, 255, 255, neg, 171);
+                printf("%lf %d %Lf\n", 1.5, 3, 2.25);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains("9000000000 7 9000000000|5 9000000000 4|4464 65535 -56 255|"));
+        assert!(result.contains(r#"|4294967295 18446744073709551615 ff FF ffffffffffffffff 000AB|1.500000 3 2.250000\n""#));
+    }
+
+    #[test]
+    fn test_printf_specifiers_bind_in_order() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                double d = 2.5;
+                int i = 7;
+                long l = 40;
+                printf("%lf %d|%d %lf|%f %ld %d|%s %c %lf\n", d, i, i, d, d, l, i, "s", 'c', d);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"2.500000 7|7 2.500000|2.500000 40 7|s c 2.500000\n""#));
+    }
+
+    #[test]
+    fn test_printf_argument_count_mismatch() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int x = 4;
+                printf("%d %d|", x);
+                printf("x\n", x, 5);
+                printf("%2$d %1$d|", 1, 2);
+                printf("%*d\n", 3, x);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"4 (missing)|x\n2 1|  4\n""#));
+        assert!(result.contains(r#""warnings":["Warning: printf format \"%d %d|\" has more conversions than arguments (1 given)","Warning: printf format \"x\\n\" uses 0 of the 2 arguments given"]"#));
+    }
+
+    #[test]
+    fn test_inner_break_keeps_outer_loop_running() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int total = 0;
+                for (int i = 0; i < 3; i++) {
+                    for (int j = 0; j < 5; j++) {
+                        if (j == 2) { if (1) { break; } }
+                        total += 10;
+                    }
+                    printf("after inner %d\n", i);
+                    int k = 0;
+                    while (1) { if (k == 1) break; k++; }
+                    switch (i) { case 1: total += 1000; break; default: break; }
+                    total += 100;
+                }
+                int m = 0;
+                do { for (int q = 0; q < 9; q++) break; m++; } while (m < 3);
+                printf("%d %d\n", total, m);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"after inner 0\nafter inner 1\nafter inner 2\n1360 3\n""#));
+    }
+
+    #[test]
+    fn test_dump_final_variable_state() {
+        let code = r#"
+            #include <stdio.h>
+            struct Point { int x; int y; };
+            int calls = 0;
+            int main() {
+                int n = 3;
+                double ratio = 1.5;
+                char grade = 'B';
+                int arr[2] = {4, 5};
+                struct Point p = {1, 2};
+                int *ptr = NULL;
+                calls++;
+                printf("done\n");
+                return 0;
+            }
+        "#;
+        let result = compile_and_dump_state(code);
+        assert!(result.contains(r#""success":true,"output":"done\n""#));
+        assert!(result.contains(r#""n":{"type":"Int","value":3}"#));
+        assert!(result.contains(r#""ratio":{"type":"Float","value":1.5}"#));
+        assert!(result.contains(r#""grade":{"type":"Char","value":"B"}"#));
+        assert!(result.contains(r#""arr":{"type":"Array","value":[{"type":"Int","value":4},{"type":"Int","value":5}]}"#));
+        assert!(result.contains(r#""x":{"type":"Int","value":1}"#));
+        assert!(result.contains(r#""ptr":{"type":"Pointer","value":0}"#));
+        assert!(result.contains(r#""calls":{"type":"Int","value":1}"#));
+        
+        // The state where a failing program stopped is still reported
+        let result = compile_and_dump_state("int main() { int i = 7; int z = 0; return i / z; }");
+        assert!(result.contains("\"success\":false"));
+        assert!(result.contains(r#""i":{"type":"Int","value":7}"#));
+    }
+
+    #[test]
+    fn test_trace_records_each_executed_statement() {
+        let code = r#"
+            #include <stdio.h>
+            int square(int v) {
+                return v * v;
+            }
+            int main() {
+                int sum = 0;
+                for (int i = 0; i < 2; i++) {
+                    sum += square(i + 1);
+                }
+                printf("%d\n", sum);
+                return 0;
+            }
+        "#;
+        let result = compile_and_trace(code);
+        assert!(result.contains(r#""success":true,"output":"5\n""#));
+        assert!(result.contains(concat!(
+            r#""trace":[{"statement":"int sum = 0;","line":7,"changed":{"sum":{"type":"Int","value":0}}},"#,
+            r#"{"statement":"return v * v;","line":4,"changed":{}},"#,
+            r#"{"statement":"sum += square(i + 1);","line":9,"changed":{"sum":{"type":"Int","value":1}}},"#,
+            r#"{"statement":"return v * v;","line":4,"changed":{}},"#,
+            r#"{"statement":"sum += square(i + 1);","line":9,"changed":{"sum":{"type":"Int","value":5}}},"#,
+            r#"{"statement":"printf(\"%d\\n\", sum);","line":11,"changed":{}},"#,
+            r#"{"statement":"return 0;","line":12,"changed":{}}]"#,
+        )));
+    }
+
+    #[test]
+    fn test_continue_in_nested_blocks_runs_the_increment() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                for (int i = 0; i < 6; i++) {
+                    if (i % 2 == 0) {
+                        if (i == 4) { continue; }
+                        printf("e%d ", i);
+                        continue;
+                    }
+                    printf("o%d ", i);
+                }
+                int n = 0;
+                for (int i = 0; i < 4; i++) {
+                    switch (i) { case 1: continue; default: break; }
+                    for (int j = 0; j < 3; j++) { if (j == 1) continue; n += 10; }
+                    n++;
+                }
+                int k = 0, hits = 0;
+                do { k++; if (k < 3) continue; hits++; } while (k < 5);
+                printf("| %d %d %d\n", n, k, hits);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"e0 o1 e2 o3 o5 | 63 5 3\n""#));
+    }
+
+    #[test]
+    fn test_length_bounded_string_functions() {
+        let code = r#"
+            #include <stdio.h>
+            #include <string.h>
+            int main() {
+                char a[8] = "xxxxxxx";
+                strncpy(a, "hi", 5);
+                printf("%s %d %c\n", a, a[4] == '\0', a[6]);
+                char b[6];
+                strncpy(b, "hello world", 5);
+                b[5] = '\0';
+                printf("%s\n", b);
+                char c[16] = "ab";
+                strncat(c, "cdefg", 3);
+                strncat(c, "Z", 10);
+                printf("%s %lu\n", c, strlen(c));
+                printf("%d %d %d\n", strncmp("apple", "apricot", 2), strncmp("apple", "apricot", 3) < 0, strncmp("ab", "abc", 2));
+                if (strncmp(c, "abc", 3) == 0) printf("prefix\n");
+                int n = strncmp(b, "help", 4);
+                printf("%d\n", n);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"hi 1 x\nhello\nabcdeZ 6\n0 1 0\nprefix\n-1\n""#));
+        
+        let code = r#"
+            #include <string.h>
+            int main() {
+                char small[4];
+                strncpy(small, "abc", 8);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("strncpy: 'small' holds 4 chars, too small to write 8"));
+    }
+
+    #[test]
+    fn test_sprintf_formats_into_buffers() {
+        let code = r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            int main() {
+                char buf[32];
+                int x = 42;
+                sprintf(buf, "x=%d %s", x, "ok");
+                printf("[%s]\n", buf);
+                int n = sprintf(buf, "%5.2f|%c", 3.14159, 'z');
+                printf("[%s] %d\n", buf, n);
+                char *heap = malloc(16);
+                sprintf(heap, "%03d", 7);
+                char *p = buf;
+                sprintf(p + 1, "%s!", heap);
+                printf("%s %s\n", heap, buf);
+                free(heap);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"[x=42 ok]\n[ 3.14|z] 7\n007  007!\n""#));
+        
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                char small[4];
+                sprintf(small, "%d", 12345);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains(r#"sprintf: 'small' holds 4 chars, too small for \"12345\""#));
+    }
+
+    #[test]
+    fn test_render_format_in_isolation() {
+        let mut interpreter = CInterpreter::new();
+        interpreter.variables.insert("x".to_string(), Value::Int(7));
+        interpreter.variables.insert("ratio".to_string(), Value::Float(0.5));
+        let args = ["x".to_string(), "ratio".to_string(), "\"hi\"".to_string()];
+        let text = interpreter.render_format("%-3d|%.1f|%5s|%%\\n", &args, "printf").unwrap();
+        assert_eq!(text, "7  |0.5|   hi|%\n");
+        assert_eq!(interpreter.render_format("%3$s %1$d", &args, "printf").unwrap(), "hi 7");
+        assert!(interpreter.warnings.is_empty());
+        
+        assert_eq!(interpreter.render_format("%d %d", &args[..1], "sprintf").unwrap(), "7 (missing)");
+        assert_eq!(interpreter.warnings, ["Warning: sprintf format \"%d %d\" has more conversions than arguments (1 given)"]);
+        assert!(interpreter.output.is_empty());
+    }
+
+    #[test]
+    fn test_bitwise_compound_assignment() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int f = 6, g = 5, h = 12, s = 1, r = 256;
+                f &= 3;
+                g |= 8;
+                h ^= 5;
+                s <<= 4;
+                r >>= 2;
+                int arr[2] = {1, 1};
+                arr[1] <<= 3;
+                int *p = &arr[0];
+                *p |= 6;
+                printf("%d %d %d %d %d %d %d\n", f, g, h, s, r, arr[1], arr[0]);
+                int x = 1, n = 0;
+                while ((x <<= 1) < 100) n++;
+                for (int bit = 1; bit < 16; bit <<= 1) n += 10;
+                printf("%d %d\n", x, n);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"2 13 9 16 64 8 7\n128 46\n""#));
+    }
 
-        // Handle array element access
-        if expr.contains('[') {
-            let bracket_pos = expr.find('[').unwrap();
-            let var_name = expr[..bracket_pos].trim();
-            let bracket_end = expr.find(']').ok_or("Invalid array syntax")?;
-            let index_expr = &expr[bracket_pos + 1..bracket_end];
-            let index = self.evaluate_numeric_expression(index_expr)? as usize;
-            
-            if let Some(Value::Array(arr)) = self.variables.get(var_name) {
-                if index < arr.len() {
-                    return match &arr[index] {
-                        Value::Int(i) => Ok(*i),
-                        Value::Float(f) => Ok(*f as i64),
-                        Value::Char(c) => Ok(*c as i64),
-                        Value::Bool(b) => Ok(*b as i64),
-                        _ => Err("Invalid array element type".to_string()),
-                    };
+    #[test]
+    fn test_float_compound_assignment() {
+        let code = r#"
+            #include <stdio.h>
+            struct Acc { double sum; int n; };
+            int main() {
+                float total = 0;
+                for (int k = 0; k < 3; k++) {
+                    total += 1.5;
                 }
+                double avg = 10;
+                avg /= 4;
+                avg *= 3;
+                avg -= 0.25;
+                int i = 1;
+                i += 1.7;
+                double vals[3] = {0.5, 1.0, 1.5};
+                vals[1] += 0.25;
+                double *p = &vals[2];
+                *p *= 2;
+                struct Acc a = {0, 0};
+                a.sum += 0.5;
+                int *q = &i;
+                i += 2;
+                printf("%.2f %.2f %d %.2f %.2f %.2f %d\n", total, avg, i, vals[1], vals[2], a.sum, *q);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"4.50 7.25 4 1.25 3.00 0.50 4\n""#));
+    }
 
-        // Handle pointer dereference: *ptr
-        if expr.starts_with('*') {
-            let ptr_expr = expr[1..].trim();
-            if let Some(Value::Pointer(addr)) = self.variables.get(ptr_expr) {
-                let value = self.memory.read(*addr)?;
-                return match value {
-                    Value::Int(i) => Ok(i),
-                    Value::Float(f) => Ok(f as i64),
-                    Value::Char(c) => Ok(c as i64),
-                    Value::Bool(b) => Ok(b as i64),
-                    _ => Err("Cannot dereference to numeric value".to_string()),
-                };
-            } else {
-                return Err(format!("'{}' is not a valid pointer", ptr_expr));
+    #[test]
+    fn test_char_literals_do_not_split_statements() {
+        let code = r#"
+            #include <stdio.h>
+            int is_closer(char c) { return c == '}' || c == ')'; }
+            int main() {
+                char s[] = "a;b{c}(d);'";
+                int count = 0;
+                int other = 0;
+                for (int i = 0; s[i] != '\0'; i++) {
+                    char c = s[i];
+                    if (c == ';') count++;
+                    if (c == '{') { other += 1; }
+                    if (is_closer(c)) other += 10;
+                    if (c == '\'') other += 100;
+                }
+                printf("%d %d\n", count, other);
+                printf("%c%c\n", ',', '"');
+                char buf[8];
+                sprintf(buf, "%c%d", ',', 5);
+                printf("%s\n", buf);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"2 121\n,\"\n,5\n""#));
+    }
 
-        // Handle address-of operator: &variable (returns address as number)
-        if expr.starts_with('&') {
-            let var_name = expr[1..].trim();
-            if let Some(value) = self.variables.get(var_name) {
-                let addr = self.memory.get_address_of(var_name, value);
-                return Ok(addr);
-            } else {
-                return Err(format!("Variable '{}' not found", var_name));
+    #[test]
+    fn test_memset_and_memcpy_through_pointers_into_arrays() {
+        let code = r#"
+            int main() {
+                int arr[4] = {1, 2, 3, 4};
+                int *p = arr;
+                memset(p, 0, 2 * sizeof(int));
+                printf("%d %d %d %d\n", arr[0], arr[1], arr[2], arr[3]);
+                int src[4] = {5, 6, 7, 8};
+                int *from = src + 1;
+                memcpy(&arr[1], from, 3 * sizeof(int));
+                printf("%d %d %d %d\n", arr[0], arr[1], arr[2], arr[3]);
+                char buf[6] = "abcde";
+                char *q = buf + 2;
+                memset(q, '-', 2);
+                printf("%s\n", buf);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"0 0 3 4\n0 6 7 8\nab--e\n""#));
+    }
 
-        // Handle ternary operator
-        if expr.contains('?') && expr.contains(':') {
-            let q_pos = expr.find('?').unwrap();
-            let c_pos = expr.rfind(':').unwrap();
-            
-            let condition = &expr[..q_pos].trim();
-            let true_expr = &expr[q_pos + 1..c_pos].trim();
-            let false_expr = &expr[c_pos + 1..].trim();
-            
-            if self.evaluate_condition(condition)? {
-                return self.evaluate_numeric_expression(true_expr);
-            } else {
-                return self.evaluate_numeric_expression(false_expr);
+    #[test]
+    fn test_increment_target_side_effects_run_once() {
+        let code = r#"
+            #include <stdio.h>
+            struct P { int x; int v[2]; };
+            int main() {
+                int b[4] = {0};
+                int i = 0;
+                b[i++]++;
+                printf("%d %d %d\n", i, b[0], b[1]);
+                int r = ++b[i++];
+                printf("%d %d %d\n", i, b[1], r);
+                struct P s = {1, {5, 6}};
+                struct P *ps = &s;
+                int k = 0;
+                ps->v[k++]--;
+                unsigned char u = 255;
+                u++;
+                printf("%d %d %d %d\n", k, s.v[0], s.v[1], u);
+                int *p = b;
+                p++;
+                int x = *p++;
+                ++p;
+                printf("%d %d\n", x, *p);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1 1 0\n2 1 1\n1 4 6 0\n1 0\n""#));
+    }
 
-        // Handle parentheses
-        if expr.starts_with('(') && expr.ends_with(')') {
-            return self.evaluate_numeric_expression(&expr[1..expr.len() - 1]);
-        }
-        
-        // Handle bitwise operators
-        let chars: Vec<char> = expr.chars().collect();
-        let mut depth = 0;
-        
-        // Handle bitwise OR
-        for i in (0..chars.len()).rev() {
-            match chars[i] {
-                ')' => depth += 1,
-                '(' => depth -= 1,
-                '|' if depth == 0 && (i == 0 || chars[i-1] != '|') && (i == chars.len()-1 || chars[i+1] != '|') => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left | right);
-                }
-                _ => {}
+    #[test]
+    fn test_string_functions_write_through_pointers() {
+        let code = r#"
+            #include <stdio.h>
+            #include <string.h>
+            int main() {
+                char b3[8];
+                char *r = b3;
+                strncpy(r, "hey", 4);
+                printf("%s %s\n", b3, r);
+                char b4[12];
+                char *q = b4;
+                strcpy(q, "ab");
+                strcat(q, "cd");
+                strncat(q, "efgh", 2);
+                printf("%s\n", b4);
+                sprintf(q, "%d!", 42);
+                strcpy(q + 2, "?");
+                printf("%s\n", b4);
+                return 0;
             }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"hey hey\nabcdef\n42?\n""#));
+    }
+
+    #[test]
+    fn test_out_of_range_shift_is_an_error() {
+        for code in [
+            "int main() { int s = 1; s <<= 70; return 0; }",
+            "int main() { int s = 1; s >>= -1; return 0; }",
+            "int main() { int n = 64; int s = 1 << n; return 0; }",
+        ] {
+            let result = compile_and_run_c(code);
+            assert!(result.contains("\"success\":false"));
+            assert!(result.contains("is negative or too large"));
         }
+    }
 
-        // Handle bitwise AND
-        depth = 0;
-        for i in (0..chars.len()).rev() {
-            match chars[i] {
-                ')' => depth += 1,
-                '(' => depth -= 1,
-                '&' if depth == 0 && (i == 0 || chars[i-1] != '&') && (i == chars.len()-1 || chars[i+1] != '&') => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left & right);
-                }
-                _ => {}
+    #[test]
+    fn test_compound_assignment_targets() {
+        let code = r#"
+            #include <stdio.h>
+            struct S { double w; int n; };
+            int main() {
+                int a[4] = {1, 2, 3, 4};
+                int *p = a;
+                p += 2;
+                printf("%d ", *p);
+                p -= 1;
+                printf("%d\n", *p);
+                int b[3] = {1, 1, 1};
+                int i = 0;
+                b[i++] += 5;
+                b[i++] <<= 1;
+                printf("%d %d %d %d\n", i, b[0], b[1], b[2]);
+                struct S s = {1.0, 3};
+                struct S *ps = &s;
+                ps->w *= 2.5;
+                ps->n %= 2;
+                char c = 'a';
+                c += 2;
+                printf("%.1f %d %c\n", s.w, s.n, c);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"3 2\n2 6 2 1\n2.5 1 c\n""#));
+    }
 
-        // Handle bitwise XOR
-        depth = 0;
-        for i in (0..chars.len()).rev() {
-            match chars[i] {
-                ')' => depth += 1,
-                '(' => depth -= 1,
-                '^' if depth == 0 => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left ^ right);
+    #[test]
+    fn test_recursive_function_body_is_parsed_once() {
+        let code = r#"
+            #include <stdio.h>
+            int fib(int n) {
+                if (n < 2) {
+                    return n;
                 }
-                _ => {}
+                return fib(n - 1) + fib(n - 2);
             }
-        }
+            int main() {
+                printf("%d\n", fib(15));
+                return 0;
+            }
+        "#;
+        
+        let mut interpreter = CInterpreter::new();
+        let output = interpreter.execute(code).unwrap();
+        assert_eq!(output, "610\n");
+        // main's body, fib's body and its if body, across 1973 calls
+        assert_eq!(interpreter.statement_cache.len(), 3);
+    }
 
-        // Handle bit shifts
-        if expr.contains("<<") {
-            let parts: Vec<&str> = expr.splitn(2, "<<").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left << right);
-        }
+    #[test]
+    fn test_getchar_with_spaced_parentheses() {
+        let code = r#"
+            #include <stdio.h>
+            int main() {
+                int a = getchar ();
+                getchar( );
+                char c = getchar( );
+                printf("%c%c\n", a, c);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_input(code, "xyz");
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"xz\n""#));
+    }
 
-        if expr.contains(">>") {
-            let parts: Vec<&str> = expr.splitn(2, ">>").collect();
-            let left = self.evaluate_numeric_expression(parts[0].trim())?;
-            let right = self.evaluate_numeric_expression(parts[1].trim())?;
-            return Ok(left >> right);
-        }
-        
-        // Handle simple arithmetic expressions with operator precedence
-        // First handle + and -
-        depth = 0;
-        for i in (0..chars.len()).rev() {
-            let ch = chars[i];
-            match ch {
-                ')' => depth += 1,
-                '(' => depth -= 1,
-                '+' if depth == 0 && i > 0 => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left + right);
-                }
-                '-' if depth == 0 && i > 0 && chars[i-1] != 'e' && chars[i-1] != 'E' => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left - right);
-                }
-                _ => {}
+    #[test]
+    fn test_rand_with_spaced_parentheses() {
+        let code = r#"
+            int main() {
+                srand(42);
+                int a = rand ();
+                int b = rand( );
+                srand(42);
+                rand ( );
+                printf("%d %d %d\n", a, b, rand( ) == b);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"19081 17033 1\n""#));
+    }
 
-        // Then handle * and / and %
-        depth = 0;
-        for i in (0..chars.len()).rev() {
-            let ch = chars[i];
-            match ch {
-                ')' => depth += 1,
-                '(' => depth -= 1,
-                '*' if depth == 0 => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left * right);
-                }
-                '/' if depth == 0 => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    if right == 0 {
-                        return Err("Error: Division by zero".to_string());
-                    }
-                    return Ok(left / right);
-                }
-                '%' if depth == 0 => {
-                    let left = self.evaluate_numeric_expression(&expr[..i])?;
-                    let right = self.evaluate_numeric_expression(&expr[i + 1..])?;
-                    return Ok(left % right);
-                }
-                _ => {}
+    #[test]
+    fn test_memset_over_empty_struct_elements_is_an_error() {
+        let code = r#"
+            struct E {};
+            int main() {
+                struct E e[2];
+                memset(e, 0, 0);
+                return 0;
             }
-        }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":false"));
+        assert!(result.contains("0-byte struct E elements"));
+    }
 
-        // Handle unary minus
-        if expr.starts_with('-') {
-            let val = self.evaluate_numeric_expression(&expr[1..])?;
-            return Ok(-val);
-        }
+    #[test]
+    fn test_abs_of_most_negative_value_wraps() {
+        let code = r#"
+            int main() {
+                long n = -9223372036854775807 - 1;
+                printf("%ld %d\n", labs(n), abs(-5));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"-9223372036854775808 5\n""#));
+    }
 
-        // Handle bitwise NOT
-        if expr.starts_with('~') {
-            let val = self.evaluate_numeric_expression(&expr[1..])?;
-            return Ok(!val);
-        }
-        
-        Err(format!("Error: Cannot evaluate expression: {}", expr))
+    #[test]
+    fn test_integer_overflow_wraps_and_overflowing_division_is_an_error() {
+        let code = r#"
+            int main() {
+                long b = 9223372036854775807;
+                b = b + 1;
+                long c = 9223372036854775807;
+                c += 1;
+                c -= 1;
+                c *= 3;
+                printf("%ld %ld %ld\n", b, c, -b);
+                long m = -9223372036854775807 - 1;
+                long q = m / -1;
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":false"));
+        assert!(result.contains(r#""output":"-9223372036854775808 9223372036854775805 -9223372036854775808\n""#));
+        assert!(result.contains("Error: -9223372036854775808 / -1 overflows"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_char_is_signed_and_unsigned_char_is_not() {
+        let code = r#"
+            int main() {
+                char c = 200;
+                signed char s = 255;
+                unsigned char u = 200;
+                int i = c;
+                printf("%d %d %d %d %x\n", c, s, u, i, u);
+                c++;
+                u += 100;
+                unsigned char bytes[2] = {250, 'a'};
+                printf("%d %d %d %c\n", c, u, bytes[0], bytes[1]);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c(code);
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"-56 -1 200 -56 c8\n-55 44 250 a\n""#));
+    }
 
     #[test]
-    fn test_simple_hello_world() {
+    fn test_assignment_through_dereferenced_array_name() {
         let code = r#"
-            #include <stdio.h>
             int main() {
-                printf("Hello, World!\n");
+                int arr[3] = {1, 2, 3};
+                *arr = 9;
+                *arr += 1;
+                double d[2];
+                *d = 2.5;
+                char s[4] = "abc";
+                *s = 'X';
+                printf("%d %d %.1f %s %c\n", *arr, arr[1], *d, s, *s);
                 return 0;
             }
         "#;
-        
         let result = compile_and_run_c(code);
-        assert!(result.contains("Hello, World!"));
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"10 2 2.5 Xbc X\n""#));
     }
 
     #[test]
-    fn test_for_loop_with_break() {
+    fn test_printf_exponent_conversions() {
         let code = r#"
             int main() {
-                for(int i = 0; i < 10; i++) {
-                    if(i == 5) {
-                        break;
-                    }
-                    printf("%d ", i);
-                }
+                printf("%e %d\n", 1.5, 7);
+                printf("%E|%.2e|%e\n", 12345.678, 0.000123, 0.0);
+                printf("%g %g %g %g %G\n", 1.5, 100000.0, 1000000.0, 0.00001234, 1e20);
+                printf("%.3g|%10.2e|%-8g|\n", 3.14159, 3.0, 2.5);
+                printf("%a\n", 1.0);
                 return 0;
             }
         "#;
-        
         let result = compile_and_run_c(code);
-        assert!(result.contains("0 1 2 3 4"));
+        assert!(result.contains("\"success\":false"));
+        assert!(result.contains(r#""output":"1.500000e+00 7\n1.234568E+04|1.23e-04|0.000000e+00\n1.5 100000 1e+06 1.234e-05 1E+20\n3.14|  3.00e+00|2.5     |\n""#));
+        assert!(result.contains("Error: printf: unsupported conversion %a"));
     }
 
     #[test]
-    fn test_else_statement() {
+    fn test_strpbrk_on_a_literal_reuses_its_storage() {
         let code = r#"
             int main() {
-                int x = 5;
-                if(x > 10) {
-                    printf("Greater\n");
-                } else {
-                    printf("Smaller\n");
+                int same = 0;
+                char *first = 0;
+                for (int i = 0; i < 3; i++) {
+                    char *p = strpbrk("hello, world", " ,");
+                    if (first == 0) first = p;
+                    if (p == first) same++;
                 }
+                printf("%d %s %d %d\n", same, first, strspn ( "aab", "a" ), strcspn("xyz", "z"));
                 return 0;
             }
         "#;
-        
         let result = compile_and_run_c(code);
-        assert!(result.contains("Smaller"));
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"3 , world 2 2\n""#));
+    }
+
+    #[test]
+    fn test_scanf_into_elements_members_and_pointers() {
+        let code = r#"
+            struct P { int x; double y; char name[8]; };
+            int main() {
+                int arr[3] = {0};
+                struct P s;
+                struct P *p = &s;
+                char buf[6];
+                double d;
+                int n;
+                int *q = &n;
+                for (int i = 0; i < 3; i++) {
+                    scanf("%d", &arr[i]);
+                }
+                scanf("%d %lf %s", &p->x, &s.y, buf);
+                scanf("%d %d %s", &d, q, s.name);
+                printf("%d %d %d %d %.1f %s %.1f %d %s %d\n", arr[0], arr[1], arr[2], s.x, s.y, buf, d, n, s.name, sizeof(buf));
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_input(code, "1 2 3\n4 5.5 hello\n7 8 bob\n");
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"1 2 3 4 5.5 hello 7.0 8 bob 6\n""#));
+    }
+
+    #[test]
+    fn test_scanf_count_used_in_expressions() {
+        let code = r#"
+            int main() {
+                int x;
+                int sum = 0;
+                while (scanf("%d", &x) == 1) {
+                    sum += x;
+                }
+                int got = scanf("%d", &x);
+                int counts[2];
+                char word[4];
+                counts[1] = scanf("%s", word) + 10;
+                printf("%d %d %d %s\n", sum, got, counts[1], word);
+                return 0;
+            }
+        "#;
+        let result = compile_and_run_c_with_input(code, "1 2 3 end");
+        assert!(result.contains("\"success\":true"));
+        assert!(result.contains(r#""output":"6 0 11 end\n""#));
     }
 }